@@ -0,0 +1,825 @@
+use super::{constant_time_eq, ApiError, ApiState, ListingsParams};
+use crate::config::MapConfig;
+use crate::database::{ListingQuery, ListingRecord, ListingSort, ListingStatus, StatusSource};
+use crate::lifecycle::{self, ListingAction, ListingButton};
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Form, Path, Query, RawQuery, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use hmac::{Hmac, KeyInit, Mac};
+use minijinja::value::Serde;
+use minijinja::{context, Environment};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+const SESSION_COOKIE: &str = "colocfinder_session";
+/// The session lasts 30 days
+const SESSION_MAX_AGE_SECONDS: u64 = 30 * 24 * 3600;
+const PAGE_SIZE: usize = 100;
+/// Listings placed on the map at most, the newest ones
+const MAX_MAP_LISTINGS: usize = 2000;
+/// Listings shown side by side at most
+const MAX_COMPARED: usize = 6;
+const MAX_NOTE_LENGTH: usize = 1000;
+
+/// Pages of the dashboard, escaped as HTML since their names end in .html
+static TEMPLATES: LazyLock<Environment<'static>> = LazyLock::new(|| {
+    let mut templates = Environment::new();
+    for (name, source) in [
+        ("base.html", include_str!("templates/base.html")),
+        ("login.html", include_str!("templates/login.html")),
+        ("listings.html", include_str!("templates/listings.html")),
+        ("listing.html", include_str!("templates/listing.html")),
+        ("compare.html", include_str!("templates/compare.html")),
+        ("map.html", include_str!("templates/map.html")),
+        ("error.html", include_str!("templates/error.html")),
+    ] {
+        templates.add_template(name, source).expect("invalid dashboard template");
+    }
+    templates
+});
+
+/// An error shown as a page
+struct PageError(ApiError);
+
+impl From<ApiError> for PageError {
+    fn from(e: ApiError) -> Self {
+        Self(e)
+    }
+}
+
+impl From<anyhow::Error> for PageError {
+    fn from(e: anyhow::Error) -> Self {
+        Self(e.into())
+    }
+}
+
+impl From<minijinja::Error> for PageError {
+    fn from(e: minijinja::Error) -> Self {
+        Self(anyhow::Error::from(e).into())
+    }
+}
+
+impl IntoResponse for PageError {
+    fn into_response(self) -> Response {
+        let ApiError { status, message } = self.0;
+        match render("error.html", context! { status => status.as_u16(), message => &message }) {
+            Ok(page) => (status, page).into_response(),
+            Err(_) => (status, message).into_response(),
+        }
+    }
+}
+
+fn render(name: &str, context: minijinja::Value) -> Result<Html<String>, minijinja::Error> {
+    Ok(Html(TEMPLATES.get_template(name)?.render(context)?))
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Post-visit rating, e.g. "⭐⭐⭐"
+fn stars(rating: u8) -> String {
+    "⭐".repeat(rating as usize)
+}
+
+/// A status change offered as a button, as on Discord
+#[derive(Debug, Serialize)]
+struct ActionView {
+    status: &'static str,
+    label: &'static str,
+}
+
+/// The status changes of the Discord buttons of both channels (with voting disabled)
+fn triage_actions(status: &ListingStatus) -> Vec<ActionView> {
+    let mut actions: Vec<ListingAction> = Vec::new();
+    for button in lifecycle::main_channel_buttons(status, false).into_iter().chain(lifecycle::interesting_channel_buttons(status)) {
+        if let ListingButton::Action(action) = button {
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+    }
+    actions.into_iter()
+        .map(|action| ActionView { status: action.target().as_str(), label: action.label() })
+        .collect()
+}
+
+/// A listing as displayed, with its values already formatted
+#[derive(Debug, Serialize)]
+struct ListingView {
+    uuid: Uuid,
+    title: String,
+    price: Option<String>,
+    surface: Option<String>,
+    price_per_m2: Option<String>,
+    location: String,
+    url: String,
+    image_url: Option<String>,
+    description: Option<String>,
+    posted_at: String,
+    source: String,
+    status: &'static str,
+    status_label: &'static str,
+    status_emoji: &'static str,
+    status_colour: String,
+    actions: Vec<ActionView>,
+}
+
+impl From<&ListingRecord> for ListingView {
+    fn from(record: &ListingRecord) -> Self {
+        let (r, g, b) = lifecycle::colour(&record.status);
+        Self {
+            uuid: record.uuid,
+            title: record.title.clone(),
+            price: record.price.map(|price| format!("{} €", format_number(price))),
+            surface: record.surface.map(|surface| format!("{} m²", format_number(surface))),
+            price_per_m2: record.price_per_m2().map(|price| format!("{:.1} €/m²", price)),
+            location: record.location.clone(),
+            url: record.url.clone(),
+            image_url: record.image_url.clone(),
+            description: record.description.clone(),
+            posted_at: record.posted_at.format("%d/%m/%Y %H:%M").to_string(),
+            source: record.source.clone(),
+            status: record.status.as_str(),
+            status_label: lifecycle::label(&record.status),
+            status_emoji: lifecycle::emoji(&record.status),
+            status_colour: format!("#{:02x}{:02x}{:02x}", r, g, b),
+            actions: triage_actions(&record.status),
+        }
+    }
+}
+
+/// Query string of the listing pages, without the empty values
+fn query_string(params: &ListingsParams, view: &str) -> String {
+    let mut pairs = vec![format!("view={}", view)];
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(params) {
+        for (name, value) in fields {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            pairs.push(format!("{}={}", name, urlencoding::encode(&value)));
+        }
+    }
+    format!("/?{}", pairs.join("&"))
+}
+
+/// A sortable column header
+#[derive(Debug, Serialize)]
+struct ColumnView {
+    label: &'static str,
+    url: String,
+    /// "▲" or "▼" on the column sorted by
+    indicator: &'static str,
+}
+
+fn sort_label(sort: ListingSort) -> &'static str {
+    match sort {
+        ListingSort::PostedAt => "Publiée",
+        ListingSort::Price => "Loyer",
+        ListingSort::Surface => "Surface",
+        ListingSort::PricePerM2 => "€/m²",
+        ListingSort::Location => "Ville",
+        ListingSort::Status => "Statut",
+    }
+}
+
+/// Clicking the column sorted by reverses the order, other columns start with the highest values
+fn columns(params: &ListingsParams, current: ListingSort, ascending: bool, view: &str) -> Vec<ColumnView> {
+    ListingSort::ALL.into_iter()
+        .map(|sort| {
+            let mut params = ListingsParams { sort: Some(sort.as_str().to_owned()), offset: None, ..params.clone() };
+            let (order, indicator) = match (sort == current, ascending) {
+                (true, false) => ("asc", "▼"),
+                (true, true) => ("desc", "▲"),
+                (false, _) => ("desc", ""),
+            };
+            params.order = Some(order.to_string());
+            ColumnView { label: sort_label(sort), url: query_string(&params, view), indicator }
+        })
+        .collect()
+}
+
+/// Query string of the listing pages
+#[derive(Debug, Default, Deserialize)]
+struct PageParams {
+    #[serde(flatten)]
+    filters: ListingsParams,
+    /// "table" (the default) or "gallery"
+    view: Option<String>,
+}
+
+async fn listings_page(
+    State(state): State<ApiState>,
+    params: Result<Query<PageParams>, QueryRejection>,
+) -> Result<Html<String>, PageError> {
+    let Query(params) = params.map_err(|rejection| ApiError::new(StatusCode::BAD_REQUEST, rejection.body_text()))?;
+    let view = if params.view.as_deref() == Some("gallery") { "gallery" } else { "table" };
+    let filters = ListingsParams { limit: Some(PAGE_SIZE), ..params.filters };
+    let query = filters.to_query()?;
+    let (records, total) = state.database.lock().await.query_listings(&query)?;
+
+    let page_url = |offset: usize| query_string(&ListingsParams { offset: Some(offset), ..filters.clone() }, view);
+    let previous_url = (query.offset > 0).then(|| page_url(query.offset.saturating_sub(PAGE_SIZE)));
+    let next_url = (query.offset + records.len() < total).then(|| page_url(query.offset + PAGE_SIZE));
+
+    let statuses: Vec<_> = ListingStatus::ALL.iter()
+        .map(|status| context! { value => status.as_str(), label => lifecycle::label(status) })
+        .collect();
+    Ok(render("listings.html", context! {
+        view,
+        filters => Serde(&filters),
+        statuses,
+        total,
+        first => query.offset + 1,
+        last => query.offset + records.len(),
+        previous_url,
+        next_url,
+        columns => Serde(columns(&filters, query.sort, query.ascending, view)),
+        table_url => query_string(&filters, "table"),
+        gallery_url => query_string(&filters, "gallery"),
+        listings => Serde(records.iter().map(ListingView::from).collect::<Vec<_>>()),
+        current_url => query_string(&ListingsParams { offset: Some(query.offset), ..filters.clone() }, view),
+    })?)
+}
+
+async fn listing_page(State(state): State<ApiState>, Path(uuid): Path<Uuid>) -> Result<Html<String>, PageError> {
+    let db = state.database.lock().await;
+    let record = db.get_listing_by_uuid(&uuid)?.ok_or_else(|| ApiError::not_found(&uuid))?;
+
+    let history: Vec<_> = db.get_status_events(&uuid)?.into_iter().rev()
+        .map(|event| context! {
+            date => event.created_at.format("%d/%m/%Y %H:%M").to_string(),
+            old_status => event.old_status.as_ref().map(lifecycle::label),
+            new_status => lifecycle::label(&event.new_status),
+            source => event.source.as_str(),
+        })
+        .collect();
+    let notes: Vec<_> = db.get_notes(&uuid)?.into_iter()
+        .map(|note| context! {
+            author => note.author,
+            content => note.content,
+            date => note.created_at.format("%d/%m/%Y %H:%M").to_string(),
+        })
+        .collect();
+    let votes = db.get_vote_tally(&uuid)?;
+    let visit = db.get_latest_visit(&uuid)?.map(|visit| context! {
+        date => visit.scheduled_at.format("%d/%m/%Y %H:%M").to_string(),
+        rating => visit.rating.map(stars),
+    });
+
+    Ok(render("listing.html", context! {
+        listing => Serde(ListingView::from(&record)),
+        history,
+        notes,
+        votes => context! { yes => votes.yes.len(), no => votes.no.len(), maybe => votes.maybe.len() },
+        visit,
+        current_url => format!("/listings/{}", uuid),
+    })?)
+}
+
+/// Where to go back after a form, only paths of the dashboard itself are followed
+fn back_to(back: Option<&str>, fallback: String) -> Redirect {
+    match back {
+        Some(path) if is_local_path(path) => Redirect::to(path),
+        _ => Redirect::to(&fallback),
+    }
+}
+
+/// Whether a path has no authority, so that browsers can't take it for another site
+/// They read `\` as `/` and drop tabs and newlines, so `/\evil.example` and `/\t/evil.example` are refused too
+fn is_local_path(path: &str) -> bool {
+    path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.chars().any(|c| c == '\\' || c.is_control())
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusForm {
+    status: String,
+    back: Option<String>,
+}
+
+/// Same lifecycle checks and notifier updates as the Discord buttons, so the embeds follow
+async fn change_status(
+    State(state): State<ApiState>,
+    Path(uuid): Path<Uuid>,
+    Form(form): Form<StatusForm>,
+) -> Result<Redirect, PageError> {
+    let status = super::parse_status(&form.status)?;
+    if state.database.lock().await.get_listing_by_uuid(&uuid)?.is_none() {
+        return Err(ApiError::not_found(&uuid).into());
+    }
+
+    state.notifiers.change_status(&state.database, &uuid, status, None, StatusSource::Dashboard).await?;
+    Ok(back_to(form.back.as_deref(), format!("/listings/{}", uuid)))
+}
+
+#[derive(Debug, Deserialize)]
+struct NoteForm {
+    author: String,
+    content: String,
+}
+
+async fn add_note(
+    State(state): State<ApiState>,
+    Path(uuid): Path<Uuid>,
+    Form(form): Form<NoteForm>,
+) -> Result<Redirect, PageError> {
+    let (author, content) = (form.author.trim(), form.content.trim());
+    if author.is_empty() || content.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "a note needs an author and a content").into());
+    }
+    if content.chars().count() > MAX_NOTE_LENGTH {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("notes are limited to {} characters", MAX_NOTE_LENGTH)).into());
+    }
+
+    let record = {
+        let db = state.database.lock().await;
+        let record = db.get_listing_by_uuid(&uuid)?.ok_or_else(|| ApiError::not_found(&uuid))?;
+        // Web visitors have no Discord account
+        db.add_note(&uuid, 0, author, content)?;
+        record
+    };
+    // The latest notes are shown in the Discord embeds
+    state.notifiers.update_listing(&record).await;
+    Ok(Redirect::to(&format!("/listings/{}#notes", uuid)))
+}
+
+/// A value of a row of the comparison, flagged when it is the best of the compared listings
+#[derive(Debug, Serialize)]
+struct CellView {
+    value: Option<String>,
+    best: bool,
+}
+
+/// One cell per listing, the lowest (or highest) value being flagged as the best
+fn compared_row<F>(records: &[ListingRecord], value: F, format: fn(f64) -> String, lowest_is_best: bool) -> Vec<CellView>
+where
+    F: Fn(&ListingRecord) -> Option<f64>,
+{
+    let values: Vec<Option<f64>> = records.iter().map(&value).collect();
+    let best = values.iter().flatten().copied().reduce(|a, b| if (a < b) == lowest_is_best { a } else { b });
+    values.into_iter()
+        .map(|value| CellView {
+            value: value.map(format),
+            best: records.len() > 1 && value.is_some() && value == best,
+        })
+        .collect()
+}
+
+async fn compare_page(State(state): State<ApiState>, RawQuery(query): RawQuery) -> Result<Html<String>, PageError> {
+    // Checkboxes repeat the key: ?uuid=...&uuid=...
+    let mut uuids: Vec<Uuid> = Vec::new();
+    for pair in query.unwrap_or_default().split('&') {
+        if let Some(value) = pair.strip_prefix("uuid=") {
+            let uuid = Uuid::parse_str(value)
+                .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, format!("invalid listing uuid \"{}\"", value)))?;
+            if !uuids.contains(&uuid) {
+                uuids.push(uuid);
+            }
+        }
+    }
+    if uuids.len() < 2 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "select at least two listings to compare").into());
+    }
+    uuids.truncate(MAX_COMPARED);
+
+    let db = state.database.lock().await;
+    let mut records = Vec::new();
+    let mut extras = Vec::new();
+    for uuid in &uuids {
+        let record = db.get_listing_by_uuid(uuid)?.ok_or_else(|| ApiError::not_found(uuid))?;
+        let votes = db.get_vote_tally(uuid)?;
+        extras.push(context! {
+            votes => format!("👍 {} · 👎 {} · 🤷 {}", votes.yes.len(), votes.no.len(), votes.maybe.len()),
+            notes => db.get_notes(uuid)?.len(),
+            rating => db.get_latest_visit(uuid)?.and_then(|visit| visit.rating).map(stars),
+        });
+        records.push(record);
+    }
+
+    Ok(render("compare.html", context! {
+        price => Serde(compared_row(&records, |record| record.price, |price| format!("{} €", format_number(price)), true)),
+        surface => Serde(compared_row(&records, |record| record.surface, |surface| format!("{} m²", format_number(surface)), false)),
+        price_per_m2 => Serde(compared_row(&records, ListingRecord::price_per_m2, |price| format!("{:.1} €/m²", price), true)),
+        listings => Serde(records.iter().map(ListingView::from).collect::<Vec<_>>()),
+        extras,
+    })?)
+}
+
+/// Commute from a listing to a point of interest, shown in the popups and used by the filters
+#[derive(Debug, Serialize)]
+struct CommuteView {
+    distance_km: f64,
+    minutes: f64,
+}
+
+/// A listing placed on the map
+#[derive(Debug, Serialize)]
+struct MarkerView {
+    latitude: f64,
+    longitude: f64,
+    price_value: Option<f64>,
+    surface_value: Option<f64>,
+    /// One per point of interest, in the configured order
+    commutes: Vec<CommuteView>,
+    #[serde(flatten)]
+    listing: ListingView,
+}
+
+#[derive(Debug, Serialize)]
+struct PointView {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    mode: &'static str,
+    /// Radius of each commute circle
+    circles: Vec<CircleView>,
+}
+
+#[derive(Debug, Serialize)]
+struct CircleView {
+    minutes: u32,
+    radius_m: f64,
+}
+
+fn map_points(map: &MapConfig) -> Vec<PointView> {
+    map.points_of_interest.iter()
+        .map(|point| PointView {
+            name: point.name.clone(),
+            latitude: point.latitude,
+            longitude: point.longitude,
+            mode: point.mode.label(),
+            circles: point.commute_minutes.iter()
+                .map(|&minutes| CircleView { minutes, radius_m: point.mode.speed_kmh() * 1000.0 * minutes as f64 / 60.0 })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Commute circles are as the crow flies, so they only give an idea of the real commute
+async fn map_page(State(state): State<ApiState>) -> Result<Html<String>, PageError> {
+    let query = ListingQuery { limit: MAX_MAP_LISTINGS, ..Default::default() };
+    let (records, _) = state.database.lock().await.query_listings(&query)?;
+
+    let (located, unlocated): (Vec<_>, Vec<_>) = records.iter().partition(|record| record.coordinates.is_some());
+    let markers: Vec<MarkerView> = located.into_iter()
+        .filter_map(|record| {
+            let coordinates = record.coordinates?;
+            Some(MarkerView {
+                latitude: coordinates.latitude,
+                longitude: coordinates.longitude,
+                price_value: record.price,
+                surface_value: record.surface,
+                commutes: state.map.points_of_interest.iter()
+                    .map(|point| CommuteView {
+                        distance_km: coordinates.distance_km(&point.coordinates()),
+                        minutes: point.commute_minutes_from(&coordinates),
+                    })
+                    .collect(),
+                listing: ListingView::from(record),
+            })
+        })
+        .collect();
+
+    let statuses: Vec<_> = ListingStatus::ALL.iter()
+        .map(|status| {
+            let (r, g, b) = lifecycle::colour(status);
+            context! {
+                value => status.as_str(),
+                label => lifecycle::label(status),
+                colour => format!("#{:02x}{:02x}{:02x}", r, g, b),
+                // Listings ruled out are hidden until asked for
+                checked => lifecycle::is_open(status),
+            }
+        })
+        .collect();
+
+    Ok(render("map.html", context! {
+        markers => Serde(markers),
+        points => Serde(map_points(&state.map)),
+        statuses,
+        unlocated => unlocated.len(),
+    })?)
+}
+
+/// Session cookie value: a HMAC of the API token, so that a stolen cookie can't be used as a bearer token
+/// Changing the token signs every browser out
+fn session_id(token: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(token.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"colocfinder dashboard session");
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+}
+
+/// Whether the dashboard is reached on this machine, where plain HTTP is expected
+fn is_local_host(headers: &HeaderMap) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    // Drop the port, after the brackets of IPv6 addresses
+    let name = match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => &host[..colon],
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Pages need the session cookie set by the login form, visitors without it are sent there
+async fn require_session(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let authorized = session_cookie(request.headers())
+        .is_some_and(|session| constant_time_eq(session.as_bytes(), session_id(&state.token).as_bytes()));
+
+    if authorized {
+        next.run(request).await
+    } else {
+        Redirect::to("/login").into_response()
+    }
+}
+
+async fn login_page() -> Result<Html<String>, PageError> {
+    Ok(render("login.html", context! { failed => false })?)
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginForm {
+    token: String,
+}
+
+async fn login(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, PageError> {
+    if !constant_time_eq(form.token.as_bytes(), state.token.as_bytes()) {
+        return Ok((StatusCode::UNAUTHORIZED, render("login.html", context! { failed => true })?).into_response());
+    }
+    // SameSite=Strict keeps other websites from posting the triage forms
+    let mut cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        SESSION_COOKIE, session_id(&state.token), SESSION_MAX_AGE_SECONDS
+    );
+    // Anywhere else the dashboard is expected behind HTTPS
+    if !is_local_host(&headers) {
+        cookie.push_str("; Secure");
+    }
+    Ok(([(header::SET_COOKIE, cookie)], Redirect::to("/")).into_response())
+}
+
+async fn logout() -> impl IntoResponse {
+    let cookie = format!("{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Strict", SESSION_COOKIE);
+    ([(header::SET_COOKIE, cookie)], Redirect::to("/login"))
+}
+
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/", get(listings_page))
+        .route("/listings/{uuid}", get(listing_page))
+        .route("/listings/{uuid}/status", post(change_status))
+        .route("/listings/{uuid}/notes", post(add_note))
+        .route("/compare", get(compare_page))
+        .route("/map", get(map_page))
+        .route("/logout", post(logout))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_session))
+        .route("/login", get(login_page).post(login))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::models::{Coordinates, Listing};
+    use crate::notifier_trait::NotifierRegistry;
+    use crate::scraper_trait::ScraperRegistry;
+    use std::sync::Arc;
+    use tokio::sync::{Mutex, Notify};
+
+    fn cookie() -> String {
+        format!("{}={}", SESSION_COOKIE, session_id("s3cret"))
+    }
+
+    fn listing(id: &str, price: f64, surface: f64) -> Listing {
+        Listing {
+            title: format!("T3 <{}>", id),
+            surface: Some(surface),
+            image_url: Some(format!("https://img.example.com/{}.jpg", id)),
+            ..Listing::test(id, price)
+        }
+    }
+
+    async fn start() -> (String, reqwest::Client, ApiState) {
+        start_with_map(MapConfig::default()).await
+    }
+
+    async fn start_with_map(map: MapConfig) -> (String, reqwest::Client, ApiState) {
+        let state = ApiState {
+            database: Arc::new(Mutex::new(Database::new(":memory:").unwrap())),
+            notifiers: Arc::new(NotifierRegistry::new()),
+            scrapers: Arc::new(ScraperRegistry::new()),
+            scrape_now: Arc::new(Notify::new()),
+            paused: Arc::new(Mutex::new(false)),
+            token: "s3cret".to_string(),
+            map,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = super::super::router(state.clone(), true);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+        (url, client, state)
+    }
+
+    async fn get(client: &reqwest::Client, url: String) -> (u16, String) {
+        let response = client.get(url).header("cookie", cookie()).send().await.unwrap();
+        (response.status().as_u16(), response.text().await.unwrap())
+    }
+
+    async fn post_form(client: &reqwest::Client, url: String, form: &str) -> reqwest::Response {
+        client.post(url)
+            .header("cookie", cookie())
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(form.to_string())
+            .send().await.unwrap()
+    }
+
+    #[test]
+    fn test_triage_actions_follow_the_discord_buttons() {
+        let statuses = |status| triage_actions(&status).into_iter().map(|action| action.status).collect::<Vec<_>>();
+        assert_eq!(statuses(ListingStatus::Unchecked), vec!["interesting", "not_good"]);
+        assert_eq!(statuses(ListingStatus::Interesting), vec!["not_good", "unchecked", "contacted"]);
+        assert_eq!(statuses(ListingStatus::Applied), vec!["accepted", "rejected"]);
+        assert!(statuses(ListingStatus::Expired).is_empty());
+    }
+
+    #[test]
+    fn test_only_local_paths_are_followed_back() {
+        assert!(is_local_path("/listings/1?view=table"));
+        for path in ["https://evil.example", "//evil.example", "/\\evil.example", "/\t/evil.example", "listings"] {
+            assert!(!is_local_path(path), "{:?} was followed", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        let (url, client, _) = start().await;
+
+        let response = client.get(format!("{}/", url)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers()["location"], "/login");
+
+        let login = |token: &str| client.post(format!("{}/login", url))
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(format!("token={}", token))
+            .send();
+        assert_eq!(login("wrong").await.unwrap().status().as_u16(), 401);
+
+        let response = login("s3cret").await.unwrap();
+        assert_eq!(response.status().as_u16(), 303);
+        let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+        assert!(set_cookie.starts_with(&format!("{};", cookie())));
+        assert!(!set_cookie.contains("s3cret"));
+        assert!(set_cookie.contains("HttpOnly; SameSite=Strict"));
+        assert!(!set_cookie.contains("Secure"));
+
+        // The cookie is no bearer token for the REST API
+        let response = client.get(format!("{}/api/listings", url))
+            .header("authorization", format!("Bearer {}", session_id("s3cret")))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    #[test]
+    fn test_secure_cookie_outside_localhost() {
+        let headers = |host: &str| HeaderMap::from_iter([(header::HOST, host.parse().unwrap())]);
+        assert!(is_local_host(&headers("127.0.0.1:8080")));
+        assert!(is_local_host(&headers("localhost")));
+        assert!(is_local_host(&headers("[::1]:8080")));
+        assert!(!is_local_host(&headers("coloc.example.org")));
+        assert!(!is_local_host(&headers("localhost.example.org:8080")));
+        assert!(!is_local_host(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_listings_page() {
+        let (url, client, state) = start().await;
+        {
+            let db = state.database.lock().await;
+            db.upsert_listing(&listing("cheap", 600.0, 30.0)).unwrap();
+            db.upsert_listing(&listing("dear", 950.0, 60.0)).unwrap();
+        }
+
+        // Empty fields as sent by the filter form
+        let (status, page) = get(&client, format!("{}/?view=table&status=&city=rennes&min_price=&max_price=800&min_surface=&sort=price&order=asc", url)).await;
+        assert_eq!(status, 200);
+        assert!(page.contains("T3 &lt;cheap&gt;"));
+        assert!(!page.contains("dear"));
+        assert!(page.contains("1–1 sur 1 annonce(s)"));
+        assert!(page.contains("Loyer ▲"));
+        assert!(page.contains(">Intéressant</button>"));
+
+        let (_, page) = get(&client, format!("{}/?view=gallery", url)).await;
+        assert!(page.contains("class=\"gallery\""));
+        // minijinja escapes the slashes too
+        assert!(page.contains("https:&#x2f;&#x2f;img.example.com&#x2f;dear.jpg"));
+
+        let (status, page) = get(&client, format!("{}/?max_price=abc", url)).await;
+        assert_eq!(status, 400);
+        assert!(page.contains("Erreur 400"));
+    }
+
+    #[tokio::test]
+    async fn test_triage_and_notes_go_through_the_database() {
+        let (url, client, state) = start().await;
+        let uuid = state.database.lock().await.upsert_listing(&listing("flat", 700.0, 40.0)).unwrap().uuid;
+
+        let response = post_form(&client, format!("{}/listings/{}/status", url, uuid), "status=interesting&back=%2F%3Fview%3Dgallery").await;
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers()["location"], "/?view=gallery");
+
+        let response = post_form(&client, format!("{}/listings/{}/status", url, uuid), "status=accepted").await;
+        assert_eq!(response.status().as_u16(), 409);
+
+        let response = post_form(&client, format!("{}/listings/{}/notes", url, uuid), "author=Alice&content=Tr%C3%A8s+lumineux").await;
+        assert_eq!(response.status().as_u16(), 303);
+
+        {
+            let db = state.database.lock().await;
+            assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Interesting);
+            assert_eq!(db.get_status_events(&uuid).unwrap()[0].source, StatusSource::Dashboard);
+            let notes = db.get_notes(&uuid).unwrap();
+            assert_eq!((notes[0].author.as_str(), notes[0].content.as_str()), ("Alice", "Très lumineux"));
+        }
+
+        let (status, page) = get(&client, format!("{}/listings/{}", url, uuid)).await;
+        assert_eq!(status, 200);
+        assert!(page.contains("Très lumineux"));
+        assert!(page.contains("Non vérifiée → Intéressante"));
+    }
+
+    #[tokio::test]
+    async fn test_compare_page_highlights_the_best_values() {
+        let (url, client, state) = start().await;
+        let (cheap, big) = {
+            let db = state.database.lock().await;
+            (db.upsert_listing(&listing("cheap", 600.0, 30.0)).unwrap().uuid, db.upsert_listing(&listing("big", 800.0, 50.0)).unwrap().uuid)
+        };
+
+        let (status, page) = get(&client, format!("{}/compare?uuid={}&uuid={}", url, cheap, big)).await;
+        assert_eq!(status, 200);
+        assert!(page.contains("<td class=\"number best\">600 €</td>"));
+        assert!(page.contains("<td class=\"number\">800 €</td>"));
+        assert!(page.contains("<td class=\"number best\">50 m²</td>"));
+        assert!(page.contains("<td class=\"number best\">16.0 €&#x2f;m²</td>"));
+
+        let (status, _) = get(&client, format!("{}/compare?uuid={}", url, cheap)).await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_map_page() {
+        let map: MapConfig = serde_yaml::from_str(
+            "points_of_interest:\n  - name: Université\n    latitude: 48.1185\n    longitude: -1.6374\n    mode: bike\n    commute_minutes: [10]\n"
+        ).unwrap();
+        let points = map_points(&map);
+        assert_eq!(points[0].mode, "à vélo");
+        assert!((points[0].circles[0].radius_m - 2333.3).abs() < 0.1);
+
+        let (url, client, state) = start_with_map(map).await;
+        {
+            let db = state.database.lock().await;
+            let located = Listing { coordinates: Some(Coordinates { latitude: 48.1173, longitude: -1.6778 }), ..listing("located", 700.0, 40.0) };
+            db.upsert_listing(&located).unwrap();
+            db.upsert_listing(&listing("unlocated", 650.0, 35.0)).unwrap();
+        }
+
+        let (status, page) = get(&client, format!("{}/map", url)).await;
+        assert_eq!(status, 200);
+        assert!(page.contains("leaflet.js"));
+        assert!(page.contains("\"latitude\": 48.1173"));
+        // About 3 km, so 13 minutes by bike
+        assert!(page.contains("\"commutes\": [{\"distance_km\": 3.00"));
+        assert!(page.contains("\"minutes\": 12.86"));
+        assert!(page.contains("\"radius_m\": 2333.3"));
+        assert!(page.contains("1 sans coordonnées"));
+        assert!(!page.contains("unlocated"));
+    }
+}
@@ -0,0 +1,563 @@
+use crate::config::{ApiConfig, MapConfig};
+use crate::database::{Database, ListingNote, ListingQuery, ListingRecord, ListingSort, ListingStatus, StatusEvent, StatusSource};
+use crate::lifecycle::{self, InvalidTransition};
+use crate::models::Coordinates;
+use crate::notifier_trait::NotifierRegistry;
+use crate::scraper_trait::{ScraperHealth, ScraperRegistry};
+use crate::visits::Visit;
+use crate::votes::VoteTally;
+use anyhow::Result;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+mod dashboard;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// What the handlers share with the rest of the bot
+#[derive(Clone)]
+pub struct ApiState {
+    pub database: Arc<Mutex<Database>>,
+    pub notifiers: Arc<NotifierRegistry>,
+    pub scrapers: Arc<ScraperRegistry>,
+    /// Wakes the scraping task up for an extra cycle
+    pub scrape_now: Arc<Notify>,
+    /// Set by /pause and /resume
+    pub paused: Arc<Mutex<bool>>,
+    pub token: String,
+    /// Points of interest of the dashboard map
+    pub map: MapConfig,
+}
+
+/// An error answered as `{"error": "..."}`
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn not_found(uuid: &Uuid) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("no listing {}", uuid))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        if let Some(invalid) = e.downcast_ref::<InvalidTransition>() {
+            return Self::new(StatusCode::CONFLICT, invalid.to_string());
+        }
+        tracing::error!("API request failed: {}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(serde_json::json!({ "error": self.message }))).into_response()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListingJson {
+    pub uuid: Uuid,
+    pub listing_id: String,
+    pub title: String,
+    pub price: Option<f64>,
+    pub surface: Option<f64>,
+    pub price_per_m2: Option<f64>,
+    pub location: String,
+    pub url: String,
+    pub image_url: Option<String>,
+    pub description: Option<String>,
+    pub posted_at: DateTime<Utc>,
+    pub scraped_at: DateTime<Utc>,
+    pub source: String,
+    pub status: String,
+    pub status_label: String,
+    pub coordinates: Option<Coordinates>,
+}
+
+impl From<&ListingRecord> for ListingJson {
+    fn from(record: &ListingRecord) -> Self {
+        Self {
+            uuid: record.uuid,
+            listing_id: record.listing_id.clone(),
+            title: record.title.clone(),
+            price: record.price,
+            surface: record.surface,
+            price_per_m2: record.price_per_m2(),
+            location: record.location.clone(),
+            url: record.url.clone(),
+            image_url: record.image_url.clone(),
+            description: record.description.clone(),
+            posted_at: record.posted_at,
+            scraped_at: record.scraped_at,
+            source: record.source.clone(),
+            status: record.status.as_str().to_owned(),
+            status_label: lifecycle::label(&record.status).to_string(),
+            coordinates: record.coordinates,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatusEventJson {
+    old_status: Option<String>,
+    new_status: String,
+    user_id: Option<u64>,
+    source: &'static str,
+    created_at: DateTime<Utc>,
+}
+
+impl From<&StatusEvent> for StatusEventJson {
+    fn from(event: &StatusEvent) -> Self {
+        Self {
+            old_status: event.old_status.as_ref().map(|status| status.as_str().to_owned()),
+            new_status: event.new_status.as_str().to_owned(),
+            user_id: event.user_id,
+            source: event.source.as_str(),
+            created_at: event.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NoteJson {
+    user_id: u64,
+    author: String,
+    content: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<&ListingNote> for NoteJson {
+    fn from(note: &ListingNote) -> Self {
+        Self {
+            user_id: note.user_id,
+            author: note.author.clone(),
+            content: note.content.clone(),
+            created_at: note.created_at,
+        }
+    }
+}
+
+/// Discord user ids of the members who voted each way
+#[derive(Debug, Serialize)]
+struct VotesJson {
+    yes: Vec<u64>,
+    no: Vec<u64>,
+    maybe: Vec<u64>,
+}
+
+impl From<VoteTally> for VotesJson {
+    fn from(tally: VoteTally) -> Self {
+        Self { yes: tally.yes, no: tally.no, maybe: tally.maybe }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct VisitJson {
+    scheduled_at: DateTime<Utc>,
+    attendees: Vec<u64>,
+    rating: Option<u8>,
+}
+
+impl From<Visit> for VisitJson {
+    fn from(visit: Visit) -> Self {
+        Self { scheduled_at: visit.scheduled_at, attendees: visit.attendees, rating: visit.rating }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ListingDetailJson {
+    #[serde(flatten)]
+    listing: ListingJson,
+    history: Vec<StatusEventJson>,
+    notes: Vec<NoteJson>,
+    votes: VotesJson,
+    latest_visit: Option<VisitJson>,
+}
+
+#[derive(Debug, Serialize)]
+struct ScraperJson {
+    name: String,
+    enabled: bool,
+    last_run: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_listing_count: usize,
+    consecutive_failures: u32,
+}
+
+impl ScraperJson {
+    fn new(name: String, enabled: bool, health: ScraperHealth) -> Self {
+        Self {
+            name,
+            enabled,
+            last_run: health.last_run,
+            last_success: health.last_success,
+            last_error: health.last_error,
+            last_listing_count: health.last_listing_count,
+            consecutive_failures: health.consecutive_failures,
+        }
+    }
+}
+
+/// Parse a query string value, taking empty ones as missing
+/// HTML forms send their empty fields, e.g. "?city=&max_price=800"
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => value.trim().parse().map(Some).map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Query string of `GET /api/listings`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListingsParams {
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<f64>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<f64>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub min_surface: Option<f64>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub posted_after: Option<DateTime<Utc>>,
+    /// One of the `ListingSort` names, posted_at by default
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// "asc" or "desc" (the default)
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+impl ListingsParams {
+    pub fn to_query(&self) -> Result<ListingQuery, ApiError> {
+        let status = match &self.status {
+            Some(name) => Some(parse_status(name)?),
+            None => None,
+        };
+        let sort = match &self.sort {
+            Some(name) => ListingSort::parse(name)
+                .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("unknown sort \"{}\"", name)))?,
+            None => ListingSort::default(),
+        };
+        Ok(ListingQuery {
+            status,
+            location: self.city.clone(),
+            min_price: self.min_price,
+            max_price: self.max_price,
+            min_surface: self.min_surface,
+            posted_after: self.posted_after,
+            sort,
+            ascending: self.order.as_deref() == Some("asc"),
+            limit: self.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE),
+            offset: self.offset.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ListingPage {
+    total: usize,
+    limit: usize,
+    offset: usize,
+    listings: Vec<ListingJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusChange {
+    status: String,
+}
+
+fn parse_status(name: &str) -> Result<ListingStatus, ApiError> {
+    ListingStatus::parse(name).ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, format!("unknown status \"{}\"", name)))
+}
+
+/// Compare without stopping at the first difference, so that response times don't leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let authorized = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), state.token.as_bytes()));
+
+    if authorized {
+        next.run(request).await
+    } else {
+        ApiError::new(StatusCode::UNAUTHORIZED, "missing or invalid bearer token").into_response()
+    }
+}
+
+async fn list_listings(State(state): State<ApiState>, Query(params): Query<ListingsParams>) -> Result<Json<ListingPage>, ApiError> {
+    let query = params.to_query()?;
+    let (records, total) = state.database.lock().await.query_listings(&query)?;
+    Ok(Json(ListingPage {
+        total,
+        limit: query.limit,
+        offset: query.offset,
+        listings: records.iter().map(ListingJson::from).collect(),
+    }))
+}
+
+async fn get_listing(State(state): State<ApiState>, Path(uuid): Path<Uuid>) -> Result<Json<ListingDetailJson>, ApiError> {
+    let db = state.database.lock().await;
+    let record = db.get_listing_by_uuid(&uuid)?.ok_or_else(|| ApiError::not_found(&uuid))?;
+    Ok(Json(ListingDetailJson {
+        listing: ListingJson::from(&record),
+        history: db.get_status_events(&uuid)?.iter().map(StatusEventJson::from).collect(),
+        notes: db.get_notes(&uuid)?.iter().map(NoteJson::from).collect(),
+        votes: db.get_vote_tally(&uuid)?.into(),
+        latest_visit: db.get_latest_visit(&uuid)?.map(VisitJson::from),
+    }))
+}
+
+/// Goes through the same lifecycle checks and notifier updates as the Discord buttons
+async fn change_status(
+    State(state): State<ApiState>,
+    Path(uuid): Path<Uuid>,
+    Json(change): Json<StatusChange>,
+) -> Result<Json<ListingJson>, ApiError> {
+    let status = parse_status(&change.status)?;
+    if state.database.lock().await.get_listing_by_uuid(&uuid)?.is_none() {
+        return Err(ApiError::not_found(&uuid));
+    }
+
+    let record = state.notifiers.change_status(&state.database, &uuid, status, None, StatusSource::Api).await?;
+    Ok(Json(ListingJson::from(&record)))
+}
+
+async fn trigger_scrape(State(state): State<ApiState>) -> Result<impl IntoResponse, ApiError> {
+    if *state.paused.lock().await {
+        return Err(ApiError::new(StatusCode::CONFLICT, "the bot is paused"));
+    }
+    state.scrape_now.notify_one();
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "queued": true }))))
+}
+
+async fn scrapers(State(state): State<ApiState>) -> Json<Vec<ScraperJson>> {
+    Json(state.scrapers.health()
+        .into_iter()
+        .map(|(name, enabled, health)| ScraperJson::new(name, enabled, health))
+        .collect())
+}
+
+pub fn router(state: ApiState, with_dashboard: bool) -> Router {
+    let api = Router::new()
+        .route("/api/listings", get(list_listings))
+        .route("/api/listings/{uuid}", get(get_listing))
+        .route("/api/listings/{uuid}/status", post(change_status))
+        .route("/api/scrape", post(trigger_scrape))
+        .route("/api/scrapers", get(scrapers))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state.clone());
+
+    if with_dashboard {
+        api.merge(dashboard::router(state))
+    } else {
+        api
+    }
+}
+
+/// Serve the API until the process exits
+pub async fn serve(config: &ApiConfig, state: ApiState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&config.bind_address).await?;
+    tracing::info!("REST API listening on {}", listener.local_addr()?);
+    axum::serve(listener, router(state, config.dashboard)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Listing;
+    use crate::scraper_trait::Scraper;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+
+    struct BrokenScraper;
+
+    #[async_trait]
+    impl Scraper for BrokenScraper {
+        fn name(&self) -> &str {
+            "Broken"
+        }
+
+        async fn scrape(&self, _cities: &[String]) -> Result<Vec<Listing>> {
+            anyhow::bail!("HTTP 403")
+        }
+    }
+
+    fn listing(id: &str, location: &str, price: f64) -> Listing {
+        Listing {
+            surface: Some(40.0),
+            location: location.to_string(),
+            ..Listing::test(id, price)
+        }
+    }
+
+    struct TestApi {
+        url: String,
+        client: reqwest::Client,
+        state: ApiState,
+    }
+
+    impl TestApi {
+        async fn start(scrapers: ScraperRegistry) -> Self {
+            let state = ApiState {
+                database: Arc::new(Mutex::new(Database::new(":memory:").unwrap())),
+                notifiers: Arc::new(NotifierRegistry::new()),
+                scrapers: Arc::new(scrapers),
+                scrape_now: Arc::new(Notify::new()),
+                paused: Arc::new(Mutex::new(false)),
+                token: "s3cret".to_string(),
+                map: MapConfig::default(),
+            };
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let app = router(state.clone(), true);
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            Self { url, client: reqwest::Client::new(), state }
+        }
+
+        async fn get(&self, path: &str) -> (u16, Value) {
+            let response = self.client.get(format!("{}{}", self.url, path)).bearer_auth("s3cret").send().await.unwrap();
+            (response.status().as_u16(), response.json().await.unwrap())
+        }
+
+        async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+            let response = self.client.post(format!("{}{}", self.url, path)).bearer_auth("s3cret").json(&body).send().await.unwrap();
+            (response.status().as_u16(), response.json().await.unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_missing_or_wrong_token() {
+        let api = TestApi::start(ScraperRegistry::new()).await;
+
+        let response = api.client.get(format!("{}/api/listings", api.url)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        let response = api.client.get(format!("{}/api/listings", api.url)).bearer_auth("s3cre").send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(response.json::<Value>().await.unwrap()["error"], "missing or invalid bearer token");
+    }
+
+    #[tokio::test]
+    async fn test_list_and_filter_listings() {
+        let api = TestApi::start(ScraperRegistry::new()).await;
+        {
+            let db = api.state.database.lock().await;
+            db.upsert_listing(&listing("rennes_cheap", "Rennes", 600.0)).unwrap();
+            db.upsert_listing(&listing("rennes_dear", "Rennes", 950.0)).unwrap();
+            let uuid = db.upsert_listing(&listing("nantes", "Nantes", 700.0)).unwrap().uuid;
+            db.update_status(&uuid, ListingStatus::Interesting, Some(1), StatusSource::Button).unwrap();
+        }
+
+        let (status, page) = api.get("/api/listings?city=rennes&max_price=800").await;
+        assert_eq!(status, 200);
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["listings"][0]["listing_id"], "rennes_cheap");
+        assert_eq!(page["listings"][0]["price_per_m2"], 15.0);
+
+        let (_, page) = api.get("/api/listings?status=interesting").await;
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["listings"][0]["listing_id"], "nantes");
+
+        let (_, page) = api.get("/api/listings?sort=price&order=asc").await;
+        let ids: Vec<&str> = page["listings"].as_array().unwrap().iter().map(|l| l["listing_id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["rennes_cheap", "nantes", "rennes_dear"]);
+
+        let (_, page) = api.get("/api/listings?limit=2").await;
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["listings"].as_array().unwrap().len(), 2);
+
+        let (status, error) = api.get("/api/listings?status=maybe").await;
+        assert_eq!(status, 400);
+        assert_eq!(error["error"], "unknown status \"maybe\"");
+    }
+
+    #[tokio::test]
+    async fn test_change_status_follows_the_lifecycle() {
+        let api = TestApi::start(ScraperRegistry::new()).await;
+        let uuid = api.state.database.lock().await.upsert_listing(&listing("rennes", "Rennes", 600.0)).unwrap().uuid;
+        api.state.database.lock().await.add_note(&uuid, 7, "Alice", "Lumineux").unwrap();
+
+        let (status, error) = api.post(&format!("/api/listings/{}/status", uuid), json!({ "status": "accepted" })).await;
+        assert_eq!(status, 409);
+        assert!(error["error"].as_str().unwrap().starts_with("a listing can't go from"));
+
+        let (status, listing) = api.post(&format!("/api/listings/{}/status", uuid), json!({ "status": "interesting" })).await;
+        assert_eq!(status, 200);
+        assert_eq!(listing["status"], "interesting");
+
+        let (status, detail) = api.get(&format!("/api/listings/{}", uuid)).await;
+        assert_eq!(status, 200);
+        assert_eq!(detail["status"], "interesting");
+        assert_eq!(detail["history"][0]["source"], "api");
+        assert_eq!(detail["history"][0]["user_id"], Value::Null);
+        assert_eq!(detail["notes"][0]["content"], "Lumineux");
+        assert_eq!(detail["votes"]["yes"], json!([]));
+
+        let unknown = Uuid::new_v4();
+        let (status, _) = api.post(&format!("/api/listings/{}/status", unknown), json!({ "status": "interesting" })).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_trigger_and_health() {
+        let mut scrapers = ScraperRegistry::new();
+        scrapers.register(Box::new(BrokenScraper));
+        let api = TestApi::start(scrapers).await;
+        api.state.scrapers.scrape_all(&["Rennes".to_string()]).await.unwrap();
+
+        let (status, health) = api.get("/api/scrapers").await;
+        assert_eq!(status, 200);
+        assert_eq!(health[0]["name"], "Broken");
+        assert_eq!(health[0]["last_error"], "HTTP 403");
+        assert_eq!(health[0]["consecutive_failures"], 1);
+        assert_eq!(health[0]["last_success"], Value::Null);
+
+        let (status, _) = api.post("/api/scrape", json!({})).await;
+        assert_eq!(status, 202);
+        // The permit is kept until the scraping task waits for it
+        tokio::time::timeout(std::time::Duration::from_secs(1), api.state.scrape_now.notified()).await.unwrap();
+
+        *api.state.paused.lock().await = true;
+        let (status, _) = api.post("/api/scrape", json!({})).await;
+        assert_eq!(status, 409);
+    }
+}
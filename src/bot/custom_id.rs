@@ -0,0 +1,137 @@
+use uuid::Uuid;
+use crate::lifecycle::ListingAction;
+use crate::votes::Vote;
+
+/// Prefix of the custom_id of the listing buttons: "listing:<action>:<uuid>"
+const PREFIX: &str = "listing:";
+/// custom_ids of the note and visit buttons posted before they carried the listing UUID
+const LEGACY_NOTE_ID: &str = "add_note";
+const LEGACY_VISIT_ID: &str = "schedule_visit";
+
+/// What a button on a listing message does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentAction {
+    Status(ListingAction),
+    Vote(Vote),
+    Note,
+    ScheduleVisit,
+}
+
+impl ComponentAction {
+    const ALL: [ComponentAction; 12] = [
+        ComponentAction::Status(ListingAction::MarkInteresting),
+        ComponentAction::Status(ListingAction::MarkNotGood),
+        ComponentAction::Status(ListingAction::RemoveFromInteresting),
+        ComponentAction::Status(ListingAction::MarkContacted),
+        ComponentAction::Status(ListingAction::MarkApplied),
+        ComponentAction::Status(ListingAction::MarkAccepted),
+        ComponentAction::Status(ListingAction::MarkRejected),
+        ComponentAction::Vote(Vote::Yes),
+        ComponentAction::Vote(Vote::No),
+        ComponentAction::Vote(Vote::Maybe),
+        ComponentAction::Note,
+        ComponentAction::ScheduleVisit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ComponentAction::Status(ListingAction::MarkInteresting) => "interesting",
+            ComponentAction::Status(ListingAction::MarkNotGood) => "not_good",
+            ComponentAction::Status(ListingAction::RemoveFromInteresting) => "remove",
+            ComponentAction::Status(ListingAction::MarkContacted) => "contacted",
+            ComponentAction::Status(ListingAction::MarkApplied) => "applied",
+            ComponentAction::Status(ListingAction::MarkAccepted) => "accepted",
+            ComponentAction::Status(ListingAction::MarkRejected) => "rejected",
+            ComponentAction::Vote(Vote::Yes) => "vote_yes",
+            ComponentAction::Vote(Vote::No) => "vote_no",
+            ComponentAction::Vote(Vote::Maybe) => "vote_maybe",
+            ComponentAction::Note => "note",
+            ComponentAction::ScheduleVisit => "visit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        ComponentAction::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Buttons posted before the custom_id carried the listing UUID
+    fn from_legacy_custom_id(custom_id: &str) -> Option<Self> {
+        match custom_id {
+            LEGACY_NOTE_ID => Some(ComponentAction::Note),
+            LEGACY_VISIT_ID => Some(ComponentAction::ScheduleVisit),
+            _ => ListingAction::from_custom_id(custom_id)
+                .map(ComponentAction::Status)
+                .or_else(|| Vote::from_custom_id(custom_id).map(ComponentAction::Vote)),
+        }
+    }
+}
+
+/// A button of a listing message, identified by its custom_id
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ListingComponent {
+    pub action: ComponentAction,
+    /// Missing on old messages, whose listing is found from the embed footer instead
+    pub uuid: Option<Uuid>,
+}
+
+impl ListingComponent {
+    pub fn custom_id(action: ComponentAction, uuid: Uuid) -> String {
+        format!("{}{}:{}", PREFIX, action.name(), uuid)
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let Some(rest) = custom_id.strip_prefix(PREFIX) else {
+            let action = ComponentAction::from_legacy_custom_id(custom_id)?;
+            return Some(Self { action, uuid: None });
+        };
+
+        let (name, uuid) = rest.split_once(':')?;
+        Some(Self {
+            action: ComponentAction::from_name(name)?,
+            uuid: Some(Uuid::parse_str(uuid).ok()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let uuid = Uuid::new_v4();
+        for action in ComponentAction::ALL {
+            let custom_id = ListingComponent::custom_id(action, uuid);
+            assert!(custom_id.len() <= 100, "{} is too long", custom_id);
+            assert_eq!(ListingComponent::parse(&custom_id), Some(ListingComponent { action, uuid: Some(uuid) }));
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let parsed = ListingComponent::parse("listing:interesting:67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(parsed.action, ComponentAction::Status(ListingAction::MarkInteresting));
+        assert_eq!(parsed.uuid, Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").ok());
+
+        assert_eq!(ListingComponent::parse("listing:interesting:not-a-uuid"), None);
+        assert_eq!(ListingComponent::parse("listing:unknown:67e55044-10b1-426f-9247-bb680e5fe0c8"), None);
+        assert_eq!(ListingComponent::parse("listing:interesting"), None);
+        assert_eq!(ListingComponent::parse("search:0:::balcon"), None);
+        assert_eq!(ListingComponent::parse("visit_rating:1:5"), None);
+    }
+
+    #[test]
+    fn test_parse_legacy_custom_ids() {
+        let legacy = [
+            ("interesting_listing", ComponentAction::Status(ListingAction::MarkInteresting)),
+            ("not_good_listing", ComponentAction::Status(ListingAction::MarkNotGood)),
+            ("remove_from_interesting", ComponentAction::Status(ListingAction::RemoveFromInteresting)),
+            ("vote_maybe", ComponentAction::Vote(Vote::Maybe)),
+            ("add_note", ComponentAction::Note),
+            ("schedule_visit", ComponentAction::ScheduleVisit),
+        ];
+        for (custom_id, action) in legacy {
+            assert_eq!(ListingComponent::parse(custom_id), Some(ListingComponent { action, uuid: None }));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use serenity::all::{
+    Colour, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    ResolvedValue,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::database::{Database, StatusEvent, StatusSource};
+use crate::lifecycle;
+
+/// Only the most recent events are shown, to stay within the embed size limit
+const MAX_EVENTS_SHOWN: usize = 25;
+
+/// Definition of the /history slash command
+pub fn command() -> CreateCommand {
+    CreateCommand::new("history")
+        .description("Show who changed the status of a listing")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "listing", "Listing UUID, id (leboncoin_123) or URL")
+                .required(true),
+        )
+}
+
+fn source_label(source: StatusSource) -> &'static str {
+    match source {
+        StatusSource::Button => "bouton",
+        StatusSource::Reaction => "réaction",
+        StatusSource::Vote => "vote",
+        StatusSource::System => "automatique",
+        StatusSource::Telegram => "Telegram",
+        StatusSource::Matrix => "Matrix",
+        StatusSource::Api => "API",
+        StatusSource::Dashboard => "dashboard",
+    }
+}
+
+/// One line per event, e.g. "<t:1700000000:f> <@42> : Non vérifiée → Intéressante (bouton)"
+fn format_event(event: &StatusEvent) -> String {
+    let who = match event.user_id {
+        Some(user_id) => format!("<@{}>", user_id),
+        // Telegram and Matrix members have no Discord account to mention
+        None if matches!(event.source, StatusSource::Telegram | StatusSource::Matrix) => "📱".to_string(),
+        None if event.source == StatusSource::Api => "🔌".to_string(),
+        None if event.source == StatusSource::Dashboard => "🖥️".to_string(),
+        None => "🤖".to_string(),
+    };
+    let old_status = event.old_status.as_ref().map(lifecycle::label).unwrap_or("?");
+    format!(
+        "<t:{}:f> {} : {} → {} ({})",
+        event.created_at.timestamp(),
+        who,
+        old_status,
+        lifecycle::label(&event.new_status),
+        source_label(event.source)
+    )
+}
+
+/// Answer the /history command with the status changes of a listing
+pub async fn handle_history_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let reference = command.data.options().into_iter().find_map(|option| match option.value {
+        ResolvedValue::String(value) if option.name == "listing" => Some(value.to_string()),
+        _ => None,
+    }).unwrap_or_default();
+
+    let lookup = {
+        let db = database.lock().await;
+        db.find_listing(&reference).and_then(|record| match record {
+            Some(record) => Ok(Some((db.get_status_events(&record.uuid)?, record))),
+            None => Ok(None),
+        })
+    };
+
+    let message = match lookup {
+        Ok(Some((events, record))) => {
+            let description = if events.is_empty() {
+                "Aucun changement de statut.".to_string()
+            } else {
+                let skipped = events.len().saturating_sub(MAX_EVENTS_SHOWN);
+                let mut lines: Vec<String> = events[skipped..].iter().map(format_event).collect();
+                if skipped > 0 {
+                    lines.insert(0, format!("… {} changement(s) plus ancien(s)", skipped));
+                }
+                lines.join("\n")
+            };
+
+            let embed = CreateEmbed::new()
+                .title(format!("📜 Historique : {}", record.title))
+                .url(&record.url)
+                .description(description)
+                .field("Statut actuel", lifecycle::label(&record.status), true)
+                .footer(CreateEmbedFooter::new(format!("Source: {} | ID: {}", record.source, record.uuid)))
+                .color(Colour::from_rgb(52, 152, 219));
+            CreateInteractionResponseMessage::new().embed(embed)
+        }
+        Ok(None) => CreateInteractionResponseMessage::new()
+            .content(format!("❌ Aucune annonce trouvée pour `{}`", reference))
+            .ephemeral(true),
+        Err(e) => {
+            tracing::error!("Failed to load listing history: {}", e);
+            CreateInteractionResponseMessage::new()
+                .content("❌ Impossible de charger l'historique.")
+                .ephemeral(true)
+        }
+    };
+
+    command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ListingStatus;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_format_event() {
+        let event = StatusEvent {
+            old_status: Some(ListingStatus::Unchecked),
+            new_status: ListingStatus::Interesting,
+            user_id: Some(42),
+            source: StatusSource::Button,
+            created_at: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        };
+        assert_eq!(format_event(&event), "<t:1700000000:f> <@42> : Non vérifiée → Intéressante (bouton)");
+
+        let expired = StatusEvent {
+            old_status: Some(ListingStatus::Interesting),
+            new_status: ListingStatus::Expired,
+            user_id: None,
+            source: StatusSource::System,
+            ..event
+        };
+        assert_eq!(format_event(&expired), "<t:1700000000:f> 🤖 : Intéressante → Retirée (automatique)");
+    }
+}
@@ -0,0 +1,935 @@
+use serenity::all::{
+    ChannelId, Command, Context, CreateCommand, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, EventHandler, GatewayIntents,
+    Interaction, Ready, Http, CreateEmbed, Colour, CreateButton, CreateActionRow,
+    ButtonStyle, ReactionType, Reaction, EditMessage, ComponentInteraction, Message,
+};
+use serenity::async_trait;
+use serenity::prelude::TypeMapKey;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::database::{Database, ListingRecord, ListingStatus, StatusSource};
+use crate::models::Listing;
+use crate::notifier_trait::{ListingAnnotations, NotifierRegistry};
+use crate::config::{Config, VotingConfig};
+use crate::votes::{Vote, VoteOutcome};
+use crate::lifecycle::{self, InvalidTransition, ListingAction, ListingButton};
+pub use custom_id::{ComponentAction, ListingComponent};
+use render::{render_listing_embed, ListingContext, MessageKind};
+
+mod custom_id;
+mod history;
+mod notes;
+mod reconcile;
+mod render;
+mod search;
+mod visits;
+
+pub use reconcile::reconcile;
+pub use visits::send_visit_reminders;
+
+pub struct Bot {
+    channel_id: Arc<Mutex<Option<u64>>>,
+    interesting_channel_id: Arc<Mutex<Option<u64>>>,
+    quarantine_channel_id: Arc<Mutex<Option<u64>>>,
+    paused: Arc<Mutex<bool>>,
+    database: Arc<Mutex<Option<Arc<Mutex<Database>>>>>,
+    voting: Arc<Mutex<VotingConfig>>,
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Self {
+            channel_id: Arc::new(Mutex::new(None)),
+            interesting_channel_id: Arc::new(Mutex::new(None)),
+            quarantine_channel_id: Arc::new(Mutex::new(None)),
+            paused: Arc::new(Mutex::new(false)),
+            database: Arc::new(Mutex::new(None)),
+            voting: Arc::new(Mutex::new(VotingConfig::default())),
+        }
+    }
+
+    pub fn set_channel_id(&self, channel_id: u64) {
+        let channel_id_clone = self.channel_id.clone();
+        tokio::spawn(async move {
+            let mut id = channel_id_clone.lock().await;
+            *id = Some(channel_id);
+        });
+    }
+
+    pub fn set_interesting_channel_id(&self, channel_id: u64) {
+        let interesting_channel_id_clone = self.interesting_channel_id.clone();
+        tokio::spawn(async move {
+            let mut id = interesting_channel_id_clone.lock().await;
+            *id = Some(channel_id);
+        });
+    }
+
+    pub fn set_quarantine_channel_id(&self, channel_id: u64) {
+        let quarantine_channel_id_clone = self.quarantine_channel_id.clone();
+        tokio::spawn(async move {
+            let mut id = quarantine_channel_id_clone.lock().await;
+            *id = Some(channel_id).filter(|id| *id != 0);
+        });
+    }
+
+    pub fn set_database(&self, database: Arc<Mutex<Database>>) {
+        let database_clone = self.database.clone();
+        tokio::spawn(async move {
+            let mut db = database_clone.lock().await;
+            *db = Some(database);
+        });
+    }
+
+    pub fn set_voting_config(&self, config: VotingConfig) {
+        let voting_clone = self.voting.clone();
+        tokio::spawn(async move {
+            let mut voting = voting_clone.lock().await;
+            *voting = config;
+        });
+    }
+
+    async fn listing_channels(&self) -> ListingChannels {
+        ListingChannels {
+            main: *self.channel_id.lock().await,
+            interesting: *self.interesting_channel_id.lock().await,
+            quarantine: *self.quarantine_channel_id.lock().await,
+            voting_enabled: self.voting.lock().await.enabled,
+        }
+    }
+
+    pub fn get_paused_state(&self) -> Arc<Mutex<bool>> {
+        self.paused.clone()
+    }
+}
+
+#[async_trait]
+impl EventHandler for Bot {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Command(command) => {
+                let response = match command.data.name.as_str() {
+                    "ping" => {
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("Pong! 🏓")
+                        )
+                    }
+                    "status" => {
+                        let paused = *self.paused.lock().await;
+                        let status_msg = if paused {
+                            "⏸️ Bot is **paused**. Use `/resume` to continue monitoring."
+                        } else {
+                            "✅ Bot is **running** and monitoring for new listings!"
+                        };
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content(status_msg)
+                        )
+                    }
+                    "pause" => {
+                        let mut paused = self.paused.lock().await;
+                        if *paused {
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("ℹ️ Bot is already paused.")
+                            )
+                        } else {
+                            *paused = true;
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("⏸️ Bot monitoring **paused**. Use `/resume` to continue.")
+                            )
+                        }
+                    }
+                    "resume" => {
+                        let mut paused = self.paused.lock().await;
+                        if !*paused {
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("ℹ️ Bot is already running.")
+                            )
+                        } else {
+                            *paused = false;
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("▶️ Bot monitoring **resumed**. Watching for new listings!")
+                            )
+                        }
+                    }
+                    "search" => {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            if let Err(e) = search::handle_search_command(&ctx, &command, db).await {
+                                tracing::error!("Error handling search command: {:?}", e);
+                            }
+                            return;
+                        }
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("❌ Database not initialized")
+                                .ephemeral(true)
+                        )
+                    }
+                    "history" => {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            if let Err(e) = history::handle_history_command(&ctx, &command, db).await {
+                                tracing::error!("Error handling history command: {:?}", e);
+                            }
+                            return;
+                        }
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("❌ Database not initialized")
+                                .ephemeral(true)
+                        )
+                    }
+                    "notes" => {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            if let Err(e) = notes::handle_notes_command(&ctx, &command, db).await {
+                                tracing::error!("Error handling notes command: {:?}", e);
+                            }
+                            return;
+                        }
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("❌ Database not initialized")
+                                .ephemeral(true)
+                        )
+                    }
+                    "visits" => {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            if let Err(e) = visits::handle_visits_command(&ctx, &command, db).await {
+                                tracing::error!("Error handling visits command: {:?}", e);
+                            }
+                            return;
+                        }
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("❌ Database not initialized")
+                                .ephemeral(true)
+                        )
+                    }
+                    "reconcile" => {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            let channels = self.listing_channels().await;
+                            if let Err(e) = reconcile::handle_reconcile_command(&ctx, &command, db, channels).await {
+                                tracing::error!("Error handling reconcile command: {:?}", e);
+                            }
+                            return;
+                        }
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("❌ Database not initialized")
+                                .ephemeral(true)
+                        )
+                    }
+                    "clear" => {
+                        // Acknowledge first with ephemeral message
+                        if let Err(e) = command.create_response(&ctx.http,
+                            CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .content("🗑️ Clearing bot messages from this channel...")
+                                    .ephemeral(true)
+                            )
+                        ).await {
+                            tracing::error!("Error acknowledging clear command: {:?}", e);
+                            return;
+                        }
+
+                        // Get the channel ID from the command
+                        let channel_id = command.channel_id;
+
+                        // Clear messages asynchronously
+                        let ctx_clone = ctx.clone();
+                        tokio::spawn(async move {
+                            match clear_bot_messages(&ctx_clone, channel_id).await {
+                                Ok(count) => {
+                                    tracing::info!("Cleared {} bot messages from channel {}", count, channel_id);
+                                    // Try to edit the response to show completion
+                                    let _ = command.edit_response(&ctx_clone.http,
+                                        serenity::all::EditInteractionResponse::new()
+                                            .content(format!("✅ Cleared {} bot message(s) from this channel!", count))
+                                    ).await;
+                                }
+                                Err(e) => {
+                                    tracing::error!("Error clearing messages: {:?}", e);
+                                    let _ = command.edit_response(&ctx_clone.http,
+                                        serenity::all::EditInteractionResponse::new()
+                                            .content(format!("❌ Error clearing messages: {}", e))
+                                    ).await;
+                                }
+                            }
+                        });
+                        return; // Early return since we already responded
+                    }
+                    _ => {
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("Unknown command")
+                        )
+                    }
+                };
+
+                if let Err(e) = command.create_response(&ctx.http, response).await {
+                    tracing::error!("Error responding to command: {:?}", e);
+                }
+            }
+            Interaction::Component(component) => {
+                // Handle button interactions
+                let db_option = self.database.lock().await.clone();
+                if let Some(db) = db_option {
+                    if let Some(button) = ListingComponent::parse(&component.data.custom_id) {
+                        // Buttons posted before the UUID was in the custom_id only have the embed footer
+                        let uuid = button.uuid.or_else(|| footer_uuid(&component.message));
+                        let channels = self.listing_channels().await;
+                        let result = match button.action {
+                            ComponentAction::Status(action) => handle_listing_action(&ctx, &component, action, uuid, channels, db.clone()).await,
+                            ComponentAction::Vote(vote) => {
+                                let voting = self.voting.lock().await.clone();
+                                handle_vote_button(&ctx, &component, vote, uuid, &voting, channels, db.clone()).await
+                            }
+                            ComponentAction::Note => notes::handle_note_button(&ctx, &component, uuid).await,
+                            ComponentAction::ScheduleVisit => visits::handle_visit_button(&ctx, &component, uuid).await,
+                        };
+                        if let Err(e) = result {
+                            tracing::error!("Error handling {:?} button: {:?}", button.action, e);
+                        }
+                    } else if visits::is_rating_custom_id(&component.data.custom_id) {
+                        let channels = self.listing_channels().await;
+                        if let Err(e) = visits::handle_rating_button(&ctx, &component, channels, db.clone()).await {
+                            tracing::error!("Error handling visit rating button: {:?}", e);
+                        }
+                    } else if search::is_search_custom_id(&component.data.custom_id) {
+                        if let Err(e) = search::handle_search_page(&ctx, &component, db.clone()).await {
+                            tracing::error!("Error handling search page button: {:?}", e);
+                        }
+                    }
+                } else {
+                    tracing::error!("Database not initialized");
+                }
+            }
+            Interaction::Modal(modal) => {
+                let db_option = self.database.lock().await.clone();
+                if let Some(db) = db_option {
+                    if notes::is_note_modal_id(&modal.data.custom_id) {
+                        let channels = self.listing_channels().await;
+                        if let Err(e) = notes::handle_note_modal(&ctx, &modal, channels, db).await {
+                            tracing::error!("Error handling note modal: {:?}", e);
+                        }
+                    } else if visits::is_visit_modal_id(&modal.data.custom_id) {
+                        let channels = self.listing_channels().await;
+                        if let Err(e) = visits::handle_visit_modal(&ctx, &modal, channels, db).await {
+                            tracing::error!("Error handling visit modal: {:?}", e);
+                        }
+                    }
+                } else {
+                    tracing::error!("Database not initialized");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        tracing::info!("Discord bot {} is connected!", ready.user.name);
+
+        // Register slash commands
+        let commands = vec![
+            CreateCommand::new("ping").description("Check if the bot is responsive"),
+            CreateCommand::new("status").description("Get the current status of the bot"),
+            CreateCommand::new("pause").description("Pause the listing monitoring"),
+            CreateCommand::new("resume").description("Resume the listing monitoring"),
+            CreateCommand::new("clear").description("Remove all bot messages from the current channel"),
+            search::command(),
+            history::command(),
+            notes::command(),
+            visits::command(),
+            reconcile::command(),
+        ];
+
+        if let Err(e) = Command::set_global_commands(&ctx.http, commands).await {
+            tracing::error!("Failed to register commands: {:?}", e);
+        } else {
+            tracing::info!("Successfully registered slash commands");
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        // Check if it's a red X emoji (❌)
+        if let ReactionType::Unicode(emoji) = &reaction.emoji {
+            if emoji == "❌" {
+                // Only handle red X in the main channel, not the interesting channel
+                let main_channel_id = *self.channel_id.lock().await;
+                if let Some(main_id) = main_channel_id {
+                    if reaction.channel_id.get() == main_id {
+                        let db_option = self.database.lock().await.clone();
+                        if let Some(db) = db_option {
+                            let channels = self.listing_channels().await;
+                            if let Err(e) = handle_red_x_reaction(&ctx, &reaction, db, channels).await {
+                                tracing::error!("Error handling red X reaction: {:?}", e);
+                            }
+                        }
+                    } else {
+                        tracing::debug!("Ignoring red X reaction in non-main channel");
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub async fn send_listing_notification(
+    http: &Arc<Http>,
+    channel_id: u64,
+    listing: &Listing,
+    uuid: Uuid,
+    database: Arc<Mutex<Database>>,
+    annotations: &ListingAnnotations,
+    voting_enabled: bool,
+) -> Result<(), serenity::Error> {
+    let (embed, components) = {
+        let db = database.lock().await;
+        let record = match db.get_listing_by_uuid(&uuid) {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                tracing::warn!("Listing '{}' is not in the database, skipping", listing.title);
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::error!("Failed to load listing '{}': {}", listing.title, e);
+                return Ok(());
+            }
+        };
+
+        // Check if this listing already has a message on Discord
+        if record.main_channel_message_id.is_some() {
+            tracing::warn!("Listing '{}' already has a Discord message (ID: {:?}), skipping",
+                listing.title, record.main_channel_message_id);
+            return Ok(());
+        }
+
+        // Skip listings without sufficient information
+        if !listing.has_sufficient_info() {
+            tracing::warn!("Skipping listing '{}' - insufficient information", listing.title);
+            // Mark as attempted with a special message ID (0) to prevent retrying
+            if let Err(e) = db.set_main_channel_message_id(&uuid, 0) {
+                tracing::error!("Failed to mark listing as skipped: {}", e);
+            }
+            return Ok(());
+        }
+
+        // Keep the warnings for later re-renders of the message
+        if let Err(e) = db.set_listing_annotations(&uuid, annotations.scam_warning.as_ref(), annotations.reposts.as_ref()) {
+            tracing::error!("Failed to store listing annotations: {}", e);
+        }
+
+        match ListingContext::load(&db, &record, voting_enabled) {
+            Ok(context) => render_listing_embed(&record, &context),
+            Err(e) => {
+                tracing::error!("Failed to load listing context for '{}': {}", listing.title, e);
+                return Ok(());
+            }
+        }
+    };
+
+    tracing::info!("Sending embed for listing: {} (has image: {}) with UUID: {}",
+        listing.title,
+        listing.image_url.is_some(),
+        uuid
+    );
+
+    let builder = CreateMessage::new()
+        .embed(embed)
+        .components(components);
+
+    let message = ChannelId::new(channel_id).send_message(http, builder).await?;
+
+    // Store the message ID in the database
+    let db = database.lock().await;
+    if let Err(e) = db.set_main_channel_message_id(&uuid, message.id.get()) {
+        tracing::error!("Failed to store main channel message ID: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Whether Discord answered that the message doesn't exist in that channel
+fn is_unknown_message(error: &serenity::Error) -> bool {
+    matches!(error, serenity::Error::Http(e) if e.status_code().is_some_and(|status| status.as_u16() == 404))
+}
+
+/// Re-render the messages of a listing from the database, e.g. after a price change, a vote or a note
+/// The interesting channel copy is posted or deleted when the status calls for it
+pub async fn refresh_listing_messages(
+    http: &Http,
+    database: &Arc<Mutex<Database>>,
+    uuid: Uuid,
+    channels: ListingChannels,
+) -> Result<(), serenity::Error> {
+    let loaded = {
+        let db = database.lock().await;
+        db.get_listing_by_uuid(&uuid).and_then(|record| match record {
+            Some(record) => Ok(Some((ListingContext::load(&db, &record, channels.voting_enabled)?, record))),
+            None => Ok(None),
+        })
+    };
+    let (context, record) = match loaded {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => {
+            tracing::warn!("Cannot refresh unknown listing {}", uuid);
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Failed to load listing {}: {}", uuid, e);
+            return Ok(());
+        }
+    };
+
+    // 0 marks listings that were never posted
+    if let (Some(channel_id), Some(message_id)) = (channels.main, record.main_channel_message_id.filter(|id| *id != 0)) {
+        let (embed, components) = render_listing_embed(&record, &context);
+        let edit = EditMessage::new().embed(embed).components(components);
+        match ChannelId::new(channel_id).edit_message(http, message_id, edit.clone()).await {
+            // Quarantined listings are posted in the quarantine channel under the same message id column
+            Err(e) if is_unknown_message(&e) => match channels.quarantine.filter(|id| *id != channel_id) {
+                Some(quarantine_channel_id) => {
+                    ChannelId::new(quarantine_channel_id).edit_message(http, message_id, edit).await?;
+                }
+                None => return Err(e),
+            },
+            result => {
+                result?;
+            }
+        }
+    }
+
+    let Some(interesting_channel_id) = channels.interesting else {
+        return Ok(());
+    };
+    let interesting_channel = ChannelId::new(interesting_channel_id);
+    let (embed, components) = render_listing_embed(&record, &context.for_kind(MessageKind::Interesting));
+    let shown = lifecycle::shown_in_interesting_channel(&record.status);
+
+    match record.interesting_channel_message_id {
+        // Expired listings keep their greyed out copy
+        Some(message_id) if shown || record.status == ListingStatus::Expired => {
+            let edit = EditMessage::new().embed(embed).components(components);
+            interesting_channel.edit_message(http, message_id, edit).await?;
+        }
+        Some(message_id) => {
+            if let Err(e) = interesting_channel.delete_message(http, message_id).await {
+                tracing::error!("Failed to delete interesting channel message: {}", e);
+            }
+            if let Err(e) = database.lock().await.clear_interesting_channel_message_id(&uuid) {
+                tracing::error!("Failed to clear interesting channel message ID: {}", e);
+            }
+        }
+        None if shown => {
+            let builder = CreateMessage::new().embed(embed).components(components);
+            let message = interesting_channel.send_message(http, builder).await?;
+            if let Err(e) = database.lock().await.set_interesting_channel_message_id(&uuid, message.id.get()) {
+                tracing::error!("Failed to store interesting channel message ID: {}", e);
+            }
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Post a new message for a listing, rendered from the database, and store its id
+/// Used when the previous message is gone; main channel messages are always posted to the main channel
+async fn post_listing_message(
+    http: &Http,
+    database: &Arc<Mutex<Database>>,
+    uuid: Uuid,
+    kind: MessageKind,
+    channels: ListingChannels,
+) -> Result<(), serenity::Error> {
+    let channel_id = match kind {
+        MessageKind::Main => channels.main,
+        MessageKind::Interesting => channels.interesting,
+    };
+    let Some(channel_id) = channel_id else {
+        return Ok(());
+    };
+
+    let rendered = {
+        let db = database.lock().await;
+        db.get_listing_by_uuid(&uuid).and_then(|record| match record {
+            Some(record) => {
+                let context = ListingContext::load(&db, &record, channels.voting_enabled)?.for_kind(kind);
+                Ok(Some(render_listing_embed(&record, &context)))
+            }
+            None => Ok(None),
+        })
+    };
+    let (embed, components) = match rendered {
+        Ok(Some(rendered)) => rendered,
+        Ok(None) => {
+            tracing::warn!("Cannot post unknown listing {}", uuid);
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Failed to load listing {}: {}", uuid, e);
+            return Ok(());
+        }
+    };
+
+    let builder = CreateMessage::new().embed(embed).components(components);
+    let message = ChannelId::new(channel_id).send_message(http, builder).await?;
+
+    let db = database.lock().await;
+    let stored = match kind {
+        MessageKind::Main => db.set_main_channel_message_id(&uuid, message.id.get()),
+        MessageKind::Interesting => db.set_interesting_channel_message_id(&uuid, message.id.get()),
+    };
+    if let Err(e) = stored {
+        tracing::error!("Failed to store message ID of listing {}: {}", uuid, e);
+    }
+
+    Ok(())
+}
+
+/// Title of the price drop announcements, which carry the footer of the listing without being its message
+const PRICE_DROP_TITLE_PREFIX: &str = "📉 Baisse de prix : ";
+
+/// Announce a price drop in the interesting channel
+pub async fn send_price_drop_notification(
+    http: &Arc<Http>,
+    interesting_channel_id: u64,
+    record: &ListingRecord,
+    old_price: f64,
+) -> Result<(), serenity::Error> {
+    let Some(new_price) = record.price else {
+        return Ok(());
+    };
+    let percent = (old_price - new_price) / old_price * 100.0;
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("{}{}", PRICE_DROP_TITLE_PREFIX, record.title))
+        .url(&record.url)
+        .description(format!("~~{:.0}€~~ → **{:.0}€** (-{:.0}%)", old_price, new_price, percent))
+        .color(Colour::from_rgb(46, 139, 87)) // Green for good news
+        .footer(serenity::all::CreateEmbedFooter::new(format!("Source: {} | ID: {}", record.source, record.uuid)));
+
+    if let Some(image_url) = &record.image_url {
+        embed = embed.thumbnail(image_url);
+    }
+
+    ChannelId::new(interesting_channel_id)
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+fn extract_uuid_from_footer(footer_text: &str) -> Option<Uuid> {
+    // Footer format: "Source: leboncoin | ID: uuid"
+    if let Some(id_part) = footer_text.split(" | ID: ").nth(1) {
+        Uuid::parse_str(id_part.trim()).ok()
+    } else {
+        None
+    }
+}
+
+/// UUID of the listing shown in a message, for messages whose buttons don't carry it
+fn footer_uuid(message: &Message) -> Option<Uuid> {
+    message.embeds.first()
+        .and_then(|embed| embed.footer.as_ref())
+        .and_then(|footer| extract_uuid_from_footer(&footer.text))
+}
+
+/// Where the messages of a listing are posted
+#[derive(Debug, Clone, Copy)]
+pub struct ListingChannels {
+    pub main: Option<u64>,
+    pub interesting: Option<u64>,
+    /// Holds the main channel message of the listings flagged as likely scams
+    pub quarantine: Option<u64>,
+    pub voting_enabled: bool,
+}
+
+impl ListingChannels {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            main: Some(config.channel_id).filter(|id| *id != 0),
+            interesting: Some(config.interesting_channel_id).filter(|id| *id != 0),
+            quarantine: Some(config.scam_detection.quarantine_channel_id).filter(|id| *id != 0),
+            voting_enabled: config.voting.enabled,
+        }
+    }
+}
+
+fn create_button(button: ListingButton, uuid: Uuid) -> CreateButton {
+    match button {
+        ListingButton::Action(action) | ListingButton::Done(action) => {
+            let style = match action {
+                ListingAction::MarkInteresting | ListingAction::MarkContacted | ListingAction::MarkApplied => ButtonStyle::Primary,
+                ListingAction::MarkNotGood | ListingAction::RemoveFromInteresting => ButtonStyle::Danger,
+                ListingAction::MarkAccepted => ButtonStyle::Success,
+                ListingAction::MarkRejected => ButtonStyle::Secondary,
+            };
+            CreateButton::new(ListingComponent::custom_id(ComponentAction::Status(action), uuid))
+                .label(action.label())
+                .style(style)
+                .disabled(matches!(button, ListingButton::Done(_)))
+        }
+        ListingButton::Vote(vote) => {
+            let style = match vote {
+                Vote::Yes => ButtonStyle::Success,
+                Vote::No => ButtonStyle::Danger,
+                Vote::Maybe => ButtonStyle::Secondary,
+            };
+            CreateButton::new(ListingComponent::custom_id(ComponentAction::Vote(vote), uuid))
+                .label(format!("{} {}", vote.emoji(), vote.label()))
+                .style(style)
+        }
+        ListingButton::Note => notes::note_button(uuid),
+        ListingButton::ScheduleVisit => visits::visit_button(uuid),
+    }
+}
+
+fn button_rows(buttons: Vec<ListingButton>, uuid: Uuid) -> Vec<CreateActionRow> {
+    if buttons.is_empty() {
+        return vec![];
+    }
+    vec![CreateActionRow::Buttons(buttons.into_iter().map(|button| create_button(button, uuid)).collect())]
+}
+
+/// Gives the event handlers the notifiers, so that changes made on Discord reach the other outputs
+pub struct NotifierRegistryKey;
+
+impl TypeMapKey for NotifierRegistryKey {
+    type Value = Arc<NotifierRegistry>;
+}
+
+/// Change the status of a listing and bring its messages in line with it
+/// Returns the reason when the lifecycle doesn't allow the change, leaving everything untouched
+async fn transition_listing(
+    ctx: &Context,
+    database: &Arc<Mutex<Database>>,
+    uuid: Uuid,
+    status: ListingStatus,
+    user_id: Option<u64>,
+    source: StatusSource,
+    channels: ListingChannels,
+) -> Result<Result<(), InvalidTransition>, serenity::Error> {
+    let label = lifecycle::label(&status);
+    let updated = database.lock().await.update_status(&uuid, status, user_id, source);
+    if let Err(e) = updated {
+        return match e.downcast::<InvalidTransition>() {
+            Ok(invalid) => Ok(Err(invalid)),
+            Err(e) => {
+                tracing::error!("Failed to update listing status: {}", e);
+                Ok(Ok(()))
+            }
+        };
+    }
+
+    tracing::info!("Listing {} is now {}", uuid, label);
+
+    let notifiers = ctx.data.read().await.get::<NotifierRegistryKey>().cloned();
+    let Some(notifiers) = notifiers else {
+        refresh_listing_messages(&ctx.http, database, uuid, channels).await?;
+        return Ok(Ok(()));
+    };
+    // Every notifier, Discord included, shows the new status
+    // In the background, as Discord wants an answer to the interaction within 3 seconds
+    match database.lock().await.get_listing_by_uuid(&uuid) {
+        Ok(Some(record)) => {
+            tokio::spawn(async move { notifiers.update_listing(&record).await });
+        }
+        Ok(None) => tracing::warn!("Listing {} disappeared after its status change", uuid),
+        Err(e) => tracing::error!("Failed to load listing {}: {}", uuid, e),
+    }
+    Ok(Ok(()))
+}
+
+/// ❌ on a main channel message sends the listing back to unchecked
+async fn handle_red_x_reaction(ctx: &Context, reaction: &Reaction, database: Arc<Mutex<Database>>, channels: ListingChannels) -> Result<(), serenity::Error> {
+    let stored = database.lock().await.get_listing_uuid_by_message_id(reaction.message_id.get());
+    let uuid = match stored {
+        Ok(Some(uuid)) => Some(uuid),
+        Ok(None) => footer_uuid(&reaction.message(&ctx.http).await?),
+        Err(e) => {
+            tracing::error!("Failed to look up the listing of message {}: {}", reaction.message_id, e);
+            None
+        }
+    };
+
+    if let Some(uuid) = uuid {
+        let user_id = reaction.user_id.map(|id| id.get());
+        if let Err(invalid) = transition_listing(ctx, &database, uuid, ListingStatus::Unchecked, user_id, StatusSource::Reaction, channels).await? {
+            tracing::info!("Ignoring ❌ reaction: {}", invalid);
+        }
+    }
+
+    // Remove the reaction
+    reaction.delete(&ctx.http).await?;
+
+    Ok(())
+}
+
+/// Status buttons ("Intéressant", "Pas bien", "Retirer", "Contacté"...) of both channels
+async fn handle_listing_action(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    action: ListingAction,
+    uuid: Option<Uuid>,
+    channels: ListingChannels,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let error = match uuid {
+        None => Some("❌ Could not find listing information!".to_string()),
+        Some(_) if lifecycle::shown_in_interesting_channel(&action.target()) && channels.interesting.is_none() => {
+            Some("❌ Interesting channel is not configured!".to_string())
+        }
+        Some(uuid) => {
+            let user_id = Some(component.user.id.get());
+            transition_listing(ctx, &database, uuid, action.target(), user_id, StatusSource::Button, channels)
+                .await?
+                .err()
+                .map(|invalid| format!("❌ Impossible : {}", invalid))
+        }
+    };
+
+    let response = match error {
+        Some(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(error)
+                .ephemeral(true)
+        ),
+        None => CreateInteractionResponse::Acknowledge,
+    };
+    component.create_response(&ctx.http, response).await?;
+
+    Ok(())
+}
+
+async fn handle_vote_button(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    vote: Vote,
+    uuid: Option<Uuid>,
+    voting: &VotingConfig,
+    channels: ListingChannels,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let Some(uuid) = uuid else {
+        tracing::warn!("Vote on a message without listing");
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let user_id = component.user.id.get();
+    let state = {
+        let db = database.lock().await;
+        db.toggle_vote(&uuid, user_id, vote)
+            .and_then(|_| db.get_vote_tally(&uuid))
+            .and_then(|tally| Ok((tally, db.get_listing_by_uuid(&uuid)?)))
+    };
+    let (tally, record) = match state {
+        Ok((tally, Some(record))) => (tally, record),
+        Ok((_, None)) => {
+            tracing::warn!("Vote on unknown listing {}", uuid);
+            component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Failed to record vote: {}", e);
+            component.create_response(&ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("❌ Impossible d'enregistrer le vote.")
+                        .ephemeral(true)
+                )
+            ).await?;
+            return Ok(());
+        }
+    };
+    tracing::info!("{} voted {} on '{}' ({})", component.user.name, vote.as_str(), record.title, tally.format().replace('\n', " "));
+
+    // Members can keep voting after a promotion, e.g. to veto the listing later
+    let target = match tally.outcome(voting) {
+        VoteOutcome::Veto => Some(ListingStatus::NotGood),
+        VoteOutcome::Promote if record.status == ListingStatus::Unchecked && channels.interesting.is_some() => {
+            Some(ListingStatus::Interesting)
+        }
+        _ => None,
+    };
+    let transitioned = match target.filter(|target| *target != record.status) {
+        Some(target) => match transition_listing(ctx, &database, uuid, target, Some(user_id), StatusSource::Vote, channels).await? {
+            Ok(()) => true,
+            Err(invalid) => {
+                tracing::info!("Vote outcome not applied: {}", invalid);
+                false
+            }
+        },
+        None => false,
+    };
+    // Show the new tally
+    if !transitioned {
+        refresh_listing_messages(&ctx.http, &database, uuid, channels).await?;
+    }
+
+    component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+
+    Ok(())
+}
+
+async fn clear_bot_messages(ctx: &Context, channel_id: ChannelId) -> Result<usize, serenity::Error> {
+    let mut count = 0;
+    let current_user = ctx.http.get_current_user().await?;
+    let bot_id = current_user.id;
+
+    tracing::info!("Starting to clear bot messages from channel {} (bot ID: {})", channel_id, bot_id);
+
+    // Fetch messages in batches
+    let mut last_message_id = None;
+
+    loop {
+        let messages = if let Some(before_id) = last_message_id {
+            channel_id.messages(&ctx.http, serenity::all::GetMessages::new().before(before_id).limit(100)).await?
+        } else {
+            channel_id.messages(&ctx.http, serenity::all::GetMessages::new().limit(100)).await?
+        };
+
+        if messages.is_empty() {
+            break;
+        }
+
+        last_message_id = messages.last().map(|m| m.id);
+
+        // Filter and delete bot messages
+        for message in messages {
+            if message.author.id == bot_id {
+                match message.delete(&ctx.http).await {
+                    Ok(_) => {
+                        count += 1;
+                        tracing::debug!("Deleted message {}", message.id);
+                        // Add a small delay to avoid rate limiting
+                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to delete message {}: {:?}", message.id, e);
+                    }
+                }
+            }
+        }
+    }
+
+    tracing::info!("Cleared {} bot messages from channel {}", count, channel_id);
+    Ok(count)
+}
+
+pub fn get_intents() -> GatewayIntents {
+    GatewayIntents::GUILDS
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+}
@@ -0,0 +1,237 @@
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, Colour, CommandInteraction, CommandOptionType, ComponentInteraction,
+    Context, CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateEmbedFooter, CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateModal, InputTextStyle, ModalInteraction, ResolvedValue,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::database::{Database, ListingNote};
+use super::custom_id::{ComponentAction, ListingComponent};
+use super::{refresh_listing_messages, ListingChannels};
+
+/// Prefix of the modal custom_id, followed by the listing UUID
+const MODAL_ID_PREFIX: &str = "note_modal:";
+const NOTE_INPUT_ID: &str = "content";
+/// Name of the embed field with the latest notes
+pub const NOTES_FIELD_NAME: &str = "📝 Notes";
+/// Number of notes shown in the listing embeds (all of them are shown by /notes)
+const NOTES_IN_EMBED: usize = 3;
+/// Notes are shortened in the embed field, which is limited to 1024 characters
+const NOTE_PREVIEW_CHARS: usize = 250;
+const MAX_NOTE_LENGTH: u16 = 1000;
+
+pub fn note_button(uuid: Uuid) -> CreateButton {
+    CreateButton::new(ListingComponent::custom_id(ComponentAction::Note, uuid))
+        .label("📝 Note")
+        .style(ButtonStyle::Secondary)
+}
+
+pub fn is_note_modal_id(custom_id: &str) -> bool {
+    custom_id.starts_with(MODAL_ID_PREFIX)
+}
+
+/// Definition of the /notes slash command
+pub fn command() -> CreateCommand {
+    CreateCommand::new("notes")
+        .description("Show all the notes left on a listing")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "listing", "Listing UUID, id (leboncoin_123) or URL")
+                .required(true),
+        )
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        format!("{}…", text.chars().take(max_chars).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+fn format_note(note: &ListingNote, max_chars: usize) -> String {
+    format!(
+        "**{}** (<t:{}:R>) : {}",
+        note.author,
+        note.created_at.timestamp(),
+        truncate(&note.content, max_chars)
+    )
+}
+
+/// Embed field value with the latest notes, e.g. "… 2 note(s) plus ancienne(s)\n**alice** (<t:…:R>) : Visite samedi"
+pub fn format_latest_notes(notes: &[ListingNote]) -> String {
+    let skipped = notes.len().saturating_sub(NOTES_IN_EMBED);
+    let mut lines: Vec<String> = notes[skipped..]
+        .iter()
+        .map(|note| format_note(note, NOTE_PREVIEW_CHARS))
+        .collect();
+    if skipped > 0 {
+        lines.insert(0, format!("… {} note(s) plus ancienne(s), voir `/notes`", skipped));
+    }
+    lines.join("\n")
+}
+
+/// Open the note modal for the listing of the clicked message
+pub async fn handle_note_button(ctx: &Context, component: &ComponentInteraction, uuid: Option<Uuid>) -> Result<(), serenity::Error> {
+    let Some(uuid) = uuid else {
+        tracing::warn!("Note button on a message without listing");
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let input = CreateInputText::new(InputTextStyle::Paragraph, "Note", NOTE_INPUT_ID)
+        .placeholder("Appelé le propriétaire, visite possible samedi…")
+        .max_length(MAX_NOTE_LENGTH);
+    let modal = CreateModal::new(format!("{}{}", MODAL_ID_PREFIX, uuid), "Ajouter une note")
+        .components(vec![CreateActionRow::InputText(input)]);
+
+    component.create_response(&ctx.http, CreateInteractionResponse::Modal(modal)).await
+}
+
+/// Store a submitted note and show it on the listing's messages
+pub async fn handle_note_modal(
+    ctx: &Context,
+    modal: &ModalInteraction,
+    channels: ListingChannels,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let uuid = modal.data.custom_id
+        .strip_prefix(MODAL_ID_PREFIX)
+        .and_then(|uuid| Uuid::parse_str(uuid).ok());
+    let content = modal.data.components.iter()
+        .flat_map(|row| row.components.iter())
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == NOTE_INPUT_ID => input.value.clone(),
+            _ => None,
+        })
+        .map(|content| content.trim().to_string())
+        .unwrap_or_default();
+
+    let Some(uuid) = uuid.filter(|_| !content.is_empty()) else {
+        modal.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let saved = database.lock().await.add_note(&uuid, modal.user.id.get(), modal.user.display_name(), &content);
+    if let Err(e) = saved {
+        tracing::error!("Failed to save note: {}", e);
+        modal.create_response(&ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("❌ Impossible d'enregistrer la note.")
+                    .ephemeral(true)
+            )
+        ).await?;
+        return Ok(());
+    }
+
+    if let Err(e) = refresh_listing_messages(&ctx.http, &database, uuid, channels).await {
+        tracing::error!("Failed to show note on listing {}: {}", uuid, e);
+    }
+
+    modal.create_response(&ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("📝 Note ajoutée !")
+                .ephemeral(true)
+        )
+    ).await
+}
+
+/// Answer the /notes command with every note of a listing
+pub async fn handle_notes_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let reference = command.data.options().into_iter().find_map(|option| match option.value {
+        ResolvedValue::String(value) if option.name == "listing" => Some(value.to_string()),
+        _ => None,
+    }).unwrap_or_default();
+
+    let lookup = {
+        let db = database.lock().await;
+        db.find_listing(&reference).and_then(|record| match record {
+            Some(record) => Ok(Some((db.get_notes(&record.uuid)?, record))),
+            None => Ok(None),
+        })
+    };
+
+    let message = match lookup {
+        Ok(Some((notes, record))) => {
+            let description = if notes.is_empty() {
+                "Aucune note pour cette annonce.".to_string()
+            } else {
+                // Keep the most recent notes within the 4096 characters of an embed description
+                let mut lines = Vec::new();
+                let mut length = 0;
+                for note in notes.iter().rev() {
+                    let line = format_note(note, MAX_NOTE_LENGTH as usize);
+                    length += line.len() + 2;
+                    if length > 3900 {
+                        lines.push(format!("… {} note(s) plus ancienne(s)", notes.len() - lines.len()));
+                        break;
+                    }
+                    lines.push(line);
+                }
+                lines.reverse();
+                lines.join("\n\n")
+            };
+
+            let embed = CreateEmbed::new()
+                .title(format!("📝 Notes : {}", record.title))
+                .url(&record.url)
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Source: {} | ID: {}", record.source, record.uuid)))
+                .color(Colour::from_rgb(52, 152, 219));
+            CreateInteractionResponseMessage::new().embed(embed)
+        }
+        Ok(None) => CreateInteractionResponseMessage::new()
+            .content(format!("❌ Aucune annonce trouvée pour `{}`", reference))
+            .ephemeral(true),
+        Err(e) => {
+            tracing::error!("Failed to load listing notes: {}", e);
+            CreateInteractionResponseMessage::new()
+                .content("❌ Impossible de charger les notes.")
+                .ephemeral(true)
+        }
+    };
+
+    command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn note(author: &str, content: &str) -> ListingNote {
+        ListingNote {
+            user_id: 1,
+            author: author.to_string(),
+            content: content.to_string(),
+            created_at: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_latest_notes() {
+        assert_eq!(
+            format_latest_notes(&[note("alice", "Visite samedi")]),
+            "**alice** (<t:1700000000:R>) : Visite samedi"
+        );
+
+        let notes: Vec<_> = (1..=5).map(|i| note("bob", &format!("note {}", i))).collect();
+        let field = format_latest_notes(&notes);
+        assert!(field.starts_with("… 2 note(s) plus ancienne(s)"));
+        assert!(!field.contains("note 2"));
+        assert!(field.ends_with("note 5"));
+    }
+
+    #[test]
+    fn test_long_notes_fit_in_field() {
+        let notes: Vec<_> = (0..NOTES_IN_EMBED).map(|_| note("alice", &"é".repeat(1000))).collect();
+        assert!(format_latest_notes(&notes).chars().count() <= 1024);
+    }
+}
@@ -0,0 +1,478 @@
+use serenity::all::{
+    ChannelId, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse, GetMessages,
+    Http, Message, MessageType, Permissions, ResolvedValue, UserId,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::database::{Database, ListingRecord, ListingStatus};
+use crate::lifecycle;
+use super::render::MessageKind;
+use super::{footer_uuid, post_listing_message, refresh_listing_messages, ListingChannels, PRICE_DROP_TITLE_PREFIX};
+
+/// Definition of the /reconcile slash command
+pub fn command() -> CreateCommand {
+    CreateCommand::new("reconcile")
+        .description("Check that the listing messages on Discord match the database")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "delete_orphans",
+            "Also delete the bot messages of listings unknown to the database",
+        ))
+}
+
+/// A listing message of the bot found in one of the channels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoundMessage {
+    pub kind: MessageKind,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub uuid: Uuid,
+}
+
+/// What brings the database and Discord back in line for one message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fix {
+    /// The message exists but its id isn't stored, e.g. the bot stopped right after posting it
+    Adopt { uuid: Uuid, kind: MessageKind, message_id: u64 },
+    /// Extra copy of a listing message, or a copy the status no longer calls for
+    Delete { channel_id: u64, message_id: u64 },
+    /// Listing message whose UUID is not in the database
+    Orphan { channel_id: u64, message_id: u64, uuid: Uuid },
+    /// The stored message was deleted and the listing still needs one
+    Repost { uuid: Uuid, kind: MessageKind },
+    /// The stored message was deleted and the listing doesn't need one any more
+    Clear { uuid: Uuid, kind: MessageKind },
+    /// Skipped for lack of information (message id 0) but complete now
+    RetrySkipped { uuid: Uuid },
+    /// Open listing still skipped for lack of information
+    StillSkipped { uuid: Uuid },
+}
+
+fn stored_message_id(record: &ListingRecord, kind: MessageKind) -> Option<u64> {
+    match kind {
+        MessageKind::Main => record.main_channel_message_id,
+        MessageKind::Interesting => record.interesting_channel_message_id,
+    }
+}
+
+/// Whether a listing should have a message of this kind
+fn needs_message(record: &ListingRecord, kind: MessageKind) -> bool {
+    match kind {
+        MessageKind::Main => lifecycle::is_open(&record.status),
+        MessageKind::Interesting => lifecycle::shown_in_interesting_channel(&record.status),
+    }
+}
+
+/// Fixes for the messages of one kind of a listing, given the copies found on Discord (oldest first)
+fn plan_listing(record: &ListingRecord, kind: MessageKind, found: &[FoundMessage]) -> Vec<Fix> {
+    let uuid = record.uuid;
+    let stored = stored_message_id(record, kind);
+    // Expired listings keep their greyed out copy in the interesting channel
+    let keeps_copy = kind == MessageKind::Main || needs_message(record, kind) || record.status == ListingStatus::Expired;
+
+    let kept = match stored.and_then(|id| found.iter().find(|message| message.message_id == id)) {
+        Some(message) => Some(message.message_id),
+        None if keeps_copy => found.last().map(|message| message.message_id),
+        None => None,
+    };
+
+    let mut fixes: Vec<Fix> = found.iter()
+        .filter(|message| Some(message.message_id) != kept)
+        .map(|message| Fix::Delete { channel_id: message.channel_id, message_id: message.message_id })
+        .collect();
+
+    match (stored, kept) {
+        (Some(stored), Some(kept)) if stored == kept => {}
+        (_, Some(message_id)) => fixes.push(Fix::Adopt { uuid, kind, message_id }),
+        // 0 marks the listings skipped for lack of information
+        (Some(0), None) if lifecycle::is_open(&record.status) => {
+            if record.to_listing().has_sufficient_info() {
+                fixes.push(Fix::RetrySkipped { uuid });
+            } else {
+                fixes.push(Fix::StillSkipped { uuid });
+            }
+        }
+        (Some(0), None) => {}
+        (Some(_), None) if needs_message(record, kind) => fixes.push(Fix::Repost { uuid, kind }),
+        (Some(_), None) => fixes.push(Fix::Clear { uuid, kind }),
+        // Not posted yet: the scraping cycle takes care of new listings
+        (None, None) if kind == MessageKind::Main => {}
+        (None, None) if needs_message(record, kind) => fixes.push(Fix::Repost { uuid, kind }),
+        (None, None) => {}
+    }
+    fixes
+}
+
+/// Compare the listings of the database with the listing messages found on Discord
+/// `records` must contain every posted listing and the listings of the found messages that are in the database
+pub fn plan(records: &[ListingRecord], found: &[FoundMessage]) -> Vec<Fix> {
+    let mut by_listing: HashMap<(Uuid, MessageKind), Vec<FoundMessage>> = HashMap::new();
+    for message in found {
+        by_listing.entry((message.uuid, message.kind)).or_default().push(*message);
+    }
+    for messages in by_listing.values_mut() {
+        messages.sort_by_key(|message| message.message_id);
+    }
+
+    let mut fixes = Vec::new();
+    for record in records {
+        for kind in [MessageKind::Main, MessageKind::Interesting] {
+            let messages = by_listing.remove(&(record.uuid, kind)).unwrap_or_default();
+            fixes.extend(plan_listing(record, kind, &messages));
+        }
+    }
+
+    // What is left belongs to listings the database doesn't know
+    let mut orphans: Vec<FoundMessage> = by_listing.into_values().flatten().collect();
+    orphans.sort_by_key(|message| message.message_id);
+    fixes.extend(orphans.into_iter().map(|message| Fix::Orphan {
+        channel_id: message.channel_id,
+        message_id: message.message_id,
+        uuid: message.uuid,
+    }));
+    fixes
+}
+
+/// Outcome of a reconciliation pass
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    pub listings_checked: usize,
+    pub messages_found: usize,
+    pub fixes: Vec<Fix>,
+    /// Fixes that could not be applied
+    pub failures: usize,
+}
+
+impl ReconcileReport {
+    fn count(&self, predicate: impl Fn(&Fix) -> bool) -> usize {
+        self.fixes.iter().filter(|fix| predicate(fix)).count()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} listing(s) and {} message(s) checked: {} adopted, {} reposted, {} cleared, {} extra deleted, {} orphan(s), {} skipped listing(s) posted",
+            self.listings_checked,
+            self.messages_found,
+            self.count(|fix| matches!(fix, Fix::Adopt { .. })),
+            self.count(|fix| matches!(fix, Fix::Repost { .. })),
+            self.count(|fix| matches!(fix, Fix::Clear { .. })),
+            self.count(|fix| matches!(fix, Fix::Delete { .. })),
+            self.count(|fix| matches!(fix, Fix::Orphan { .. })),
+            self.count(|fix| matches!(fix, Fix::RetrySkipped { .. })),
+        );
+        let still_skipped = self.count(|fix| matches!(fix, Fix::StillSkipped { .. }));
+        if still_skipped > 0 {
+            summary.push_str(&format!(", {} still skipped for lack of information", still_skipped));
+        }
+        if self.failures > 0 {
+            summary.push_str(&format!(", {} failure(s)", self.failures));
+        }
+        summary
+    }
+}
+
+/// UUID of a listing message, None for the other messages of the bot (price drops, reminders, command answers)
+fn listing_message_uuid(message: &Message) -> Option<Uuid> {
+    if message.kind != MessageType::Regular {
+        return None;
+    }
+    let title = message.embeds.first()?.title.as_deref().unwrap_or_default();
+    if title.starts_with(PRICE_DROP_TITLE_PREFIX) {
+        return None;
+    }
+    footer_uuid(message)
+}
+
+/// Every listing message of the bot in a channel, going through the whole history
+async fn scan_channel(http: &Http, channel_id: u64, kind: MessageKind, bot_id: UserId) -> Result<Vec<FoundMessage>, serenity::Error> {
+    let channel = ChannelId::new(channel_id);
+    let mut found = Vec::new();
+    let mut last_message_id = None;
+
+    loop {
+        let request = match last_message_id {
+            Some(before_id) => GetMessages::new().before(before_id).limit(100),
+            None => GetMessages::new().limit(100),
+        };
+        let messages = channel.messages(http, request).await?;
+        if messages.is_empty() {
+            break;
+        }
+        last_message_id = messages.last().map(|message| message.id);
+
+        found.extend(messages.iter()
+            .filter(|message| message.author.id == bot_id)
+            .filter_map(|message| Some(FoundMessage {
+                kind,
+                channel_id,
+                message_id: message.id.get(),
+                uuid: listing_message_uuid(message)?,
+            })));
+    }
+
+    Ok(found)
+}
+
+async fn apply_fix(
+    http: &Http,
+    database: &Arc<Mutex<Database>>,
+    fix: Fix,
+    channels: ListingChannels,
+    delete_orphans: bool,
+) -> anyhow::Result<()> {
+    match fix {
+        Fix::Adopt { uuid, kind: MessageKind::Main, message_id } => database.lock().await.set_main_channel_message_id(&uuid, message_id)?,
+        Fix::Adopt { uuid, kind: MessageKind::Interesting, message_id } => database.lock().await.set_interesting_channel_message_id(&uuid, message_id)?,
+        Fix::Delete { channel_id, message_id } => ChannelId::new(channel_id).delete_message(http, message_id).await?,
+        Fix::Orphan { channel_id, message_id, uuid } => {
+            tracing::warn!("Message {} in channel {} shows listing {}, which is not in the database", message_id, channel_id, uuid);
+            if delete_orphans {
+                ChannelId::new(channel_id).delete_message(http, message_id).await?;
+            }
+        }
+        Fix::Repost { uuid, kind: MessageKind::Main } | Fix::RetrySkipped { uuid } => {
+            post_listing_message(http, database, uuid, MessageKind::Main, channels).await?;
+        }
+        Fix::Repost { uuid, kind: MessageKind::Interesting } => {
+            database.lock().await.clear_interesting_channel_message_id(&uuid)?;
+            post_listing_message(http, database, uuid, MessageKind::Interesting, channels).await?;
+        }
+        // 0 keeps the scraping cycle from posting it again
+        Fix::Clear { uuid, kind: MessageKind::Main } => database.lock().await.set_main_channel_message_id(&uuid, 0)?,
+        Fix::Clear { uuid, kind: MessageKind::Interesting } => database.lock().await.clear_interesting_channel_message_id(&uuid)?,
+        Fix::StillSkipped { uuid } => tracing::info!("Listing {} is still skipped for lack of information", uuid),
+    }
+    Ok(())
+}
+
+/// Bring the database and the listing messages on Discord back in line: adopt messages whose id was
+/// never stored, re-post or clear deleted ones, remove extra copies and report messages of unknown listings
+pub async fn reconcile(
+    http: &Http,
+    database: &Arc<Mutex<Database>>,
+    channels: ListingChannels,
+    delete_orphans: bool,
+) -> anyhow::Result<ReconcileReport> {
+    let bot_id = http.get_current_user().await?.id;
+
+    let mut scanned = HashSet::new();
+    let mut found = Vec::new();
+    let channel_kinds = [
+        (channels.main, MessageKind::Main),
+        (channels.quarantine, MessageKind::Main),
+        (channels.interesting, MessageKind::Interesting),
+    ];
+    for (channel_id, kind) in channel_kinds {
+        let Some(channel_id) = channel_id.filter(|id| scanned.insert(*id)) else {
+            continue;
+        };
+        // A partial scan would make every message it missed look deleted
+        found.extend(scan_channel(http, channel_id, kind, bot_id).await?);
+    }
+
+    let records = {
+        let db = database.lock().await;
+        let mut records = db.get_posted_listings()?;
+        let mut known: HashSet<Uuid> = records.iter().map(|record| record.uuid).collect();
+        for message in &found {
+            if known.insert(message.uuid) {
+                if let Some(record) = db.get_listing_by_uuid(&message.uuid)? {
+                    records.push(record);
+                }
+            }
+        }
+        records
+    };
+
+    let fixes = plan(&records, &found);
+    let mut report = ReconcileReport {
+        listings_checked: records.len(),
+        messages_found: found.len(),
+        fixes: fixes.clone(),
+        failures: 0,
+    };
+
+    let mut refreshed = HashSet::new();
+    for fix in fixes {
+        tracing::debug!("Reconciliation: {:?}", fix);
+        if let Err(e) = apply_fix(http, database, fix, channels, delete_orphans).await {
+            tracing::error!("Failed to apply {:?}: {}", fix, e);
+            report.failures += 1;
+            continue;
+        }
+        // Adopted messages may have been posted with outdated buttons
+        if let Fix::Adopt { uuid, .. } = fix {
+            if refreshed.insert(uuid) {
+                if let Err(e) = refresh_listing_messages(http, database, uuid, channels).await {
+                    tracing::error!("Failed to refresh adopted listing {}: {}", uuid, e);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Answer the /reconcile command once the reconciliation is done
+pub async fn handle_reconcile_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    database: Arc<Mutex<Database>>,
+    channels: ListingChannels,
+) -> Result<(), serenity::Error> {
+    let delete_orphans = command.data.options().into_iter().any(|option| {
+        option.name == "delete_orphans" && matches!(option.value, ResolvedValue::Boolean(true))
+    });
+
+    // Going through the channel history can take longer than the 3s Discord gives to answer
+    command.create_response(&ctx.http, CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content("🔍 Comparing the listing messages with the database...")
+            .ephemeral(true)
+    )).await?;
+
+    let content = match reconcile(&ctx.http, &database, channels, delete_orphans).await {
+        Ok(report) => {
+            tracing::info!("Reconciliation: {}", report.summary());
+            format!("✅ {}", report.summary())
+        }
+        Err(e) => {
+            tracing::error!("Reconciliation failed: {}", e);
+            format!("❌ Reconciliation failed: {}", e)
+        }
+    };
+    command.edit_response(&ctx.http, EditInteractionResponse::new().content(content)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const MAIN_CHANNEL: u64 = 1;
+    const INTERESTING_CHANNEL: u64 = 2;
+
+    fn record(n: u128, status: ListingStatus, main: Option<u64>, interesting: Option<u64>) -> ListingRecord {
+        ListingRecord {
+            uuid: Uuid::from_u128(n),
+            listing_id: format!("leboncoin_{}", n),
+            title: "Appartement T2 45m²".to_string(),
+            price: Some(900.0),
+            surface: Some(45.0),
+            location: "Rennes".to_string(),
+            url: format!("https://example.com/{}", n),
+            image_url: None,
+            description: None,
+            posted_at: Utc::now(),
+            source: "Leboncoin".to_string(),
+            status,
+            scraped_at: Utc::now(),
+            main_channel_message_id: main,
+            interesting_channel_message_id: interesting,
+            coordinates: None,
+        }
+    }
+
+    fn found(n: u128, kind: MessageKind, message_id: u64) -> FoundMessage {
+        let channel_id = match kind {
+            MessageKind::Main => MAIN_CHANNEL,
+            MessageKind::Interesting => INTERESTING_CHANNEL,
+        };
+        FoundMessage { kind, channel_id, message_id, uuid: Uuid::from_u128(n) }
+    }
+
+    #[test]
+    fn test_matching_messages_need_nothing() {
+        let records = [record(1, ListingStatus::Interesting, Some(10), Some(20))];
+        let found = [found(1, MessageKind::Main, 10), found(1, MessageKind::Interesting, 20)];
+        assert_eq!(plan(&records, &found), vec![]);
+    }
+
+    #[test]
+    fn test_message_posted_without_stored_id_is_adopted() {
+        // The bot stopped between posting the message and storing its id, then posted it again
+        let records = [
+            record(1, ListingStatus::Unchecked, None, None),
+            record(2, ListingStatus::Unchecked, Some(12), None),
+        ];
+        let found = [
+            found(1, MessageKind::Main, 10),
+            found(2, MessageKind::Main, 11),
+            found(2, MessageKind::Main, 12),
+        ];
+        assert_eq!(plan(&records, &found), vec![
+            Fix::Adopt { uuid: Uuid::from_u128(1), kind: MessageKind::Main, message_id: 10 },
+            Fix::Delete { channel_id: MAIN_CHANNEL, message_id: 11 },
+        ]);
+    }
+
+    #[test]
+    fn test_deleted_messages_are_reposted_or_cleared() {
+        let records = [
+            record(1, ListingStatus::Contacted, Some(10), Some(20)),
+            record(2, ListingStatus::NotGood, Some(11), None),
+            record(3, ListingStatus::Unchecked, Some(12), Some(21)),
+        ];
+        assert_eq!(plan(&records, &[]), vec![
+            Fix::Repost { uuid: Uuid::from_u128(1), kind: MessageKind::Main },
+            Fix::Repost { uuid: Uuid::from_u128(1), kind: MessageKind::Interesting },
+            Fix::Clear { uuid: Uuid::from_u128(2), kind: MessageKind::Main },
+            Fix::Repost { uuid: Uuid::from_u128(3), kind: MessageKind::Main },
+            Fix::Clear { uuid: Uuid::from_u128(3), kind: MessageKind::Interesting },
+        ]);
+    }
+
+    #[test]
+    fn test_interesting_copy_follows_the_status() {
+        let records = [
+            // Sent back to unchecked but its interesting copy is still there
+            record(1, ListingStatus::Unchecked, Some(10), None),
+            // Interesting without a copy in the interesting channel
+            record(2, ListingStatus::Interesting, Some(11), None),
+            // Expired listings keep their copy
+            record(3, ListingStatus::Expired, Some(12), None),
+        ];
+        let found = [
+            found(1, MessageKind::Main, 10),
+            found(1, MessageKind::Interesting, 20),
+            found(2, MessageKind::Main, 11),
+            found(3, MessageKind::Main, 12),
+            found(3, MessageKind::Interesting, 22),
+        ];
+        assert_eq!(plan(&records, &found), vec![
+            Fix::Delete { channel_id: INTERESTING_CHANNEL, message_id: 20 },
+            Fix::Repost { uuid: Uuid::from_u128(2), kind: MessageKind::Interesting },
+            Fix::Adopt { uuid: Uuid::from_u128(3), kind: MessageKind::Interesting, message_id: 22 },
+        ]);
+    }
+
+    #[test]
+    fn test_unknown_uuids_are_orphans() {
+        let records = [record(1, ListingStatus::Unchecked, Some(10), None)];
+        let found = [found(1, MessageKind::Main, 10), found(9, MessageKind::Interesting, 20)];
+        assert_eq!(plan(&records, &found), vec![
+            Fix::Orphan { channel_id: INTERESTING_CHANNEL, message_id: 20, uuid: Uuid::from_u128(9) },
+        ]);
+    }
+
+    #[test]
+    fn test_skipped_listings() {
+        let mut incomplete = record(2, ListingStatus::Unchecked, Some(0), None);
+        incomplete.price = None;
+        incomplete.surface = None;
+        let records = [
+            record(1, ListingStatus::Unchecked, Some(0), None),
+            incomplete,
+            record(3, ListingStatus::NotGood, Some(0), None),
+        ];
+        assert_eq!(plan(&records, &[]), vec![
+            Fix::RetrySkipped { uuid: Uuid::from_u128(1) },
+            Fix::StillSkipped { uuid: Uuid::from_u128(2) },
+        ]);
+    }
+}
@@ -0,0 +1,298 @@
+use serenity::all::{Colour, CreateActionRow, CreateEmbed, CreateEmbedFooter, Timestamp};
+use chrono::{DateTime, Utc};
+use crate::database::{Database, ListingNote, ListingRecord, ListingStatus};
+use crate::image_hash::RepostSummary;
+use crate::lifecycle;
+use crate::scam::ScamReport;
+use crate::visits::Visit;
+use crate::votes::VoteTally;
+use super::{button_rows, notes, visits};
+
+/// Name of the embed field listing the duplicates of a listing
+const ALSO_ON_FIELD_NAME: &str = "🔁 Aussi sur";
+/// Name of the embed field holding the price
+const PRICE_FIELD_NAME: &str = "💰 Prix";
+/// Name of the embed field with the member votes
+const VOTES_FIELD_NAME: &str = "🗳️ Votes";
+/// Descriptions are shortened to keep the messages readable
+const DESCRIPTION_PREVIEW_CHARS: usize = 300;
+
+/// Which of the two messages of a listing is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Main,
+    Interesting,
+}
+
+/// Everything shown on a listing message besides the stored listing itself
+#[derive(Debug, Clone)]
+pub struct ListingContext {
+    pub kind: MessageKind,
+    pub voting_enabled: bool,
+    pub votes: VoteTally,
+    pub notes: Vec<ListingNote>,
+    pub visit: Option<Visit>,
+    /// (source, url) of the duplicates of this listing
+    pub duplicate_sources: Vec<(String, String)>,
+    /// Price before the last change, shown struck through
+    pub previous_price: Option<f64>,
+    pub disappeared_at: Option<DateTime<Utc>>,
+    pub scam_warning: Option<ScamReport>,
+    pub reposts: Option<RepostSummary>,
+}
+
+impl ListingContext {
+    /// Load what the main channel message of a listing shows
+    pub fn load(db: &Database, record: &ListingRecord, voting_enabled: bool) -> anyhow::Result<Self> {
+        let (scam_warning, reposts) = db.get_listing_annotations(&record.uuid)?;
+        Ok(Self {
+            kind: MessageKind::Main,
+            voting_enabled,
+            votes: db.get_vote_tally(&record.uuid)?,
+            notes: db.get_notes(&record.uuid)?,
+            visit: db.get_latest_visit(&record.uuid)?,
+            duplicate_sources: db.get_duplicate_sources(&record.uuid)?,
+            previous_price: db.get_previous_price(&record.uuid)?,
+            disappeared_at: db.get_disappeared_at(&record.uuid)?,
+            scam_warning,
+            reposts,
+        })
+    }
+
+    pub fn for_kind(&self, kind: MessageKind) -> Self {
+        Self { kind, ..self.clone() }
+    }
+}
+
+/// "Il y a 5 min", relative to when the listing was first seen
+fn format_age(record: &ListingRecord) -> String {
+    let duration = record.scraped_at.signed_duration_since(record.posted_at);
+    if duration.num_minutes() < 1 {
+        "À l'instant".to_string()
+    } else if duration.num_minutes() < 60 {
+        format!("Il y a {} min", duration.num_minutes())
+    } else if duration.num_hours() < 24 {
+        format!("Il y a {} h", duration.num_hours())
+    } else {
+        format!("Il y a {} j", duration.num_days())
+    }
+}
+
+fn format_price(price: f64, previous_price: Option<f64>) -> String {
+    match previous_price {
+        Some(previous) if previous.round() != price.round() => format!("~~{:.0}€~~ **{:.0}€**", previous, price),
+        _ => format!("**{:.0}€**", price),
+    }
+}
+
+fn format_description(record: &ListingRecord, context: &ListingContext) -> Option<String> {
+    let description = record.description.as_ref().map(|description| {
+        if description.chars().count() > DESCRIPTION_PREVIEW_CHARS {
+            format!("{}...", description.chars().take(DESCRIPTION_PREVIEW_CHARS).collect::<String>())
+        } else {
+            description.clone()
+        }
+    });
+
+    let banner = match (&record.status, context.disappeared_at) {
+        (ListingStatus::Expired, Some(disappeared_at)) => {
+            Some(format!("❌ **Annonce retirée** le {}", disappeared_at.format("%d/%m/%Y à %H:%M")))
+        }
+        _ => None,
+    };
+
+    match (banner, description) {
+        (Some(banner), Some(description)) => Some(format!("{}\n\n{}", banner, description)),
+        (banner, description) => banner.or(description),
+    }
+}
+
+/// Build a listing message from the database: embed coloured by status, fields and the buttons
+/// of the next allowed steps
+pub fn render_listing_embed(record: &ListingRecord, context: &ListingContext) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let (r, g, b) = lifecycle::colour(&record.status);
+    let mut embed = CreateEmbed::new()
+        .title(&record.title)
+        .url(&record.url)
+        .color(Colour::from_rgb(r, g, b));
+
+    // Listings marked "Pas bien" are shown without their photo
+    if let Some(image_url) = record.image_url.as_ref().filter(|_| record.status != ListingStatus::NotGood) {
+        embed = embed.image(image_url);
+    }
+
+    if let Some(price) = record.price {
+        embed = embed.field(PRICE_FIELD_NAME, format_price(price, context.previous_price), true);
+    }
+    if let Some(surface) = record.surface {
+        embed = embed.field("📐 Surface", format!("**{:.0}m²**", surface), true);
+    }
+    let posted = format!("{}\n({})", format_age(record), record.posted_at.format("%d/%m/%Y à %H:%M"));
+    embed = embed.field("🕐 Publié", posted, true);
+
+    // Earlier postings of the same photo
+    if let Some(reposts) = &context.reposts {
+        embed = embed.field("🔁 Republiée", reposts.describe(record.scraped_at), false);
+    }
+    if let Some(report) = &context.scam_warning {
+        embed = embed.field(format!("⚠️ Annonce suspecte (score {})", report.score), report.format_reasons(), false);
+    }
+    if !context.duplicate_sources.is_empty() {
+        let also_on = context.duplicate_sources.iter()
+            .map(|(source, url)| format!("[{}]({})", source, url))
+            .collect::<Vec<_>>()
+            .join(", ");
+        embed = embed.field(ALSO_ON_FIELD_NAME, also_on, false);
+    }
+    if !context.votes.is_empty() {
+        embed = embed.field(VOTES_FIELD_NAME, context.votes.format(), false);
+    }
+    if !context.notes.is_empty() {
+        embed = embed.field(notes::NOTES_FIELD_NAME, notes::format_latest_notes(&context.notes), false);
+    }
+    if let Some(visit) = &context.visit {
+        embed = embed.field(visits::VISIT_FIELD_NAME, visits::format_visit(visit), false);
+    }
+
+    if let Some(description) = format_description(record, context) {
+        embed = embed.description(description);
+    }
+
+    embed = embed
+        .timestamp(Timestamp::from_unix_timestamp(record.posted_at.timestamp()).unwrap_or_else(|_| Timestamp::now()))
+        .footer(CreateEmbedFooter::new(format!("Source: {} | ID: {}", record.source, record.uuid)));
+
+    let buttons = match context.kind {
+        MessageKind::Main => lifecycle::main_channel_buttons(&record.status, context.voting_enabled),
+        MessageKind::Interesting => lifecycle::interesting_channel_buttons(&record.status),
+    };
+
+    (embed, button_rows(buttons, record.uuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+    use crate::votes::Vote;
+
+    fn record(status: ListingStatus) -> ListingRecord {
+        ListingRecord {
+            uuid: Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap(),
+            listing_id: "leboncoin_1".to_string(),
+            title: "T3 lumineux avec balcon".to_string(),
+            price: Some(820.0),
+            surface: Some(64.0),
+            location: "Rennes".to_string(),
+            url: "https://www.leboncoin.fr/ad/locations/1".to_string(),
+            image_url: Some("https://img.leboncoin.fr/1.jpg".to_string()),
+            description: Some("Bel appartement refait à neuf, proche métro.".to_string()),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap(),
+            source: "Leboncoin".to_string(),
+            status,
+            scraped_at: Utc.with_ymd_and_hms(2025, 3, 1, 9, 25, 0).unwrap(),
+            main_channel_message_id: Some(1),
+            interesting_channel_message_id: None,
+            coordinates: None,
+        }
+    }
+
+    fn context(kind: MessageKind) -> ListingContext {
+        let mut votes = VoteTally::default();
+        votes.add(Vote::Yes, 11);
+        votes.add(Vote::Maybe, 12);
+
+        ListingContext {
+            kind,
+            voting_enabled: false,
+            votes,
+            notes: vec![ListingNote {
+                user_id: 11,
+                author: "alice".to_string(),
+                content: "Propriétaire joignable le soir".to_string(),
+                created_at: Utc.with_ymd_and_hms(2025, 3, 1, 18, 0, 0).unwrap(),
+            }],
+            visit: Some(Visit {
+                id: 1,
+                uuid: Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap(),
+                scheduled_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+                attendees: vec![11, 12],
+                created_by: 11,
+                reminded_day_before: true,
+                reminded_hour_before: true,
+                rating_requested: true,
+                rating: Some(4),
+            }),
+            duplicate_sources: vec![("SeLoger".to_string(), "https://www.seloger.com/1".to_string())],
+            previous_price: Some(850.0),
+            disappeared_at: Some(Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap()),
+            scam_warning: None,
+            reposts: Some(RepostSummary {
+                previous_postings: 1,
+                first_posted_at: Utc.with_ymd_and_hms(2025, 2, 20, 9, 0, 0).unwrap(),
+                previous_price: Some(850.0),
+            }),
+        }
+    }
+
+    fn status_name(status: &ListingStatus) -> String {
+        format!("{:?}", status).to_lowercase()
+    }
+
+    /// Compare with the stored snapshot, or write it when UPDATE_SNAPSHOTS is set
+    fn assert_snapshot(name: &str, rendered: (CreateEmbed, Vec<CreateActionRow>)) {
+        let (embed, components) = rendered;
+        let value = serde_json::json!({ "embed": embed, "components": components });
+        let actual = format!("{}\n", serde_json::to_string_pretty(&value).unwrap());
+        let path = format!("{}/src/bot/snapshots/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("missing snapshot {}, run the tests with UPDATE_SNAPSHOTS=1", path));
+        assert_eq!(actual, expected, "{} changed, run the tests with UPDATE_SNAPSHOTS=1 if this is expected", name);
+    }
+
+    #[test]
+    fn test_render_main_message_per_status() {
+        for status in ListingStatus::ALL {
+            let name = format!("main_{}", status_name(&status));
+            assert_snapshot(&name, render_listing_embed(&record(status), &context(MessageKind::Main)));
+        }
+    }
+
+    #[test]
+    fn test_render_interesting_message_per_status() {
+        for status in ListingStatus::ALL.into_iter().filter(lifecycle::shown_in_interesting_channel) {
+            let name = format!("interesting_{}", status_name(&status));
+            assert_snapshot(&name, render_listing_embed(&record(status), &context(MessageKind::Interesting)));
+        }
+    }
+
+    #[test]
+    fn test_render_with_voting_and_scam_warning() {
+        let context = ListingContext {
+            voting_enabled: true,
+            votes: VoteTally::default(),
+            notes: vec![],
+            visit: None,
+            duplicate_sources: vec![],
+            previous_price: None,
+            reposts: None,
+            scam_warning: Some(ScamReport { score: 4, reasons: vec!["Prix 40% sous la moyenne".to_string()] }),
+            ..context(MessageKind::Main)
+        };
+        assert_snapshot("main_voting_scam_warning", render_listing_embed(&record(ListingStatus::Unchecked), &context));
+    }
+
+    #[test]
+    fn test_long_description_is_shortened() {
+        let mut record = record(ListingStatus::Unchecked);
+        record.description = Some("é".repeat(400));
+        let description = format_description(&record, &context(MessageKind::Main)).unwrap();
+        assert_eq!(description.chars().count(), DESCRIPTION_PREVIEW_CHARS + 3);
+    }
+}
@@ -0,0 +1,289 @@
+use serenity::all::{
+    ButtonStyle, Colour, CommandInteraction, CommandOptionType, ComponentInteraction, Context,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::database::{Database, SearchFilters, SearchHit};
+use crate::lifecycle;
+
+/// Number of results shown on each page
+const RESULTS_PER_PAGE: usize = 5;
+/// Prefix of the custom_id of the pagination buttons
+const CUSTOM_ID_PREFIX: &str = "search:";
+/// Maximum lengths of the options, so that a request always fits in a custom_id (100 chars)
+const MAX_QUERY_LENGTH: u16 = 50;
+const MAX_CITY_LENGTH: u16 = 30;
+
+/// A /search request, stored in the custom_id of the pagination buttons
+/// so that pages can still be browsed after a restart
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchRequest {
+    pub query: String,
+    pub city: Option<String>,
+    pub days: Option<u32>,
+    pub page: usize,
+}
+
+impl SearchRequest {
+    /// Encode as "search:<page>:<days>:<city>:<query>" (the query is last as it may contain ':')
+    pub fn to_custom_id(&self) -> String {
+        format!(
+            "{}{}:{}:{}:{}",
+            CUSTOM_ID_PREFIX,
+            self.page,
+            self.days.map(|d| d.to_string()).unwrap_or_default(),
+            self.city.as_deref().unwrap_or_default().replace(':', " "),
+            self.query
+        )
+    }
+
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.strip_prefix(CUSTOM_ID_PREFIX)?.splitn(4, ':');
+        let page = parts.next()?.parse().ok()?;
+        let days = match parts.next()? {
+            "" => None,
+            days => Some(days.parse().ok()?),
+        };
+        let city = Some(parts.next()?).filter(|c| !c.is_empty()).map(str::to_string);
+        let query = parts.next()?.to_string();
+
+        Some(Self { query, city, days, page })
+    }
+
+    fn filters(&self) -> SearchFilters {
+        SearchFilters {
+            location: self.city.clone(),
+            posted_after: self.days.map(|days| Utc::now() - Duration::days(days as i64)),
+            status: None,
+            limit: RESULTS_PER_PAGE,
+            offset: self.page * RESULTS_PER_PAGE,
+        }
+    }
+}
+
+/// Whether a component custom_id belongs to the search pagination buttons
+pub fn is_search_custom_id(custom_id: &str) -> bool {
+    custom_id.starts_with(CUSTOM_ID_PREFIX)
+}
+
+/// Definition of the /search slash command
+pub fn command() -> CreateCommand {
+    CreateCommand::new("search")
+        .description("Search stored listings by keyword")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "query", "Words to look for, e.g. balcon parking")
+                .required(true)
+                .max_length(MAX_QUERY_LENGTH),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "city", "Only listings in this city")
+                .max_length(MAX_CITY_LENGTH),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "days", "Only listings posted in the last N days")
+                .min_int_value(1)
+                .max_int_value(365),
+        )
+}
+
+/// One line per hit with its matching excerpt
+fn format_hit(index: usize, hit: &SearchHit) -> String {
+    let price = hit.price.map(|p| format!("{:.0}€", p)).unwrap_or_else(|| "?€".to_string());
+    let snippet = hit.snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!(
+        "{} **{}. [{}]({})**\n{} · {} · {}\n> {}",
+        lifecycle::emoji(&hit.status),
+        index,
+        hit.title,
+        hit.url,
+        price,
+        hit.location,
+        hit.posted_at.format("%d/%m/%Y"),
+        snippet
+    )
+}
+
+/// Build the embed and pagination buttons of a results page
+fn render_page(request: &SearchRequest, hits: &[SearchHit], total: usize) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let page_count = total.div_ceil(RESULTS_PER_PAGE).max(1);
+    let description = if hits.is_empty() {
+        "Aucune annonce ne correspond à cette recherche.".to_string()
+    } else {
+        hits.iter()
+            .enumerate()
+            .map(|(i, hit)| format_hit(request.page * RESULTS_PER_PAGE + i + 1, hit))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    let mut filters = Vec::new();
+    if let Some(city) = &request.city {
+        filters.push(format!("ville : {}", city));
+    }
+    if let Some(days) = request.days {
+        filters.push(format!("{} derniers jours", days));
+    }
+    let mut footer = format!("{} résultat(s) | Page {}/{}", total, request.page + 1, page_count);
+    if !filters.is_empty() {
+        footer.push_str(&format!(" | {}", filters.join(", ")));
+    }
+
+    let embed = CreateEmbed::new()
+        .title(format!("🔎 Recherche : {}", request.query))
+        .description(description)
+        .footer(CreateEmbedFooter::new(footer))
+        .color(Colour::from_rgb(52, 152, 219));
+
+    if page_count <= 1 {
+        return (embed, vec![]);
+    }
+
+    let previous = SearchRequest { page: request.page.saturating_sub(1), ..request.clone() };
+    let next = SearchRequest { page: request.page + 1, ..request.clone() };
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(previous.to_custom_id())
+            .label("◀️ Précédent")
+            .style(ButtonStyle::Secondary)
+            .disabled(request.page == 0),
+        CreateButton::new(next.to_custom_id())
+            .label("Suivant ▶️")
+            .style(ButtonStyle::Secondary)
+            .disabled(request.page + 1 >= page_count),
+    ]);
+
+    (embed, vec![buttons])
+}
+
+async fn run_search(
+    request: &SearchRequest,
+    database: &Arc<Mutex<Database>>,
+) -> anyhow::Result<(CreateEmbed, Vec<CreateActionRow>)> {
+    let results = database.lock().await.search(&request.query, &request.filters())?;
+    Ok(render_page(request, &results.hits, results.total))
+}
+
+/// Answer the /search command with the first page of results
+pub async fn handle_search_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let mut request = SearchRequest { query: String::new(), city: None, days: None, page: 0 };
+    for option in command.data.options() {
+        match (option.name, option.value) {
+            ("query", ResolvedValue::String(query)) => request.query = query.trim().to_string(),
+            ("city", ResolvedValue::String(city)) => request.city = Some(city.trim().to_string()),
+            ("days", ResolvedValue::Integer(days)) => request.days = Some(days as u32),
+            _ => {}
+        }
+    }
+
+    let message = match run_search(&request, &database).await {
+        Ok((embed, components)) => CreateInteractionResponseMessage::new().embed(embed).components(components),
+        Err(e) => {
+            tracing::error!("Search failed: {}", e);
+            CreateInteractionResponseMessage::new()
+                .content("❌ La recherche a échoué.")
+                .ephemeral(true)
+        }
+    };
+
+    command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await
+}
+
+/// Show another page of results when a pagination button is clicked
+pub async fn handle_search_page(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let Some(request) = SearchRequest::from_custom_id(&component.data.custom_id) else {
+        tracing::warn!("Invalid search custom_id: {}", component.data.custom_id);
+        return Ok(());
+    };
+
+    let response = match run_search(&request, &database).await {
+        Ok((embed, components)) => CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().embed(embed).components(components),
+        ),
+        Err(e) => {
+            tracing::error!("Search failed: {}", e);
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("❌ La recherche a échoué.")
+                    .ephemeral(true),
+            )
+        }
+    };
+
+    component.create_response(&ctx.http, response).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ListingStatus;
+
+    fn hit(title: &str) -> SearchHit {
+        SearchHit {
+            uuid: uuid::Uuid::new_v4(),
+            title: title.to_string(),
+            url: "https://www.leboncoin.fr/ad/locations/1".to_string(),
+            location: "Rennes".to_string(),
+            price: Some(750.0),
+            posted_at: chrono::DateTime::parse_from_rfc3339("2025-03-01T10:00:00Z").unwrap().with_timezone(&Utc),
+            status: ListingStatus::Interesting,
+            snippet: "appartement avec **balcon**\net parking".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        let request = SearchRequest {
+            query: "balcon: sud".to_string(),
+            city: Some("Rennes".to_string()),
+            days: Some(7),
+            page: 2,
+        };
+        assert_eq!(request.to_custom_id(), "search:2:7:Rennes:balcon: sud");
+        assert_eq!(SearchRequest::from_custom_id(&request.to_custom_id()), Some(request));
+
+        let bare = SearchRequest { query: "t2".to_string(), city: None, days: None, page: 0 };
+        assert_eq!(SearchRequest::from_custom_id(&bare.to_custom_id()), Some(bare));
+
+        assert_eq!(SearchRequest::from_custom_id("interesting_listing"), None);
+        assert_eq!(SearchRequest::from_custom_id("search:x:::t2"), None);
+    }
+
+    #[test]
+    fn test_longest_request_fits_in_custom_id() {
+        let request = SearchRequest {
+            query: "q".repeat(MAX_QUERY_LENGTH as usize),
+            city: Some("c".repeat(MAX_CITY_LENGTH as usize)),
+            days: Some(365),
+            page: 999,
+        };
+        assert!(request.to_custom_id().chars().count() <= 100);
+    }
+
+    #[test]
+    fn test_format_hit() {
+        assert_eq!(
+            format_hit(3, &hit("T2 lumineux")),
+            "⭐ **3. [T2 lumineux](https://www.leboncoin.fr/ad/locations/1)**\n750€ · Rennes · 01/03/2025\n> appartement avec **balcon** et parking"
+        );
+    }
+
+    #[test]
+    fn test_pagination_buttons() {
+        let request = SearchRequest { query: "balcon".to_string(), city: None, days: None, page: 0 };
+        let (_, components) = render_page(&request, &[hit("T2")], 1);
+        assert!(components.is_empty());
+
+        let (_, components) = render_page(&request, &[hit("T2")], RESULTS_PER_PAGE + 1);
+        assert_eq!(components.len(), 1);
+    }
+}
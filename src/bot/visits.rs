@@ -0,0 +1,459 @@
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, ChannelId, Colour, CommandInteraction, ComponentInteraction, Context,
+    CreateActionRow, CreateButton, CreateCommand, CreateEmbed, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateModal, GuildId, Http,
+    InputTextStyle, ModalInteraction,
+};
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use crate::database::{Database, ListingStatus, StatusSource};
+use crate::visits::{Visit, VisitReminder};
+use super::custom_id::{ComponentAction, ListingComponent};
+use super::{refresh_listing_messages, transition_listing, ListingChannels};
+
+/// Prefix of the modal custom_id, followed by the listing UUID
+const MODAL_ID_PREFIX: &str = "visit_modal:";
+/// Prefix of the rating buttons, followed by "<visit id>:<rating>"
+const RATING_ID_PREFIX: &str = "visit_rating:";
+const DATE_INPUT_ID: &str = "date";
+const TIME_INPUT_ID: &str = "time";
+const ATTENDEES_INPUT_ID: &str = "attendees";
+/// Name of the embed field with the visit
+pub const VISIT_FIELD_NAME: &str = "📅 Visite";
+/// Maximum number of visits listed by /visits
+const MAX_VISITS_SHOWN: usize = 15;
+
+pub fn visit_button(uuid: Uuid) -> CreateButton {
+    CreateButton::new(ListingComponent::custom_id(ComponentAction::ScheduleVisit, uuid))
+        .label("📅 Planifier une visite")
+        .style(ButtonStyle::Success)
+}
+
+pub fn is_visit_modal_id(custom_id: &str) -> bool {
+    custom_id.starts_with(MODAL_ID_PREFIX)
+}
+
+pub fn is_rating_custom_id(custom_id: &str) -> bool {
+    custom_id.starts_with(RATING_ID_PREFIX)
+}
+
+/// Definition of the /visits slash command
+pub fn command() -> CreateCommand {
+    CreateCommand::new("visits").description("List the upcoming visits")
+}
+
+/// Parse a date typed as "25/03/2025", "25/03" (this year) or "2025-03-25"
+fn parse_date(date: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let date = date.trim();
+    NaiveDate::parse_from_str(date, "%d/%m/%Y")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}/{}", date, today.year()), "%d/%m/%Y").ok())
+}
+
+/// Parse a time typed as "14:30", "14h30" or "14h"
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    let time = time.trim().to_lowercase().replace('h', ":");
+    let time = if time.ends_with(':') { format!("{}00", time) } else { time };
+    NaiveTime::parse_from_str(&time, "%H:%M").ok()
+}
+
+fn parse_schedule(date: &str, time: &str, today: NaiveDate) -> Option<NaiveDateTime> {
+    Some(parse_date(date, today)?.and_time(parse_time(time)?))
+}
+
+/// Split the attendees field into Discord user ids (mentions or raw ids) and names to look up
+fn parse_attendee_tokens(attendees: &str) -> (Vec<u64>, Vec<String>) {
+    let mention = Regex::new(r"^<@!?(\d+)>$").unwrap();
+    let mut ids = Vec::new();
+    let mut names = Vec::new();
+
+    for token in attendees.split(',').map(str::trim).filter(|token| !token.is_empty()) {
+        if let Some(captures) = mention.captures(token) {
+            ids.extend(captures[1].parse::<u64>().ok());
+        } else if let Ok(id) = token.parse::<u64>() {
+            ids.push(id);
+        } else {
+            names.push(token.trim_start_matches('@').to_string());
+        }
+    }
+
+    (ids, names)
+}
+
+/// Look up attendees typed by name among the server members
+/// Returns the resolved ids and the names that matched nobody
+async fn resolve_attendees(ctx: &Context, guild_id: Option<GuildId>, attendees: &str) -> (Vec<u64>, Vec<String>) {
+    let (mut ids, names) = parse_attendee_tokens(attendees);
+    let mut unknown = Vec::new();
+
+    for name in names {
+        let member = match guild_id {
+            Some(guild_id) => guild_id.search_members(&ctx.http, &name, Some(1)).await.ok().and_then(|m| m.into_iter().next()),
+            None => None,
+        };
+        match member {
+            Some(member) => ids.push(member.user.id.get()),
+            None => unknown.push(name),
+        }
+    }
+
+    (ids, unknown)
+}
+
+fn rating_stars(rating: u8) -> String {
+    format!("{}{} ({}/5)", "⭐".repeat(rating as usize), "☆".repeat(5 - rating.min(5) as usize), rating)
+}
+
+/// Embed field value describing a visit
+pub fn format_visit(visit: &Visit) -> String {
+    let timestamp = visit.scheduled_at.timestamp();
+    match visit.rating {
+        Some(rating) => format!("Visitée le <t:{}:D> · {}", timestamp, rating_stars(rating)),
+        None => format!("<t:{}:F> (<t:{}:R>) avec {}", timestamp, timestamp, visit.mentions()),
+    }
+}
+
+/// Open the visit modal for the listing of the clicked message
+pub async fn handle_visit_button(ctx: &Context, component: &ComponentInteraction, uuid: Option<Uuid>) -> Result<(), serenity::Error> {
+    let Some(uuid) = uuid else {
+        tracing::warn!("Visit button on a message without listing");
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let tomorrow = Local::now().date_naive().succ_opt().unwrap_or_else(|| Local::now().date_naive());
+    let inputs = vec![
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Date (JJ/MM/AAAA)", DATE_INPUT_ID)
+                .placeholder(tomorrow.format("%d/%m/%Y").to_string())
+                .max_length(10),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Heure (HH:MM)", TIME_INPUT_ID)
+                .placeholder("18:30")
+                .max_length(5),
+        ),
+        CreateActionRow::InputText(
+            CreateInputText::new(InputTextStyle::Short, "Participants (noms séparés par des virgules)", ATTENDEES_INPUT_ID)
+                .placeholder("Vide = vous uniquement")
+                .required(false)
+                .max_length(200),
+        ),
+    ];
+    let modal = CreateModal::new(format!("{}{}", MODAL_ID_PREFIX, uuid), "Planifier une visite")
+        .components(inputs);
+
+    component.create_response(&ctx.http, CreateInteractionResponse::Modal(modal)).await
+}
+
+async fn respond_ephemeral(ctx: &Context, modal: &ModalInteraction, content: impl Into<String>) -> Result<(), serenity::Error> {
+    modal.create_response(&ctx.http,
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true)
+        )
+    ).await
+}
+
+/// Store a visit submitted through the modal and show it on the listing
+pub async fn handle_visit_modal(
+    ctx: &Context,
+    modal: &ModalInteraction,
+    channels: ListingChannels,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let Some(uuid) = modal.data.custom_id.strip_prefix(MODAL_ID_PREFIX).and_then(|uuid| Uuid::parse_str(uuid).ok()) else {
+        modal.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let input = |id: &str| {
+        modal.data.components.iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == id => input.value.clone(),
+                _ => None,
+            })
+            .unwrap_or_default()
+    };
+    let (date, time, attendees) = (input(DATE_INPUT_ID), input(TIME_INPUT_ID), input(ATTENDEES_INPUT_ID));
+
+    // Dates are typed in the local time of the bot (set TZ in Docker)
+    let scheduled_at = parse_schedule(&date, &time, Local::now().date_naive())
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|local| local.with_timezone(&Utc));
+    let Some(scheduled_at) = scheduled_at else {
+        return respond_ephemeral(ctx, modal, format!("❌ Date ou heure invalide : `{}` `{}` (format JJ/MM/AAAA et HH:MM)", date, time)).await;
+    };
+    if scheduled_at <= Utc::now() {
+        return respond_ephemeral(ctx, modal, "❌ La visite doit être dans le futur.").await;
+    }
+
+    let (mut attendee_ids, unknown) = resolve_attendees(ctx, modal.guild_id, &attendees).await;
+    if attendee_ids.is_empty() {
+        attendee_ids.push(modal.user.id.get());
+    }
+    attendee_ids.sort_unstable();
+    attendee_ids.dedup();
+
+    let saved = {
+        let db = database.lock().await;
+        db.add_visit(&uuid, scheduled_at, &attendee_ids, modal.user.id.get())
+            .and_then(|id| db.get_visit(id))
+    };
+    let visit = match saved {
+        Ok(Some(visit)) => visit,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to save visit: {}", e);
+            return respond_ephemeral(ctx, modal, "❌ Impossible d'enregistrer la visite.").await;
+        }
+    };
+    tracing::info!("Visit of {} scheduled at {} by {}", uuid, scheduled_at, modal.user.name);
+
+    if let Err(e) = refresh_listing_messages(&ctx.http, &database, uuid, channels).await {
+        tracing::error!("Failed to show visit on listing {}: {}", uuid, e);
+    }
+
+    let mut confirmation = format!("📅 Visite planifiée le <t:{}:F> avec {}", scheduled_at.timestamp(), visit.mentions());
+    if !unknown.is_empty() {
+        confirmation.push_str(&format!("\n⚠️ Participants introuvables : {}", unknown.join(", ")));
+    }
+    respond_ephemeral(ctx, modal, confirmation).await
+}
+
+fn rating_buttons(visit_id: i64) -> CreateActionRow {
+    CreateActionRow::Buttons(
+        (1..=5)
+            .map(|rating| {
+                CreateButton::new(format!("{}{}:{}", RATING_ID_PREFIX, visit_id, rating))
+                    .label(format!("{} ⭐", rating))
+                    .style(ButtonStyle::Secondary)
+            })
+            .collect(),
+    )
+}
+
+fn parse_rating_custom_id(custom_id: &str) -> Option<(i64, u8)> {
+    let (visit_id, rating) = custom_id.strip_prefix(RATING_ID_PREFIX)?.split_once(':')?;
+    let rating = rating.parse().ok().filter(|rating| (1..=5).contains(rating))?;
+    Some((visit_id.parse().ok()?, rating))
+}
+
+/// Store the rating of a visit and mark the listing as visited
+pub async fn handle_rating_button(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    channels: ListingChannels,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let Some((visit_id, rating)) = parse_rating_custom_id(&component.data.custom_id) else {
+        component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+        return Ok(());
+    };
+
+    let user_id = component.user.id.get();
+    let saved = {
+        let db = database.lock().await;
+        db.rate_visit(visit_id, rating, user_id)
+            .and_then(|_| db.get_visit(visit_id))
+            .and_then(|visit| match visit {
+                Some(visit) => Ok(Some((db.get_listing_by_uuid(&visit.uuid)?, visit))),
+                None => Ok(None),
+            })
+    };
+
+    let (record, visit) = match saved {
+        Ok(Some(saved)) => saved,
+        Ok(None) => {
+            component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Failed to rate visit {}: {}", visit_id, e);
+            component.create_response(&ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("❌ Impossible d'enregistrer la note.")
+                        .ephemeral(true)
+                )
+            ).await?;
+            return Ok(());
+        }
+    };
+
+    // A rated visit took place: move the listing forward unless it is already further along
+    let transitioned = match record.as_ref().filter(|record| record.status != ListingStatus::Visited) {
+        Some(record) => {
+            let result = transition_listing(ctx, &database, visit.uuid, ListingStatus::Visited, Some(user_id), StatusSource::Button, channels).await?;
+            if let Err(invalid) = &result {
+                tracing::debug!("Not marking '{}' as visited: {}", record.title, invalid);
+            }
+            result.is_ok()
+        }
+        None => false,
+    };
+    // Show the rating
+    if !transitioned {
+        if let Err(e) = refresh_listing_messages(&ctx.http, &database, visit.uuid, channels).await {
+            tracing::warn!("Failed to show rating on listing {}: {}", visit.uuid, e);
+        }
+    }
+
+    // Keep the buttons so that the rating can be changed
+    let title = record.map(|record| record.title).unwrap_or_default();
+    component.create_response(&ctx.http,
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(format!("🏠 Visite de **{}** notée {} par <@{}>", title, rating_stars(rating), user_id))
+        )
+    ).await
+}
+
+/// Post the reminders and rating requests that are due
+/// Called every minute by the reminder task
+pub async fn send_visit_reminders(
+    http: &Arc<Http>,
+    interesting_channel_id: u64,
+    database: &Arc<Mutex<Database>>,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let due = {
+        let db = database.lock().await;
+        let mut due = Vec::new();
+        for visit in db.get_visits_awaiting_reminders()? {
+            if let Some(reminder) = visit.due_reminder(now) {
+                if let Some(record) = db.get_listing_by_uuid(&visit.uuid)? {
+                    due.push((visit, reminder, record));
+                }
+            }
+        }
+        due
+    };
+
+    let channel = ChannelId::new(interesting_channel_id);
+    for (visit, reminder, record) in due {
+        let timestamp = visit.scheduled_at.timestamp();
+        let builder = match reminder {
+            VisitReminder::DayBefore | VisitReminder::HourBefore => CreateMessage::new().content(format!(
+                "⏰ {} Visite de **[{}](<{}>)** <t:{}:R> (<t:{}:F>)",
+                visit.mentions(), record.title, record.url, timestamp, timestamp
+            )),
+            VisitReminder::RatingRequest => CreateMessage::new()
+                .content(format!(
+                    "🏠 {} Comment s'est passée la visite de **[{}](<{}>)** ?",
+                    visit.mentions(), record.title, record.url
+                ))
+                .components(vec![rating_buttons(visit.id)]),
+        };
+
+        match channel.send_message(http, builder).await {
+            Ok(_) => {
+                tracing::info!("Sent {:?} for the visit of '{}'", reminder, record.title);
+                database.lock().await.mark_visit_reminder_sent(visit.id, reminder)?;
+            }
+            Err(e) => tracing::error!("Failed to send visit reminder: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Answer the /visits command with the upcoming visits
+pub async fn handle_visits_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    database: Arc<Mutex<Database>>,
+) -> Result<(), serenity::Error> {
+    let upcoming = {
+        let db = database.lock().await;
+        db.get_upcoming_visits(Utc::now()).and_then(|visits| {
+            visits.into_iter()
+                .take(MAX_VISITS_SHOWN)
+                .map(|visit| Ok((db.get_listing_by_uuid(&visit.uuid)?, visit)))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+    };
+
+    let message = match upcoming {
+        Ok(visits) => {
+            let description = if visits.is_empty() {
+                "Aucune visite prévue.".to_string()
+            } else {
+                visits.iter()
+                    .map(|(record, visit)| {
+                        let listing = match record {
+                            Some(record) => format!("[{}]({})", record.title, record.url),
+                            None => visit.uuid.to_string(),
+                        };
+                        format!("**{}**\n{}", listing, format_visit(visit))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            };
+
+            let embed = CreateEmbed::new()
+                .title("📅 Visites à venir")
+                .description(description)
+                .color(Colour::from_rgb(46, 204, 113));
+            CreateInteractionResponseMessage::new().embed(embed)
+        }
+        Err(e) => {
+            tracing::error!("Failed to load visits: {}", e);
+            CreateInteractionResponseMessage::new()
+                .content("❌ Impossible de charger les visites.")
+                .ephemeral(true)
+        }
+    };
+
+    command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schedule() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 20).unwrap();
+        let expected = NaiveDate::from_ymd_opt(2025, 3, 25).unwrap().and_hms_opt(18, 30, 0).unwrap();
+
+        assert_eq!(parse_schedule("25/03/2025", "18:30", today), Some(expected));
+        assert_eq!(parse_schedule("2025-03-25", "18h30", today), Some(expected));
+        assert_eq!(parse_schedule(" 25/03 ", "18H30", today), Some(expected));
+        assert_eq!(
+            parse_schedule("25/03/2025", "18h", today),
+            Some(expected.date().and_hms_opt(18, 0, 0).unwrap())
+        );
+
+        assert_eq!(parse_schedule("32/03/2025", "18:30", today), None);
+        assert_eq!(parse_schedule("25/03/2025", "25:00", today), None);
+        assert_eq!(parse_schedule("demain", "18:30", today), None);
+    }
+
+    #[test]
+    fn test_parse_attendee_tokens() {
+        let (ids, names) = parse_attendee_tokens("<@123>, <@!456>, 789, @alice, Bob Martin, ");
+        assert_eq!(ids, vec![123, 456, 789]);
+        assert_eq!(names, vec!["alice", "Bob Martin"]);
+    }
+
+    #[test]
+    fn test_rating_custom_id() {
+        let buttons_id = format!("{}{}:{}", RATING_ID_PREFIX, 42, 4);
+        assert_eq!(parse_rating_custom_id(&buttons_id), Some((42, 4)));
+        assert_eq!(parse_rating_custom_id("visit_rating:42:6"), None);
+        assert_eq!(parse_rating_custom_id("visit_rating:x:3"), None);
+        assert_eq!(parse_rating_custom_id("schedule_visit"), None);
+    }
+
+    #[test]
+    fn test_rating_stars() {
+        assert_eq!(rating_stars(4), "⭐⭐⭐⭐☆ (4/5)");
+    }
+}
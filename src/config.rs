@@ -1,3 +1,5 @@
+use crate::database::ListingRecord;
+use crate::models::Coordinates;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::env;
@@ -20,6 +22,500 @@ pub struct Config {
     pub max_listing_age_minutes: u64,
     #[serde(default = "default_min_rooms")]
     pub min_rooms: u32,
+    #[serde(default = "default_true")]
+    pub price_drop_notifications: bool,
+    #[serde(default = "default_liveness_check_interval_minutes")]
+    pub liveness_check_interval_minutes: u64,
+    /// Check at startup that the listing messages on Discord match the database
+    #[serde(default = "default_true")]
+    pub reconcile_on_startup: bool,
+    #[serde(default)]
+    pub scam_detection: ScamDetectionConfig,
+    #[serde(default)]
+    pub duplicate_detection: DuplicateDetectionConfig,
+    #[serde(default)]
+    pub image_hashing: ImageHashingConfig,
+    #[serde(default)]
+    pub voting: VotingConfig,
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    #[serde(default)]
+    pub matrix: MatrixConfig,
+    /// URLs every new or updated listing is POSTed to
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub email_digest: EmailDigestConfig,
+    #[serde(default)]
+    pub push: PushConfig,
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub map: MapConfig,
+}
+
+/// Settings for the suspicious listing heuristics (see `scam.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScamDetectionConfig {
+    /// Score from which a warning field is added to the embed
+    #[serde(default = "default_scam_warning_threshold")]
+    pub warning_threshold: u32,
+    /// Score from which the listing is posted to the quarantine channel instead
+    #[serde(default = "default_scam_quarantine_threshold")]
+    pub quarantine_threshold: u32,
+    /// Channel receiving quarantined listings (0 = keep them in the main channel)
+    #[serde(default)]
+    pub quarantine_channel_id: u64,
+    /// A listing is an outlier when its price per m² is below this ratio of the city median
+    #[serde(default = "default_scam_price_outlier_ratio")]
+    pub price_outlier_ratio: f64,
+    /// Minimum number of known listings in a city before price outliers are checked
+    #[serde(default = "default_scam_min_price_samples")]
+    pub min_price_samples: usize,
+    /// Additional phrases (case-insensitive) that flag a listing, on top of the built-in list
+    #[serde(default)]
+    pub extra_phrases: Vec<String>,
+}
+
+/// Settings for the cross-source duplicate detection (see `duplicates.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateDetectionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Similarity (0.0 to 1.0) from which two listings are considered the same apartment
+    #[serde(default = "default_duplicate_similarity_threshold")]
+    pub similarity_threshold: f64,
+    /// How far back (in days) to look for the original listing
+    #[serde(default = "default_duplicate_lookback_days")]
+    pub lookback_days: u64,
+}
+
+impl Default for DuplicateDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            similarity_threshold: default_duplicate_similarity_threshold(),
+            lookback_days: default_duplicate_lookback_days(),
+        }
+    }
+}
+
+/// Settings for the listing photo download and perceptual hashing (see `image_hash.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageHashingConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Photos larger than this are not downloaded
+    #[serde(default = "default_max_image_bytes")]
+    pub max_image_bytes: usize,
+}
+
+impl Default for ImageHashingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_image_bytes: default_max_image_bytes(),
+        }
+    }
+}
+
+/// Settings for per-member voting on listings (see `votes.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VotingConfig {
+    /// Replace the "Intéressant"/"Pas bien" buttons with 👍/👎/🤷 votes
+    #[serde(default)]
+    pub enabled: bool,
+    /// Number of 👍 votes that moves a listing to the interesting channel
+    #[serde(default = "default_voting_quorum")]
+    pub quorum: usize,
+    /// Number of 👎 votes that marks a listing as not good
+    #[serde(default = "default_voting_veto_threshold")]
+    pub veto_threshold: usize,
+}
+
+impl Default for VotingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quorum: default_voting_quorum(),
+            veto_threshold: default_voting_veto_threshold(),
+        }
+    }
+}
+
+/// Settings for the Telegram notifier (see `notifiers/telegram.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramConfig {
+    /// Token given by @BotFather (empty = Telegram disabled)
+    #[serde(default)]
+    pub bot_token: String,
+    /// Chat or group the listings are posted to
+    #[serde(default)]
+    pub chat_id: i64,
+    /// Base URL of the Bot API, e.g. a local mock server in tests
+    #[serde(default = "default_telegram_api_base_url")]
+    pub api_base_url: String,
+}
+
+impl TelegramConfig {
+    pub fn is_configured(&self) -> bool {
+        !self.bot_token.is_empty() && self.chat_id != 0
+    }
+}
+
+/// Settings for the Matrix notifier (see `notifiers/matrix.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MatrixConfig {
+    /// e.g. "https://matrix.example.org" (empty = Matrix disabled)
+    #[serde(default)]
+    pub homeserver_url: String,
+    /// Access token of the bot account
+    #[serde(default)]
+    pub access_token: String,
+    /// Room the listings are posted to, e.g. "!abcdef:example.org"
+    #[serde(default)]
+    pub room_id: String,
+}
+
+impl MatrixConfig {
+    pub fn is_configured(&self) -> bool {
+        !self.homeserver_url.is_empty() && !self.access_token.is_empty() && !self.room_id.is_empty()
+    }
+}
+
+/// An outgoing webhook (see `notifiers/webhook.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// minijinja template of the JSON body, rendered with `event`, `listing`, `quarantined` and `old_price`
+    /// Defaults to {"event": ..., "listing": {...}}
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Signs the body with HMAC-SHA256 in the X-Colocfinder-Signature header
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Attempts before the event goes to the dead-letter log
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each failed attempt
+    #[serde(default = "default_webhook_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (port 587)
+    Starttls,
+    /// TLS from the start (port 465)
+    Tls,
+    /// No encryption, e.g. a local relay or a test sink
+    None,
+}
+
+/// When the digest is sent
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestSchedule {
+    Hourly,
+    /// Every day at `send_at_hour`
+    Daily,
+}
+
+/// Settings for the email digest (see `notifiers/email.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmailDigestConfig {
+    /// SMTP server (empty = digest disabled)
+    #[serde(default)]
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    #[serde(default = "default_smtp_security")]
+    pub smtp_security: SmtpSecurity,
+    #[serde(default)]
+    pub smtp_username: String,
+    #[serde(default)]
+    pub smtp_password: String,
+    /// Sender, e.g. "Colocfinder <colocfinder@example.org>"
+    #[serde(default)]
+    pub from: String,
+    #[serde(default = "default_digest_schedule")]
+    pub schedule: DigestSchedule,
+    /// Local hour of the daily digest
+    #[serde(default = "default_digest_send_at_hour")]
+    pub send_at_hour: u32,
+    #[serde(default)]
+    pub recipients: Vec<DigestRecipient>,
+}
+
+impl EmailDigestConfig {
+    pub fn is_configured(&self) -> bool {
+        !self.smtp_host.is_empty() && !self.from.is_empty() && !self.recipients.is_empty()
+    }
+}
+
+impl Default for EmailDigestConfig {
+    fn default() -> Self {
+        Self {
+            smtp_host: String::new(),
+            smtp_port: default_smtp_port(),
+            smtp_security: default_smtp_security(),
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            from: String::new(),
+            schedule: default_digest_schedule(),
+            send_at_hour: default_digest_send_at_hour(),
+            recipients: Vec::new(),
+        }
+    }
+}
+
+/// Criteria a listing must meet, e.g. to be in someone's digest (no criteria = every listing)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ListingFilter {
+    /// Only listings whose location contains one of these (empty = every city)
+    #[serde(default)]
+    pub cities: Vec<String>,
+    #[serde(default)]
+    pub max_price: Option<f64>,
+    #[serde(default)]
+    pub min_surface: Option<f64>,
+    #[serde(default)]
+    pub max_price_per_m2: Option<f64>,
+}
+
+impl ListingFilter {
+    /// Listings missing the price or the surface are not held against the criteria on them
+    pub fn matches(&self, record: &ListingRecord) -> bool {
+        let location = record.location.to_lowercase();
+        let city_matches = self.cities.is_empty()
+            || self.cities.iter().any(|city| location.contains(&city.to_lowercase()));
+        let at_most = |limit: Option<f64>, value: Option<f64>| match (limit, value) {
+            (Some(limit), Some(value)) => value <= limit,
+            _ => true,
+        };
+        let surface_matches = match (self.min_surface, record.surface) {
+            (Some(min_surface), Some(surface)) => surface >= min_surface,
+            _ => true,
+        };
+        city_matches
+            && at_most(self.max_price, record.price)
+            && at_most(self.max_price_per_m2, record.price_per_m2())
+            && surface_matches
+    }
+}
+
+/// Self-hostable push service
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PushService {
+    Ntfy,
+    Gotify,
+}
+
+/// Settings for the phone pushes of urgent listings (see `notifiers/push.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PushConfig {
+    #[serde(default = "default_push_service")]
+    pub service: PushService,
+    /// e.g. "https://ntfy.sh" or "https://gotify.example.org" (empty = pushes disabled)
+    #[serde(default)]
+    pub server_url: String,
+    /// ntfy topic (unused by Gotify)
+    #[serde(default)]
+    pub topic: String,
+    /// ntfy access token or Gotify application token
+    #[serde(default)]
+    pub token: String,
+    /// Only listings matching this filter are pushed
+    #[serde(default)]
+    pub urgent: ListingFilter,
+    /// Priority of the new urgent listings (ntfy: 1-5, Gotify: 0-10)
+    #[serde(default = "default_push_urgent_priority")]
+    pub urgent_priority: u8,
+    /// Priority of the price drops that make a listing urgent (0 = not pushed)
+    #[serde(default)]
+    pub price_drop_priority: u8,
+}
+
+impl PushConfig {
+    pub fn is_configured(&self) -> bool {
+        !self.server_url.is_empty() && (self.service == PushService::Gotify || !self.topic.is_empty())
+    }
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            service: default_push_service(),
+            server_url: String::new(),
+            topic: String::new(),
+            token: String::new(),
+            urgent: ListingFilter::default(),
+            urgent_priority: default_push_urgent_priority(),
+            price_drop_priority: 0,
+        }
+    }
+}
+
+/// Settings for the Atom feeds (see `feeds.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where the <profile>.xml files are written after each cycle
+    #[serde(default = "default_feeds_directory")]
+    pub directory: String,
+    /// Number of listings in each feed
+    #[serde(default = "default_feeds_max_entries")]
+    pub max_entries: usize,
+    /// One feed per profile; without profiles, one feed per city plus "all"
+    #[serde(default)]
+    pub profiles: Vec<FeedProfile>,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: default_feeds_directory(),
+            max_entries: default_feeds_max_entries(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// Settings for the REST API (see `api/mod.rs`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address the HTTP server listens on
+    #[serde(default = "default_api_bind_address")]
+    pub bind_address: String,
+    /// Expected in the "Authorization: Bearer <token>" header of every request
+    #[serde(default)]
+    pub token: String,
+    /// Also serve the web dashboard at the root of the server, behind the same token
+    #[serde(default = "default_true")]
+    pub dashboard: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_api_bind_address(),
+            token: String::new(),
+            dashboard: true,
+        }
+    }
+}
+
+/// How a member gets to a point of interest, to turn commute times into distances
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommuteMode {
+    Walk,
+    Bike,
+    #[default]
+    Transit,
+    Car,
+}
+
+impl CommuteMode {
+    /// Rough door-to-door average in town, stops and waits included
+    pub fn speed_kmh(self) -> f64 {
+        match self {
+            CommuteMode::Walk => 4.5,
+            CommuteMode::Bike => 14.0,
+            CommuteMode::Transit => 18.0,
+            CommuteMode::Car => 25.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CommuteMode::Walk => "à pied",
+            CommuteMode::Bike => "à vélo",
+            CommuteMode::Transit => "en transports",
+            CommuteMode::Car => "en voiture",
+        }
+    }
+}
+
+/// A place members go to every day, e.g. a workplace or a university
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PointOfInterest {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default)]
+    pub mode: CommuteMode,
+    /// A circle is drawn around the place for each of these commute times
+    #[serde(default = "default_commute_minutes")]
+    pub commute_minutes: Vec<u32>,
+}
+
+impl PointOfInterest {
+    pub fn coordinates(&self) -> Coordinates {
+        Coordinates { latitude: self.latitude, longitude: self.longitude }
+    }
+
+    /// Estimated commute from a listing, in minutes
+    pub fn commute_minutes_from(&self, coordinates: &Coordinates) -> f64 {
+        coordinates.distance_km(&self.coordinates()) / self.mode.speed_kmh() * 60.0
+    }
+}
+
+/// Settings of the map of the dashboard
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MapConfig {
+    #[serde(default)]
+    pub points_of_interest: Vec<PointOfInterest>,
+}
+
+/// A search whose matching listings get their own feed
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FeedProfile {
+    /// File name of the feed, e.g. "rennes-cheap" for data/feeds/rennes-cheap.xml
+    pub name: String,
+    #[serde(flatten)]
+    pub filter: ListingFilter,
+}
+
+/// Someone receiving the digest, with the listings they care about
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DigestRecipient {
+    pub email: String,
+    #[serde(flatten)]
+    pub filter: ListingFilter,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: String::new(),
+            chat_id: 0,
+            api_base_url: default_telegram_api_base_url(),
+        }
+    }
+}
+
+impl Default for ScamDetectionConfig {
+    fn default() -> Self {
+        Self {
+            warning_threshold: default_scam_warning_threshold(),
+            quarantine_threshold: default_scam_quarantine_threshold(),
+            quarantine_channel_id: 0,
+            price_outlier_ratio: default_scam_price_outlier_ratio(),
+            min_price_samples: default_scam_min_price_samples(),
+            extra_phrases: vec![],
+        }
+    }
 }
 
 fn default_tracing_level() -> String {
@@ -42,6 +538,102 @@ fn default_min_rooms() -> u32 {
     1 // Accept all listings by default
 }
 
+fn default_liveness_check_interval_minutes() -> u64 {
+    360 // Every 6 hours
+}
+
+fn default_scam_warning_threshold() -> u32 {
+    30
+}
+
+fn default_scam_quarantine_threshold() -> u32 {
+    60
+}
+
+fn default_scam_price_outlier_ratio() -> f64 {
+    0.5 // Half the median price per m² of the city
+}
+
+fn default_scam_min_price_samples() -> usize {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_duplicate_similarity_threshold() -> f64 {
+    0.75
+}
+
+fn default_duplicate_lookback_days() -> u64 {
+    30
+}
+
+fn default_max_image_bytes() -> usize {
+    5 * 1024 * 1024 // 5 MiB
+}
+
+fn default_voting_quorum() -> usize {
+    2
+}
+
+fn default_voting_veto_threshold() -> usize {
+    2
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    4
+}
+
+fn default_webhook_retry_delay_ms() -> u64 {
+    1000
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_security() -> SmtpSecurity {
+    SmtpSecurity::Starttls
+}
+
+fn default_digest_schedule() -> DigestSchedule {
+    DigestSchedule::Daily
+}
+
+fn default_digest_send_at_hour() -> u32 {
+    8
+}
+
+fn default_push_service() -> PushService {
+    PushService::Ntfy
+}
+
+fn default_push_urgent_priority() -> u8 {
+    5
+}
+
+fn default_feeds_directory() -> String {
+    "data/feeds".to_string()
+}
+
+fn default_feeds_max_entries() -> usize {
+    50
+}
+
+fn default_commute_minutes() -> Vec<u32> {
+    vec![15, 30]
+}
+
+fn default_api_bind_address() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_telegram_api_base_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         // Try to load from file first, or use defaults
@@ -63,6 +655,21 @@ impl Config {
                 request_delay_ms: default_request_delay_ms(),
                 max_listing_age_minutes: default_max_listing_age_minutes(),
                 min_rooms: default_min_rooms(),
+                price_drop_notifications: true,
+                liveness_check_interval_minutes: default_liveness_check_interval_minutes(),
+                reconcile_on_startup: true,
+                scam_detection: ScamDetectionConfig::default(),
+                duplicate_detection: DuplicateDetectionConfig::default(),
+                image_hashing: ImageHashingConfig::default(),
+                voting: VotingConfig::default(),
+                telegram: TelegramConfig::default(),
+                matrix: MatrixConfig::default(),
+                webhooks: Vec::new(),
+                email_digest: EmailDigestConfig::default(),
+                push: PushConfig::default(),
+                feeds: FeedsConfig::default(),
+                api: ApiConfig::default(),
+                map: MapConfig::default(),
             }
         };
 
@@ -117,11 +724,98 @@ impl Config {
                 .context("Failed to parse MIN_ROOMS environment variable")?;
         }
 
+        if let Ok(price_drop_notifications) = env::var("PRICE_DROP_NOTIFICATIONS") {
+            config.price_drop_notifications = price_drop_notifications.parse()
+                .context("Failed to parse PRICE_DROP_NOTIFICATIONS environment variable")?;
+        }
+
+        if let Ok(liveness_interval) = env::var("LIVENESS_CHECK_INTERVAL_MINUTES") {
+            config.liveness_check_interval_minutes = liveness_interval.parse()
+                .context("Failed to parse LIVENESS_CHECK_INTERVAL_MINUTES environment variable")?;
+        }
+
+        if let Ok(reconcile_on_startup) = env::var("RECONCILE_ON_STARTUP") {
+            config.reconcile_on_startup = reconcile_on_startup.parse()
+                .context("Failed to parse RECONCILE_ON_STARTUP environment variable")?;
+        }
+
+        if let Ok(quarantine_channel_id) = env::var("QUARANTINE_CHANNEL_ID") {
+            config.scam_detection.quarantine_channel_id = quarantine_channel_id.parse()
+                .context("Failed to parse QUARANTINE_CHANNEL_ID environment variable")?;
+        }
+
+        if let Ok(bot_token) = env::var("TELEGRAM_BOT_TOKEN") {
+            config.telegram.bot_token = bot_token;
+        }
+
+        if let Ok(chat_id) = env::var("TELEGRAM_CHAT_ID") {
+            config.telegram.chat_id = chat_id.parse()
+                .context("Failed to parse TELEGRAM_CHAT_ID environment variable")?;
+        }
+
+        if let Ok(homeserver_url) = env::var("MATRIX_HOMESERVER_URL") {
+            config.matrix.homeserver_url = homeserver_url;
+        }
+
+        if let Ok(access_token) = env::var("MATRIX_ACCESS_TOKEN") {
+            config.matrix.access_token = access_token;
+        }
+
+        if let Ok(room_id) = env::var("MATRIX_ROOM_ID") {
+            config.matrix.room_id = room_id;
+        }
+
+        if let Ok(smtp_host) = env::var("SMTP_HOST") {
+            config.email_digest.smtp_host = smtp_host;
+        }
+
+        if let Ok(smtp_username) = env::var("SMTP_USERNAME") {
+            config.email_digest.smtp_username = smtp_username;
+        }
+
+        if let Ok(smtp_password) = env::var("SMTP_PASSWORD") {
+            config.email_digest.smtp_password = smtp_password;
+        }
+
+        if let Ok(server_url) = env::var("PUSH_SERVER_URL") {
+            config.push.server_url = server_url;
+        }
+
+        if let Ok(topic) = env::var("PUSH_TOPIC") {
+            config.push.topic = topic;
+        }
+
+        if let Ok(token) = env::var("PUSH_TOKEN") {
+            config.push.token = token;
+        }
+
+        if let Ok(feeds_enabled) = env::var("FEEDS_ENABLED") {
+            config.feeds.enabled = feeds_enabled.parse()
+                .context("Failed to parse FEEDS_ENABLED environment variable")?;
+        }
+
+        if let Ok(api_enabled) = env::var("API_ENABLED") {
+            config.api.enabled = api_enabled.parse()
+                .context("Failed to parse API_ENABLED environment variable")?;
+        }
+
+        if let Ok(bind_address) = env::var("API_BIND_ADDRESS") {
+            config.api.bind_address = bind_address;
+        }
+
+        if let Ok(token) = env::var("API_TOKEN") {
+            config.api.token = token;
+        }
+
         // Validate required fields
         if config.discord_token.is_empty() {
             anyhow::bail!("discord_token is required (set via data/config.yaml or DISCORD_TOKEN env var)");
         }
 
+        if config.api.enabled && config.api.token.is_empty() {
+            anyhow::bail!("api.token is required when the REST API is enabled (set via data/config.yaml or API_TOKEN env var)");
+        }
+
         if config.channel_id == 0 {
             anyhow::bail!("channel_id is required (set via data/config.yaml or CHANNEL_ID env var)");
         }
@@ -152,6 +846,21 @@ impl Config {
             request_delay_ms: 2000,
             max_listing_age_minutes: 1440, // 24 hours
             min_rooms: 1,
+            price_drop_notifications: true,
+            liveness_check_interval_minutes: default_liveness_check_interval_minutes(),
+            reconcile_on_startup: true,
+            scam_detection: ScamDetectionConfig::default(),
+            duplicate_detection: DuplicateDetectionConfig::default(),
+            image_hashing: ImageHashingConfig::default(),
+            voting: VotingConfig::default(),
+            telegram: TelegramConfig::default(),
+            matrix: MatrixConfig::default(),
+            webhooks: Vec::new(),
+            email_digest: EmailDigestConfig::default(),
+            push: PushConfig::default(),
+            feeds: FeedsConfig::default(),
+            api: ApiConfig::default(),
+            map: MapConfig::default(),
         };
 
         let config_str = serde_yaml::to_string(&default_config)?;
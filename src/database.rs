@@ -2,36 +2,164 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use uuid::Uuid;
-use crate::models::Listing;
+use crate::image_hash::RepostSummary;
+use crate::lifecycle;
+use crate::migrations::{self, Migration};
+use crate::models::{Coordinates, Listing};
+use crate::notifiers::matrix::MatrixMessage;
+use crate::notifiers::telegram::TelegramMessage;
+use crate::notifiers::webhook::DeadLetter;
+use crate::scam::ScamReport;
+use crate::visits::{self, Visit, VisitReminder};
+use crate::votes::{Vote, VoteTally};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ListingStatus {
     Unchecked,
     Interesting,
-    Verified,
+    Contacted,
+    /// Visited, with a post-visit rating (see `Visit`)
+    Visited,
+    Applied,
+    Accepted,
+    Rejected,
     NotGood,
+    Expired,
 }
 
 impl ListingStatus {
-    fn to_string(&self) -> &str {
+    pub const ALL: [ListingStatus; 9] = [
+        ListingStatus::Unchecked,
+        ListingStatus::Interesting,
+        ListingStatus::Contacted,
+        ListingStatus::Visited,
+        ListingStatus::Applied,
+        ListingStatus::Accepted,
+        ListingStatus::Rejected,
+        ListingStatus::NotGood,
+        ListingStatus::Expired,
+    ];
+
+    /// Unlike the column values, unknown names are rejected
+    pub fn parse(s: &str) -> Option<Self> {
+        ListingStatus::ALL.into_iter().find(|status| status.as_str() == s)
+    }
+
+    pub fn as_str(&self) -> &'static str {
         match self {
             ListingStatus::Unchecked => "unchecked",
             ListingStatus::Interesting => "interesting",
-            ListingStatus::Verified => "verified",
+            ListingStatus::Contacted => "contacted",
+            ListingStatus::Visited => "visited",
+            ListingStatus::Applied => "applied",
+            ListingStatus::Accepted => "accepted",
+            ListingStatus::Rejected => "rejected",
             ListingStatus::NotGood => "not_good",
+            ListingStatus::Expired => "expired",
         }
     }
 
     fn from_string(s: &str) -> Self {
         match s {
             "interesting" => ListingStatus::Interesting,
-            "verified" => ListingStatus::Verified,
+            // "verified" was the name of this status before visits were tracked
+            "contacted" => ListingStatus::Contacted,
+            "visited" | "verified" => ListingStatus::Visited,
+            "applied" => ListingStatus::Applied,
+            "accepted" => ListingStatus::Accepted,
+            "rejected" => ListingStatus::Rejected,
             "not_good" => ListingStatus::NotGood,
+            "expired" => ListingStatus::Expired,
             _ => ListingStatus::Unchecked,
         }
     }
 }
 
+/// Where a status change came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusSource {
+    Button,
+    Reaction,
+    /// Quorum or veto reached by member votes
+    Vote,
+    /// Automatic changes, e.g. listings detected as removed
+    System,
+    /// Inline buttons of the Telegram messages
+    Telegram,
+    /// Reactions in the Matrix room
+    Matrix,
+    /// REST API
+    Api,
+    /// Triage buttons of the web dashboard
+    Dashboard,
+}
+
+impl StatusSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatusSource::Button => "button",
+            StatusSource::Reaction => "reaction",
+            StatusSource::Vote => "vote",
+            StatusSource::System => "system",
+            StatusSource::Telegram => "telegram",
+            StatusSource::Matrix => "matrix",
+            StatusSource::Api => "api",
+            StatusSource::Dashboard => "dashboard",
+        }
+    }
+
+    fn from_string(s: &str) -> Self {
+        match s {
+            "button" => StatusSource::Button,
+            "reaction" => StatusSource::Reaction,
+            "vote" => StatusSource::Vote,
+            "telegram" => StatusSource::Telegram,
+            "matrix" => StatusSource::Matrix,
+            "api" => StatusSource::Api,
+            "dashboard" => StatusSource::Dashboard,
+            _ => StatusSource::System,
+        }
+    }
+}
+
+/// A recorded status change of a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEvent {
+    pub old_status: Option<ListingStatus>,
+    pub new_status: ListingStatus,
+    /// Discord user who made the change (None for automatic changes)
+    pub user_id: Option<u64>,
+    pub source: StatusSource,
+    pub created_at: DateTime<Utc>,
+}
+
+const VISIT_COLUMNS: &str = "id, uuid, scheduled_at, attendees, created_by, reminded_day_before,
+     reminded_hour_before, rating_requested, rating";
+
+fn visit_from_row(row: &rusqlite::Row) -> rusqlite::Result<Visit> {
+    Ok(Visit {
+        id: row.get(0)?,
+        uuid: Uuid::parse_str(&row.get::<_, String>(1)?).unwrap(),
+        scheduled_at: row.get(2)?,
+        attendees: visits::parse_attendees(&row.get::<_, String>(3)?),
+        created_by: row.get(4)?,
+        reminded_day_before: row.get(5)?,
+        reminded_hour_before: row.get(6)?,
+        rating_requested: row.get(7)?,
+        rating: row.get(8)?,
+    })
+}
+
+/// A note left by a member on a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListingNote {
+    pub user_id: u64,
+    /// Display name of the author when the note was written
+    pub author: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
 pub struct ListingRecord {
     pub uuid: Uuid,
     pub listing_id: String,
@@ -48,6 +176,245 @@ pub struct ListingRecord {
     pub scraped_at: DateTime<Utc>,
     pub main_channel_message_id: Option<u64>,
     pub interesting_channel_message_id: Option<u64>,
+    pub coordinates: Option<Coordinates>,
+}
+
+impl ListingRecord {
+    /// The listing as it was last scraped
+    pub fn to_listing(&self) -> Listing {
+        Listing {
+            id: self.listing_id.clone(),
+            title: self.title.clone(),
+            price: self.price,
+            surface: self.surface,
+            location: self.location.clone(),
+            url: self.url.clone(),
+            image_url: self.image_url.clone(),
+            description: self.description.clone(),
+            posted_at: self.posted_at,
+            source: self.source.clone(),
+            coordinates: self.coordinates,
+        }
+    }
+
+    pub fn price_per_m2(&self) -> Option<f64> {
+        match (self.price, self.surface) {
+            (Some(price), Some(surface)) if surface > 0.0 => Some(price / surface),
+            _ => None,
+        }
+    }
+}
+
+/// Columns selected to build a ListingRecord, in the order expected by record_from_row
+const RECORD_COLUMNS: &str = "uuid, listing_id, title, price, surface, location, url,
+    image_url, description, posted_at, source, status, scraped_at,
+    main_channel_message_id, interesting_channel_message_id, latitude, longitude";
+
+/// Coordinates stored in a latitude column followed by a longitude column
+fn coordinates_from_row(row: &rusqlite::Row, latitude_index: usize) -> rusqlite::Result<Option<Coordinates>> {
+    let latitude: Option<f64> = row.get(latitude_index)?;
+    let longitude: Option<f64> = row.get(latitude_index + 1)?;
+    Ok(latitude.zip(longitude).map(|(latitude, longitude)| Coordinates { latitude, longitude }))
+}
+
+fn record_from_row(row: &rusqlite::Row) -> rusqlite::Result<ListingRecord> {
+    Ok(ListingRecord {
+        uuid: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        listing_id: row.get(1)?,
+        title: row.get(2)?,
+        price: row.get(3)?,
+        surface: row.get(4)?,
+        location: row.get(5)?,
+        url: row.get(6)?,
+        image_url: row.get(7)?,
+        description: row.get(8)?,
+        posted_at: row.get(9)?,
+        source: row.get(10)?,
+        status: ListingStatus::from_string(&row.get::<_, String>(11)?),
+        scraped_at: row.get(12)?,
+        main_channel_message_id: row.get(13)?,
+        interesting_channel_message_id: row.get(14)?,
+        coordinates: coordinates_from_row(row, 15)?,
+    })
+}
+
+/// A field of a known listing that changed since it was last scraped
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// Result of inserting or updating a scraped listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpsertOutcome {
+    pub uuid: Uuid,
+    pub is_new: bool,
+    pub changes: Vec<FieldChange>,
+    /// Price before this scrape, set only when the price changed
+    pub previous_price: Option<f64>,
+}
+
+impl UpsertOutcome {
+    /// Old and new price if the price went down
+    pub fn price_drop(&self, listing: &Listing) -> Option<(f64, f64)> {
+        let old_price = self.previous_price?;
+        let new_price = listing.price?;
+        (new_price < old_price).then_some((old_price, new_price))
+    }
+}
+
+/// Compare a scraped listing with its stored record
+/// Optional fields missing from the scrape are not considered changed
+fn diff_listing(record: &ListingRecord, listing: &Listing) -> Vec<FieldChange> {
+    fn text_change(field: &'static str, old: &str, new: &str) -> Option<FieldChange> {
+        (old != new).then(|| FieldChange {
+            field,
+            old_value: Some(old.to_string()),
+            new_value: Some(new.to_string()),
+        })
+    }
+
+    fn optional_change<T: PartialEq + ToString>(field: &'static str, old: &Option<T>, new: &Option<T>) -> Option<FieldChange> {
+        (new.is_some() && old != new).then(|| FieldChange {
+            field,
+            old_value: old.as_ref().map(|v| v.to_string()),
+            new_value: new.as_ref().map(|v| v.to_string()),
+        })
+    }
+
+    [
+        text_change("title", &record.title, &listing.title),
+        optional_change("price", &record.price, &listing.price),
+        optional_change("surface", &record.surface, &listing.surface),
+        text_change("url", &record.url, &listing.url),
+        optional_change("image_url", &record.image_url, &listing.image_url),
+        optional_change("description", &record.description, &listing.description),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// A stored listing whose photo is similar to a given image hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageMatch {
+    pub uuid: Uuid,
+    pub listing_id: String,
+    pub location: String,
+    pub price: Option<f64>,
+    pub posted_at: DateTime<Utc>,
+    pub distance: u32,
+}
+
+/// Optional filters for full-text search
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub location: Option<String>,
+    pub posted_after: Option<DateTime<Utc>>,
+    pub status: Option<ListingStatus>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Column the listing list of the REST API and dashboard is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ListingSort {
+    #[default]
+    PostedAt,
+    Price,
+    Surface,
+    PricePerM2,
+    Location,
+    Status,
+}
+
+impl ListingSort {
+    pub const ALL: [ListingSort; 6] = [
+        ListingSort::PostedAt,
+        ListingSort::Price,
+        ListingSort::Surface,
+        ListingSort::PricePerM2,
+        ListingSort::Location,
+        ListingSort::Status,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ListingSort::PostedAt => "posted_at",
+            ListingSort::Price => "price",
+            ListingSort::Surface => "surface",
+            ListingSort::PricePerM2 => "price_per_m2",
+            ListingSort::Location => "location",
+            ListingSort::Status => "status",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        ListingSort::ALL.into_iter().find(|sort| sort.as_str() == s)
+    }
+
+    /// SQL expression sorted on
+    fn expression(self) -> &'static str {
+        match self {
+            ListingSort::PostedAt => "posted_at",
+            ListingSort::Price => "price",
+            ListingSort::Surface => "surface",
+            ListingSort::PricePerM2 => "price / NULLIF(surface, 0)",
+            ListingSort::Location => "location COLLATE NOCASE",
+            ListingSort::Status => "status",
+        }
+    }
+}
+
+/// Filters of the listing list of the REST API
+#[derive(Debug, Clone, Default)]
+pub struct ListingQuery {
+    pub status: Option<ListingStatus>,
+    pub location: Option<String>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    pub min_surface: Option<f64>,
+    pub posted_after: Option<DateTime<Utc>>,
+    pub sort: ListingSort,
+    /// Newest, most expensive... first unless set
+    pub ascending: bool,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// A listing matching a full-text search, best matches first
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub uuid: Uuid,
+    pub title: String,
+    pub url: String,
+    pub location: String,
+    pub price: Option<f64>,
+    pub posted_at: DateTime<Utc>,
+    pub status: ListingStatus,
+    /// Matching excerpt, with the matched words in bold (Markdown)
+    pub snippet: String,
+}
+
+/// One page of search results
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Total number of matches, across all pages
+    pub total: usize,
+}
+
+/// Turn free text into an FTS5 query matching every word
+/// Each word is quoted so that FTS5 operators and punctuation can't break the query
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "")))
+        .filter(|word| word != "\"\"")
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub struct Database {
@@ -55,57 +422,115 @@ pub struct Database {
 }
 
 impl Database {
+    /// Open the database and apply pending schema migrations
     pub fn new(path: &str) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        let db = Database { conn };
-        db.init_schema()?;
+        let db = Self::open(path)?;
+        let applied = db.migrate()?;
+        if !applied.is_empty() {
+            tracing::info!("Applied {} database migration(s), schema is now at version {}",
+                applied.len(), db.schema_version()?);
+        }
         Ok(db)
     }
 
-    fn init_schema(&self) -> Result<()> {
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS listings (
-                uuid TEXT PRIMARY KEY,
-                listing_id TEXT NOT NULL UNIQUE,
-                title TEXT NOT NULL,
-                price REAL,
-                surface REAL,
-                location TEXT NOT NULL,
-                url TEXT NOT NULL,
-                image_url TEXT,
-                description TEXT,
-                posted_at TEXT,
-                source TEXT NOT NULL,
-                status TEXT NOT NULL DEFAULT 'unchecked',
-                scraped_at TEXT NOT NULL,
-                main_channel_message_id INTEGER,
-                interesting_channel_message_id INTEGER
-            )",
-            [],
-        )?;
-
-        // Create index on listing_id for faster lookups
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_listing_id ON listings(listing_id)",
-            [],
-        )?;
+    /// Open the database without touching its schema
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        Ok(Database { conn })
+    }
 
-        // Create index on status for filtering
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_status ON listings(status)",
-            [],
-        )?;
+    /// Version of the last applied schema migration
+    pub fn schema_version(&self) -> Result<u32> {
+        migrations::current_version(&self.conn)
+    }
 
-        Ok(())
+    /// Schema migrations not applied to this database yet
+    pub fn pending_migrations(&self) -> Result<Vec<&'static Migration>> {
+        migrations::pending(&self.conn)
     }
 
-    /// Insert a new listing or get existing one if already exists
-    pub fn insert_or_get_listing(&self, listing: &Listing) -> Result<Uuid> {
-        // Check if listing already exists
-        if let Some(uuid) = self.get_listing_uuid_by_id(&listing.id)? {
-            return Ok(uuid);
+    /// Apply every pending schema migration in a single transaction
+    pub fn migrate(&self) -> Result<Vec<&'static Migration>> {
+        migrations::run(&self.conn)
+    }
+
+    /// Insert a new listing, or diff a known listing against the stored record
+    /// Changed fields are updated and recorded in the listing_history table
+    pub fn upsert_listing(&self, listing: &Listing) -> Result<UpsertOutcome> {
+        let Some(existing) = self.get_listing_uuid_by_id(&listing.id)?
+            .map(|uuid| self.get_listing_by_uuid(&uuid))
+            .transpose()?
+            .flatten() else {
+            return Ok(UpsertOutcome {
+                uuid: self.insert_listing(listing)?,
+                is_new: true,
+                changes: vec![],
+                previous_price: None,
+            });
+        };
+
+        let changes = diff_listing(&existing, listing);
+        if !changes.is_empty() {
+            let tx = self.conn.unchecked_transaction()?;
+            let now = Utc::now();
+
+            for change in &changes {
+                tracing::info!("Listing '{}' changed {}: {:?} -> {:?}",
+                    existing.title, change.field, change.old_value, change.new_value);
+                tx.execute(
+                    "INSERT INTO listing_history (uuid, field, old_value, new_value, changed_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![existing.uuid.to_string(), change.field, change.old_value, change.new_value, now],
+                )?;
+            }
+
+            // Fields the scraper did not provide keep their stored value
+            tx.execute(
+                "UPDATE listings SET
+                    title = ?1,
+                    price = COALESCE(?2, price),
+                    surface = COALESCE(?3, surface),
+                    url = ?4,
+                    image_url = COALESCE(?5, image_url),
+                    description = COALESCE(?6, description)
+                 WHERE uuid = ?7",
+                params![
+                    &listing.title,
+                    listing.price,
+                    listing.surface,
+                    &listing.url,
+                    &listing.image_url,
+                    &listing.description,
+                    existing.uuid.to_string(),
+                ],
+            )?;
+            tx.commit()?;
+        }
+
+        // Not a change worth recording: the website may only place the flat once its address is given
+        if listing.coordinates.is_some() && listing.coordinates != existing.coordinates {
+            self.conn.execute(
+                "UPDATE listings SET latitude = ?1, longitude = ?2 WHERE uuid = ?3",
+                params![
+                    listing.coordinates.map(|c| c.latitude),
+                    listing.coordinates.map(|c| c.longitude),
+                    existing.uuid.to_string(),
+                ],
+            )?;
         }
 
+        let previous_price = existing.price
+            .filter(|_| changes.iter().any(|change| change.field == "price"));
+
+        Ok(UpsertOutcome {
+            uuid: existing.uuid,
+            is_new: false,
+            changes,
+            previous_price,
+        })
+    }
+
+    fn insert_listing(&self, listing: &Listing) -> Result<Uuid> {
         // Generate new UUID and insert
         let uuid = Uuid::new_v4();
         let scraped_at = Utc::now();
@@ -113,8 +538,9 @@ impl Database {
         self.conn.execute(
             "INSERT INTO listings (
                 uuid, listing_id, title, price, surface, location, url,
-                image_url, description, posted_at, source, status, scraped_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                image_url, description, posted_at, source, status, scraped_at,
+                latitude, longitude
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
             params![
                 uuid.to_string(),
                 &listing.id,
@@ -127,24 +553,16 @@ impl Database {
                 &listing.description,
                 listing.posted_at,
                 &listing.source,
-                ListingStatus::Unchecked.to_string(),
+                ListingStatus::Unchecked.as_str(),
                 scraped_at,
+                listing.coordinates.map(|c| c.latitude),
+                listing.coordinates.map(|c| c.longitude),
             ],
         )?;
 
         Ok(uuid)
     }
 
-    /// Check if a listing exists by its listing ID
-    pub fn listing_exists(&self, listing_id: &str) -> Result<bool> {
-        let exists: bool = self.conn.query_row(
-            "SELECT EXISTS(SELECT 1 FROM listings WHERE listing_id = ?1)",
-            params![listing_id],
-            |row| row.get(0),
-        )?;
-        Ok(exists)
-    }
-
     /// Get UUID for a listing by its listing ID
     pub fn get_listing_uuid_by_id(&self, listing_id: &str) -> Result<Option<Uuid>> {
         let uuid_str: Option<String> = self.conn
@@ -162,140 +580,1289 @@ impl Database {
     pub fn get_listing_by_uuid(&self, uuid: &Uuid) -> Result<Option<ListingRecord>> {
         let record = self.conn
             .query_row(
-                "SELECT uuid, listing_id, title, price, surface, location, url,
-                        image_url, description, posted_at, source, status, scraped_at,
-                        main_channel_message_id, interesting_channel_message_id
-                 FROM listings WHERE uuid = ?1",
+                &format!("SELECT {} FROM listings WHERE uuid = ?1", RECORD_COLUMNS),
                 params![uuid.to_string()],
-                |row| {
-                    Ok(ListingRecord {
-                        uuid: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                        listing_id: row.get(1)?,
-                        title: row.get(2)?,
-                        price: row.get(3)?,
-                        surface: row.get(4)?,
-                        location: row.get(5)?,
-                        url: row.get(6)?,
-                        image_url: row.get(7)?,
-                        description: row.get(8)?,
-                        posted_at: row.get(9)?,
-                        source: row.get(10)?,
-                        status: ListingStatus::from_string(&row.get::<_, String>(11)?),
-                        scraped_at: row.get(12)?,
-                        main_channel_message_id: row.get(13)?,
-                        interesting_channel_message_id: row.get(14)?,
-                    })
-                },
+                record_from_row,
             )
             .optional()?;
 
         Ok(record)
     }
 
-    /// Update the status of a listing
-    pub fn update_status(&self, uuid: &Uuid, status: ListingStatus) -> Result<()> {
-        self.conn.execute(
+    /// Update the status of a listing, recording who changed it in `status_events`
+    /// Fails with `InvalidTransition` if the lifecycle doesn't allow the change
+    /// An expired listing can also go back to the status it had before expiring
+    pub fn update_status(&self, uuid: &Uuid, status: ListingStatus, user_id: Option<u64>, source: StatusSource) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        Self::check_status_change(&tx, uuid, &status)?;
+        Self::record_status_change(&tx, uuid, &status, user_id, source)?;
+        if status != ListingStatus::Expired {
+            tx.execute("DELETE FROM listing_removals WHERE uuid = ?1", params![uuid.to_string()])?;
+        }
+        tx.execute(
             "UPDATE listings SET status = ?1 WHERE uuid = ?2",
-            params![status.to_string(), uuid.to_string()],
+            params![status.as_str(), uuid.to_string()],
         )?;
+        tx.commit()?;
         Ok(())
     }
 
-    /// Set the main channel message ID for a listing
-    pub fn set_main_channel_message_id(&self, uuid: &Uuid, message_id: u64) -> Result<()> {
-        self.conn.execute(
-            "UPDATE listings SET main_channel_message_id = ?1 WHERE uuid = ?2",
-            params![message_id, uuid.to_string()],
+    fn check_status_change(conn: &Connection, uuid: &Uuid, status: &ListingStatus) -> Result<()> {
+        let current: Option<String> = conn
+            .query_row("SELECT status FROM listings WHERE uuid = ?1", params![uuid.to_string()], |row| row.get(0))
+            .optional()?;
+        let Some(current) = current.map(|current| ListingStatus::from_string(&current)) else {
+            return Ok(());
+        };
+        if current == ListingStatus::Expired {
+            let previous: Option<String> = conn
+                .query_row(
+                    "SELECT previous_status FROM listing_removals WHERE uuid = ?1",
+                    params![uuid.to_string()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if previous.is_some_and(|previous| ListingStatus::from_string(&previous) == *status) {
+                return Ok(());
+            }
+        }
+        lifecycle::check_transition(&current, status)?;
+        Ok(())
+    }
+
+    /// Insert a status event, unless the listing already has that status
+    /// Must run before the listings table is updated
+    fn record_status_change(
+        conn: &Connection,
+        uuid: &Uuid,
+        status: &ListingStatus,
+        user_id: Option<u64>,
+        source: StatusSource,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO status_events (uuid, old_status, new_status, user_id, source, created_at)
+             SELECT uuid, status, ?2, ?3, ?4, ?5 FROM listings WHERE uuid = ?1 AND status != ?2",
+            params![uuid.to_string(), status.as_str(), user_id, source.as_str(), Utc::now()],
         )?;
         Ok(())
     }
 
-    /// Set the interesting channel message ID for a listing
-    pub fn set_interesting_channel_message_id(&self, uuid: &Uuid, message_id: u64) -> Result<()> {
+    /// Get the status changes of a listing, oldest first
+    pub fn get_status_events(&self, uuid: &Uuid) -> Result<Vec<StatusEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT old_status, new_status, user_id, source, created_at
+             FROM status_events WHERE uuid = ?1 ORDER BY created_at, id"
+        )?;
+
+        let events = stmt
+            .query_map(params![uuid.to_string()], |row| {
+                Ok(StatusEvent {
+                    old_status: row.get::<_, Option<String>>(0)?.map(|s| ListingStatus::from_string(&s)),
+                    new_status: ListingStatus::from_string(&row.get::<_, String>(1)?),
+                    user_id: row.get(2)?,
+                    source: StatusSource::from_string(&row.get::<_, String>(3)?),
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    /// Record a member's vote on a listing
+    /// Voting the same thing twice withdraws the vote. Returns the member's vote afterwards.
+    pub fn toggle_vote(&self, uuid: &Uuid, user_id: u64, vote: Vote) -> Result<Option<Vote>> {
+        let current: Option<String> = self.conn
+            .query_row(
+                "SELECT vote FROM listing_votes WHERE uuid = ?1 AND user_id = ?2",
+                params![uuid.to_string(), user_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if current.as_deref() == Some(vote.as_str()) {
+            self.conn.execute(
+                "DELETE FROM listing_votes WHERE uuid = ?1 AND user_id = ?2",
+                params![uuid.to_string(), user_id],
+            )?;
+            return Ok(None);
+        }
+
         self.conn.execute(
-            "UPDATE listings SET interesting_channel_message_id = ?1 WHERE uuid = ?2",
-            params![message_id, uuid.to_string()],
+            "INSERT OR REPLACE INTO listing_votes (uuid, user_id, vote, voted_at) VALUES (?1, ?2, ?3, ?4)",
+            params![uuid.to_string(), user_id, vote.as_str(), Utc::now()],
         )?;
-        Ok(())
+        Ok(Some(vote))
     }
 
-    /// Clear the interesting channel message ID for a listing (when removed from interesting)
-    pub fn clear_interesting_channel_message_id(&self, uuid: &Uuid) -> Result<()> {
+    /// Get the votes on a listing, in the order they were cast
+    pub fn get_vote_tally(&self, uuid: &Uuid) -> Result<VoteTally> {
+        let mut stmt = self.conn.prepare(
+            "SELECT user_id, vote FROM listing_votes WHERE uuid = ?1 ORDER BY voted_at"
+        )?;
+        let rows = stmt.query_map(params![uuid.to_string()], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut tally = VoteTally::default();
+        for row in rows {
+            let (user_id, vote) = row?;
+            if let Some(vote) = Vote::parse(&vote) {
+                tally.add(vote, user_id);
+            }
+        }
+        Ok(tally)
+    }
+
+    /// Add a note to a listing
+    pub fn add_note(&self, uuid: &Uuid, user_id: u64, author: &str, content: &str) -> Result<()> {
         self.conn.execute(
-            "UPDATE listings SET interesting_channel_message_id = NULL WHERE uuid = ?1",
-            params![uuid.to_string()],
+            "INSERT INTO listing_notes (uuid, user_id, author, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![uuid.to_string(), user_id, author, content, Utc::now()],
         )?;
         Ok(())
     }
 
-    /// Get all new listings (unchecked status, no main channel message)
-    /// Filters out listings older than max_listing_age_minutes
-    pub fn get_new_listings(&self, max_listing_age_minutes: u64) -> Result<Vec<(Uuid, Listing)>> {
+    /// Get the notes of a listing, oldest first
+    pub fn get_notes(&self, uuid: &Uuid) -> Result<Vec<ListingNote>> {
         let mut stmt = self.conn.prepare(
-            "SELECT uuid, listing_id, title, price, surface, location, url,
-                    image_url, description, posted_at, source
-             FROM listings
-             WHERE main_channel_message_id IS NULL
-             ORDER BY scraped_at DESC"
+            "SELECT user_id, author, content, created_at FROM listing_notes
+             WHERE uuid = ?1 ORDER BY created_at, id"
         )?;
 
+        let notes = stmt
+            .query_map(params![uuid.to_string()], |row| {
+                Ok(ListingNote {
+                    user_id: row.get(0)?,
+                    author: row.get(1)?,
+                    content: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
+    /// Schedule a visit of a listing, returning its id
+    /// Reminders whose time has already passed are not sent (e.g. no day-before reminder for a visit in 3 hours)
+    pub fn add_visit(&self, uuid: &Uuid, scheduled_at: DateTime<Utc>, attendees: &[u64], created_by: u64) -> Result<i64> {
         let now = Utc::now();
-        let max_age = chrono::Duration::minutes(max_listing_age_minutes as i64);
+        let until_visit = scheduled_at.signed_duration_since(now);
+        self.conn.execute(
+            "INSERT INTO visits (uuid, scheduled_at, attendees, created_by, created_at, reminded_day_before, reminded_hour_before)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                uuid.to_string(),
+                scheduled_at,
+                visits::format_attendees(attendees),
+                created_by,
+                now,
+                until_visit <= chrono::Duration::hours(24),
+                until_visit <= chrono::Duration::hours(1),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
 
-        let listings = stmt
-            .query_map([], |row| {
-                let uuid = Uuid::parse_str(&row.get::<_, String>(0)?).unwrap();
-                let listing = Listing {
-                    id: row.get(1)?,
-                    title: row.get(2)?,
-                    price: row.get(3)?,
-                    surface: row.get(4)?,
-                    location: row.get(5)?,
-                    url: row.get(6)?,
-                    image_url: row.get(7)?,
-                    description: row.get(8)?,
-                    posted_at: row.get(9)?,
-                    source: row.get(10)?,
-                };
-                Ok((uuid, listing))
-            })?
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .filter(|(_, listing)| {
-                // Check if the listing is too old
-                let age = now.signed_duration_since(listing.posted_at);
-                if age > max_age {
-                    tracing::debug!(
-                        "Filtering out old listing '{}' - age: {} minutes (max: {})",
-                        listing.title, age.num_minutes(), max_listing_age_minutes
-                    );
-                    return false;
-                }
-                true
-            })
-            .collect();
+    pub fn get_visit(&self, id: i64) -> Result<Option<Visit>> {
+        let visit = self.conn
+            .query_row(
+                &format!("SELECT {} FROM visits WHERE id = ?1", VISIT_COLUMNS),
+                params![id],
+                visit_from_row,
+            )
+            .optional()?;
+        Ok(visit)
+    }
 
-        Ok(listings)
+    /// Get the visits that haven't happened yet, soonest first
+    pub fn get_upcoming_visits(&self, now: DateTime<Utc>) -> Result<Vec<Visit>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM visits WHERE scheduled_at >= ?1 ORDER BY scheduled_at", VISIT_COLUMNS)
+        )?;
+        let visits = stmt.query_map(params![now], visit_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(visits)
     }
 
-    /// Delete old unposted listings that are past the max age
-    /// This helps keep the database clean by removing stale listings that were never posted
-    pub fn cleanup_old_listings(&self, max_listing_age_minutes: u64) -> Result<usize> {
-        let now = Utc::now();
-        let cutoff_time = now - chrono::Duration::minutes(max_listing_age_minutes as i64);
+    /// Get the visits that still have a reminder or rating request to send
+    pub fn get_visits_awaiting_reminders(&self) -> Result<Vec<Visit>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM visits WHERE rating_requested = 0 ORDER BY scheduled_at", VISIT_COLUMNS)
+        )?;
+        let visits = stmt.query_map([], visit_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(visits)
+    }
 
-        let deleted = self.conn.execute(
-            "DELETE FROM listings
-             WHERE main_channel_message_id IS NULL
-             AND posted_at IS NOT NULL
-             AND posted_at < ?1",
-            params![cutoff_time],
+    pub fn mark_visit_reminder_sent(&self, id: i64, reminder: VisitReminder) -> Result<()> {
+        let column = match reminder {
+            VisitReminder::DayBefore => "reminded_day_before",
+            VisitReminder::HourBefore => "reminded_hour_before",
+            VisitReminder::RatingRequest => "rating_requested",
+        };
+        self.conn.execute(&format!("UPDATE visits SET {} = 1 WHERE id = ?1", column), params![id])?;
+        Ok(())
+    }
+
+    /// Get the most recently scheduled visit of a listing
+    pub fn get_latest_visit(&self, uuid: &Uuid) -> Result<Option<Visit>> {
+        let visit = self.conn
+            .query_row(
+                &format!("SELECT {} FROM visits WHERE uuid = ?1 ORDER BY created_at DESC, id DESC LIMIT 1", VISIT_COLUMNS),
+                params![uuid.to_string()],
+                visit_from_row,
+            )
+            .optional()?;
+        Ok(visit)
+    }
+
+    /// Record the post-visit rating (1 to 5)
+    pub fn rate_visit(&self, id: i64, rating: u8, user_id: u64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE visits SET rating = ?1, rated_by = ?2 WHERE id = ?3",
+            params![rating, user_id, id],
         )?;
+        Ok(())
+    }
 
-        if deleted > 0 {
-            tracing::info!("Cleaned up {} old unposted listings from database", deleted);
+    /// Find a listing from what a user typed: its UUID, its id (e.g. leboncoin_123) or its URL
+    pub fn find_listing(&self, reference: &str) -> Result<Option<ListingRecord>> {
+        let reference = reference.trim();
+        if let Ok(uuid) = Uuid::parse_str(reference) {
+            return self.get_listing_by_uuid(&uuid);
         }
 
-        Ok(deleted)
+        let record = self.conn
+            .query_row(
+                &format!("SELECT {} FROM listings WHERE listing_id = ?1 OR url = ?1", RECORD_COLUMNS),
+                params![reference],
+                record_from_row,
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Get all listings currently in one of the given statuses
+    pub fn get_listings_by_status(&self, statuses: &[ListingStatus]) -> Result<Vec<ListingRecord>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM listings WHERE status = ?1 ORDER BY scraped_at", RECORD_COLUMNS)
+        )?;
+
+        let mut records = Vec::new();
+        for status in statuses {
+            let rows = stmt.query_map(params![status.as_str()], record_from_row)?;
+            for record in rows {
+                records.push(record?);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Get every listing that has a Discord message id stored, including the skipped ones (id 0)
+    /// Duplicates are left out since they are shown on the message of their original
+    pub fn get_posted_listings(&self) -> Result<Vec<ListingRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM listings
+             WHERE (main_channel_message_id IS NOT NULL OR interesting_channel_message_id IS NOT NULL)
+               AND uuid NOT IN (SELECT uuid FROM listing_duplicates)
+             ORDER BY scraped_at",
+            RECORD_COLUMNS
+        ))?;
+        let records = stmt.query_map([], record_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// Get one page of the listings matching the filters, in the requested order, and the total number of matches
+    /// Duplicates are left out, they are listed on their original. Missing values are sorted last
+    pub fn query_listings(&self, query: &ListingQuery) -> Result<(Vec<ListingRecord>, usize)> {
+        let conditions = "uuid NOT IN (SELECT uuid FROM listing_duplicates)
+             AND (?1 IS NULL OR status = ?1)
+             AND (?2 IS NULL OR location = ?2 COLLATE NOCASE)
+             AND (?3 IS NULL OR price >= ?3)
+             AND (?4 IS NULL OR price <= ?4)
+             AND (?5 IS NULL OR surface >= ?5)
+             AND (?6 IS NULL OR posted_at >= ?6)";
+        let status = query.status.as_ref().map(|s| s.as_str());
+        let filters = params![status, query.location, query.min_price, query.max_price, query.min_surface, query.posted_after];
+
+        let total: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM listings WHERE {}", conditions),
+            filters,
+            |row| row.get(0),
+        )?;
+
+        let expression = query.sort.expression();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM listings WHERE {} ORDER BY ({}) IS NULL, {} {}, posted_at DESC LIMIT ?7 OFFSET ?8",
+            RECORD_COLUMNS, conditions, expression, expression, if query.ascending { "ASC" } else { "DESC" }
+        ))?;
+        let records = stmt
+            .query_map(
+                params![status, query.location, query.min_price, query.max_price, query.min_surface, query.posted_after,
+                    query.limit as i64, query.offset as i64],
+                record_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((records, total as usize))
+    }
+
+    /// Get the most recently announced listings, newest first, with when they were announced
+    /// Duplicates are left out, they were shown on their original
+    pub fn get_recently_notified_listings(&self, limit: usize) -> Result<Vec<(ListingRecord, DateTime<Utc>)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, notified_at FROM listings
+             WHERE notified_at IS NOT NULL
+               AND uuid NOT IN (SELECT uuid FROM listing_duplicates)
+             ORDER BY notified_at DESC LIMIT ?1",
+            RECORD_COLUMNS
+        ))?;
+        let records = stmt
+            .query_map(params![limit as i64], |row| Ok((record_from_row(row)?, row.get(17)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// Mark a listing as removed from its website and record when it disappeared
+    pub fn mark_expired(&self, uuid: &Uuid, disappeared_at: DateTime<Utc>) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        Self::check_status_change(&tx, uuid, &ListingStatus::Expired)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO listing_removals (uuid, previous_status, disappeared_at)
+             SELECT uuid, status, ?2 FROM listings WHERE uuid = ?1",
+            params![uuid.to_string(), disappeared_at],
+        )?;
+        Self::record_status_change(&tx, uuid, &ListingStatus::Expired, None, StatusSource::System)?;
+        tx.execute(
+            "UPDATE listings SET status = ?1 WHERE uuid = ?2",
+            params![ListingStatus::Expired.as_str(), uuid.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get when a listing was detected as removed from its website
+    pub fn get_disappeared_at(&self, uuid: &Uuid) -> Result<Option<DateTime<Utc>>> {
+        let disappeared_at = self.conn
+            .query_row(
+                "SELECT disappeared_at FROM listing_removals WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(disappeared_at)
+    }
+
+    /// Find the listing posted as the given Discord message, in either channel
+    /// Duplicates linked by older versions share the message of their original, which is the one returned
+    pub fn get_listing_uuid_by_message_id(&self, message_id: u64) -> Result<Option<Uuid>> {
+        let uuid: Option<String> = self.conn
+            .query_row(
+                "SELECT uuid FROM listings
+                 WHERE (main_channel_message_id = ?1 OR interesting_channel_message_id = ?1)
+                   AND uuid NOT IN (SELECT uuid FROM listing_duplicates)",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(uuid.and_then(|uuid| Uuid::parse_str(&uuid).ok()))
+    }
+
+    /// Price before the last price change, if the price ever changed
+    pub fn get_previous_price(&self, uuid: &Uuid) -> Result<Option<f64>> {
+        let old_value: Option<Option<String>> = self.conn
+            .query_row(
+                "SELECT old_value FROM listing_history
+                 WHERE uuid = ?1 AND field = 'price'
+                 ORDER BY changed_at DESC, id DESC LIMIT 1",
+                params![uuid.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(old_value.flatten().and_then(|price| price.parse().ok()))
+    }
+
+    /// Store the warnings computed when a listing is posted, so that they survive re-renders
+    pub fn set_listing_annotations(&self, uuid: &Uuid, scam_warning: Option<&ScamReport>, reposts: Option<&RepostSummary>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO listing_annotations
+                (uuid, scam_score, scam_reasons, repost_count, repost_first_posted_at, repost_previous_price)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                uuid.to_string(),
+                scam_warning.map(|report| report.score),
+                scam_warning.map(|report| report.reasons.join("\n")),
+                reposts.map(|reposts| reposts.previous_postings),
+                reposts.map(|reposts| reposts.first_posted_at),
+                reposts.and_then(|reposts| reposts.previous_price),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_listing_annotations(&self, uuid: &Uuid) -> Result<(Option<ScamReport>, Option<RepostSummary>)> {
+        let annotations = self.conn
+            .query_row(
+                "SELECT scam_score, scam_reasons, repost_count, repost_first_posted_at, repost_previous_price
+                 FROM listing_annotations WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| {
+                    let scam_warning = match (row.get::<_, Option<u32>>(0)?, row.get::<_, Option<String>>(1)?) {
+                        (Some(score), Some(reasons)) => Some(ScamReport {
+                            score,
+                            reasons: reasons.lines().map(str::to_string).collect(),
+                        }),
+                        _ => None,
+                    };
+                    let reposts = match (row.get::<_, Option<usize>>(2)?, row.get::<_, Option<DateTime<Utc>>>(3)?) {
+                        (Some(previous_postings), Some(first_posted_at)) => Some(RepostSummary {
+                            previous_postings,
+                            first_posted_at,
+                            previous_price: row.get(4)?,
+                        }),
+                        _ => None,
+                    };
+                    Ok((scam_warning, reposts))
+                },
+            )
+            .optional()?;
+        Ok(annotations.unwrap_or((None, None)))
+    }
+
+    /// Set the main channel message ID for a listing
+    pub fn set_main_channel_message_id(&self, uuid: &Uuid, message_id: u64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE listings SET main_channel_message_id = ?1 WHERE uuid = ?2",
+            params![message_id, uuid.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Set the interesting channel message ID for a listing
+    pub fn set_interesting_channel_message_id(&self, uuid: &Uuid, message_id: u64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE listings SET interesting_channel_message_id = ?1 WHERE uuid = ?2",
+            params![message_id, uuid.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Clear the interesting channel message ID for a listing (when removed from interesting)
+    pub fn clear_interesting_channel_message_id(&self, uuid: &Uuid) -> Result<()> {
+        self.conn.execute(
+            "UPDATE listings SET interesting_channel_message_id = NULL WHERE uuid = ?1",
+            params![uuid.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Record that a listing was delivered to every notifier, so it isn't announced again
+    pub fn mark_notified(&self, uuid: &Uuid) -> Result<()> {
+        self.conn.execute(
+            "UPDATE listings SET notified_at = ?1 WHERE uuid = ?2",
+            params![Utc::now(), uuid.to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_notified(&self, uuid: &Uuid) -> Result<bool> {
+        let notified = self.conn.query_row(
+            "SELECT notified_at IS NOT NULL FROM listings WHERE uuid = ?1",
+            params![uuid.to_string()],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(notified.unwrap_or(false))
+    }
+
+    pub fn set_telegram_message(&self, uuid: &Uuid, message: &TelegramMessage) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO telegram_messages (uuid, chat_id, message_id, has_photo) VALUES (?1, ?2, ?3, ?4)",
+            params![uuid.to_string(), message.chat_id, message.message_id, message.has_photo],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_telegram_message(&self, uuid: &Uuid) -> Result<Option<TelegramMessage>> {
+        let message = self.conn
+            .query_row(
+                "SELECT chat_id, message_id, has_photo FROM telegram_messages WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| Ok(TelegramMessage { chat_id: row.get(0)?, message_id: row.get(1)?, has_photo: row.get(2)? }),
+            )
+            .optional()?;
+        Ok(message)
+    }
+
+    pub fn set_matrix_message(&self, uuid: &Uuid, message: &MatrixMessage) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO matrix_messages (uuid, room_id, event_id, image_uri) VALUES (?1, ?2, ?3, ?4)",
+            params![uuid.to_string(), message.room_id, message.event_id, message.image_uri],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_matrix_message(&self, uuid: &Uuid) -> Result<Option<MatrixMessage>> {
+        let message = self.conn
+            .query_row(
+                "SELECT room_id, event_id, image_uri FROM matrix_messages WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| Ok(MatrixMessage { room_id: row.get(0)?, event_id: row.get(1)?, image_uri: row.get(2)? }),
+            )
+            .optional()?;
+        Ok(message)
+    }
+
+    /// Find the listing a Matrix event announced
+    pub fn get_listing_uuid_by_matrix_event(&self, event_id: &str) -> Result<Option<Uuid>> {
+        let uuid: Option<String> = self.conn
+            .query_row(
+                "SELECT uuid FROM matrix_messages WHERE event_id = ?1",
+                params![event_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(uuid.and_then(|uuid| Uuid::parse_str(&uuid).ok()))
+    }
+
+    pub fn add_webhook_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO webhook_dead_letters (url, event, uuid, payload, attempts, error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![letter.url, letter.event, letter.uuid.to_string(), letter.payload, letter.attempts, letter.error, letter.created_at],
+        )?;
+        Ok(())
+    }
+
+    /// Get the most recent undeliverable webhook events, newest first
+    pub fn get_webhook_dead_letters(&self, limit: usize) -> Result<Vec<DeadLetter>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT url, event, uuid, payload, attempts, error, created_at
+             FROM webhook_dead_letters ORDER BY id DESC LIMIT ?1"
+        )?;
+
+        let letters = stmt
+            .query_map(params![limit as i64], |row| {
+                let uuid: String = row.get(2)?;
+                Ok(DeadLetter {
+                    url: row.get(0)?,
+                    event: row.get(1)?,
+                    uuid: Uuid::parse_str(&uuid).unwrap_or_default(),
+                    payload: row.get(3)?,
+                    attempts: row.get(4)?,
+                    error: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(letters)
+    }
+
+    /// Add a listing to a recipient's next digest; listings are only ever sent once to each recipient
+    pub fn queue_for_digest(&self, uuid: &Uuid, recipient: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO email_digest_entries (uuid, recipient, queued_at) VALUES (?1, ?2, ?3)",
+            params![uuid.to_string(), recipient, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Listings waiting for a recipient's next digest, oldest first
+    pub fn get_pending_digest(&self, recipient: &str) -> Result<Vec<Uuid>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT uuid FROM email_digest_entries WHERE recipient = ?1 AND sent_at IS NULL ORDER BY queued_at"
+        )?;
+
+        let uuids = stmt
+            .query_map(params![recipient], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(uuids.iter().filter_map(|uuid| Uuid::parse_str(uuid).ok()).collect())
+    }
+
+    pub fn mark_digest_sent(&self, recipient: &str, uuids: &[Uuid]) -> Result<()> {
+        let now = Utc::now();
+        for uuid in uuids {
+            self.conn.execute(
+                "UPDATE email_digest_entries SET sent_at = ?1 WHERE uuid = ?2 AND recipient = ?3",
+                params![now, uuid.to_string(), recipient],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Record that a notifier announced a new listing, so that retries skip it
+    pub fn mark_delivered(&self, uuid: &Uuid, notifier: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO notifier_deliveries (uuid, notifier, delivered_at) VALUES (?1, ?2, ?3)",
+            params![uuid.to_string(), notifier, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_delivered(&self, uuid: &Uuid, notifier: &str) -> Result<bool> {
+        let delivered = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM notifier_deliveries WHERE uuid = ?1 AND notifier = ?2)",
+            params![uuid.to_string(), notifier],
+            |row| row.get(0),
+        )?;
+        Ok(delivered)
+    }
+
+    /// Get all new listings (not delivered to the notifiers yet)
+    /// Filters out listings older than max_listing_age_minutes
+    pub fn get_new_listings(&self, max_listing_age_minutes: u64) -> Result<Vec<(Uuid, Listing)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT uuid, listing_id, title, price, surface, location, url,
+                    image_url, description, posted_at, source, latitude, longitude
+             FROM listings
+             WHERE notified_at IS NULL
+             ORDER BY scraped_at DESC"
+        )?;
+
+        let now = Utc::now();
+        let max_age = chrono::Duration::minutes(max_listing_age_minutes as i64);
+
+        let listings = stmt
+            .query_map([], |row| {
+                let uuid = Uuid::parse_str(&row.get::<_, String>(0)?).unwrap();
+                let listing = Listing {
+                    id: row.get(1)?,
+                    title: row.get(2)?,
+                    price: row.get(3)?,
+                    surface: row.get(4)?,
+                    location: row.get(5)?,
+                    url: row.get(6)?,
+                    image_url: row.get(7)?,
+                    description: row.get(8)?,
+                    posted_at: row.get(9)?,
+                    source: row.get(10)?,
+                    coordinates: coordinates_from_row(row, 11)?,
+                };
+                Ok((uuid, listing))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|(_, listing)| {
+                // Check if the listing is too old
+                let age = now.signed_duration_since(listing.posted_at);
+                if age > max_age {
+                    tracing::debug!(
+                        "Filtering out old listing '{}' - age: {} minutes (max: {})",
+                        listing.title, age.num_minutes(), max_listing_age_minutes
+                    );
+                    return false;
+                }
+                true
+            })
+            .collect();
+
+        Ok(listings)
+    }
+
+    /// Get the price per m² of every stored listing in a location, excluding one listing ID
+    /// Used as the historical baseline for price outlier detection
+    pub fn get_price_per_m2_for_location(&self, location: &str, exclude_listing_id: &str) -> Result<Vec<f64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT price / surface FROM listings
+             WHERE location = ?1 COLLATE NOCASE
+             AND listing_id != ?2
+             AND price > 0 AND surface > 0"
+        )?;
+
+        let values = stmt
+            .query_map(params![location, exclude_listing_id], |row| row.get(0))?
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        Ok(values)
+    }
+
+    /// Count other listings (different listing ID) that use the same image URL
+    pub fn count_other_listings_with_image(&self, image_url: &str, exclude_listing_id: &str) -> Result<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM listings WHERE image_url = ?1 AND listing_id != ?2",
+            params![image_url, exclude_listing_id],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Get announced listings in a location that can be the original of a duplicate
+    /// Only listings that are not duplicates themselves are returned
+    pub fn get_duplicate_candidates(&self, location: &str, exclude_listing_id: &str, lookback_days: u64) -> Result<Vec<(Uuid, Listing)>> {
+        let cutoff_time = Utc::now() - chrono::Duration::days(lookback_days as i64);
+
+        let mut stmt = self.conn.prepare(
+            "SELECT uuid, listing_id, title, price, surface, location, url,
+                    image_url, description, posted_at, source, latitude, longitude
+             FROM listings
+             WHERE location = ?1 COLLATE NOCASE
+             AND listing_id != ?2
+             AND scraped_at >= ?3
+             AND notified_at IS NOT NULL
+             AND uuid NOT IN (SELECT uuid FROM listing_duplicates)"
+        )?;
+
+        let candidates = stmt
+            .query_map(params![location, exclude_listing_id, cutoff_time], |row| {
+                let uuid = Uuid::parse_str(&row.get::<_, String>(0)?).unwrap();
+                let listing = Listing {
+                    id: row.get(1)?,
+                    title: row.get(2)?,
+                    price: row.get(3)?,
+                    surface: row.get(4)?,
+                    location: row.get(5)?,
+                    url: row.get(6)?,
+                    image_url: row.get(7)?,
+                    description: row.get(8)?,
+                    posted_at: row.get(9)?,
+                    source: row.get(10)?,
+                    coordinates: coordinates_from_row(row, 11)?,
+                };
+                Ok((uuid, listing))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(candidates)
+    }
+
+    /// Link a listing to the duplicate group of an original listing
+    pub fn link_duplicate(&self, uuid: &Uuid, group_uuid: &Uuid, similarity: f64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO listing_duplicates (uuid, group_uuid, similarity, linked_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![uuid.to_string(), group_uuid.to_string(), similarity, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the original listing of the duplicate group a listing belongs to, if any
+    pub fn get_duplicate_group(&self, uuid: &Uuid) -> Result<Option<Uuid>> {
+        let group: Option<String> = self.conn
+            .query_row(
+                "SELECT group_uuid FROM listing_duplicates WHERE uuid = ?1",
+                params![uuid.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(group.map(|s| Uuid::parse_str(&s).unwrap()))
+    }
+
+    /// Get the (source, url) of every duplicate linked to an original listing
+    pub fn get_duplicate_sources(&self, group_uuid: &Uuid) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT l.source, l.url
+             FROM listing_duplicates d
+             JOIN listings l ON l.uuid = d.uuid
+             WHERE d.group_uuid = ?1
+             ORDER BY d.linked_at"
+        )?;
+
+        let sources = stmt
+            .query_map(params![group_uuid.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(sources)
+    }
+
+    /// Get the cached perceptual hash of an image
+    /// Returns None if the image was never hashed, Some(None) if hashing failed
+    pub fn get_image_hash(&self, image_url: &str) -> Result<Option<Option<u64>>> {
+        let hash: Option<Option<i64>> = self.conn
+            .query_row(
+                "SELECT hash FROM image_hashes WHERE image_url = ?1",
+                params![image_url],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(hash.map(|h| h.map(|h| h as u64)))
+    }
+
+    /// When hashing an image last failed, None if it was hashed or never tried
+    pub fn get_image_hash_failure(&self, image_url: &str) -> Result<Option<DateTime<Utc>>> {
+        let failed_at = self.conn
+            .query_row(
+                "SELECT computed_at FROM image_hashes WHERE image_url = ?1 AND hash IS NULL",
+                params![image_url],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(failed_at)
+    }
+
+    /// Cache the perceptual hash of an image (None records a failed download)
+    pub fn set_image_hash(&self, image_url: &str, hash: Option<u64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO image_hashes (image_url, hash, computed_at) VALUES (?1, ?2, ?3)",
+            params![image_url, hash.map(|h| h as i64), Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Find listings (other than the given listing ID) whose photo hash is within max_distance bits
+    /// Results are sorted from the oldest to the most recent posting
+    pub fn find_similar_images(&self, hash: u64, max_distance: u32, exclude_listing_id: &str) -> Result<Vec<ImageMatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT l.uuid, l.listing_id, l.location, l.price, l.posted_at, h.hash
+             FROM listings l
+             JOIN image_hashes h ON h.image_url = l.image_url
+             WHERE h.hash IS NOT NULL AND l.listing_id != ?1
+             ORDER BY l.posted_at"
+        )?;
+
+        let matches = stmt
+            .query_map(params![exclude_listing_id], |row| {
+                let other_hash = row.get::<_, i64>(5)? as u64;
+                Ok(ImageMatch {
+                    uuid: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                    listing_id: row.get(1)?,
+                    location: row.get(2)?,
+                    price: row.get(3)?,
+                    posted_at: row.get(4)?,
+                    distance: crate::image_hash::hamming_distance(hash, other_hash),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|m| m.distance <= max_distance)
+            .collect();
+
+        Ok(matches)
+    }
+
+    /// Full-text search over listing titles, descriptions and locations
+    /// Results are ranked with BM25, best matches first
+    pub fn search(&self, query: &str, filters: &SearchFilters) -> Result<SearchResults> {
+        let fts_query = fts_query(query);
+        if fts_query.is_empty() {
+            return Ok(SearchResults { hits: vec![], total: 0 });
+        }
+
+        let conditions = "listings_fts MATCH ?1
+             AND (?2 IS NULL OR l.location = ?2 COLLATE NOCASE)
+             AND (?3 IS NULL OR l.posted_at >= ?3)
+             AND (?4 IS NULL OR l.status = ?4)";
+        let status = filters.status.as_ref().map(|s| s.as_str());
+
+        let total: i64 = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM listings_fts JOIN listings l ON l.rowid = listings_fts.rowid WHERE {}",
+                conditions
+            ),
+            params![fts_query, filters.location, filters.posted_after, status],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT l.uuid, l.title, l.url, l.location, l.price, l.posted_at, l.status,
+                    snippet(listings_fts, -1, '**', '**', '…', 16)
+             FROM listings_fts JOIN listings l ON l.rowid = listings_fts.rowid
+             WHERE {}
+             ORDER BY bm25(listings_fts)
+             LIMIT ?5 OFFSET ?6",
+            conditions
+        ))?;
+
+        let hits = stmt
+            .query_map(
+                params![fts_query, filters.location, filters.posted_after, status, filters.limit as i64, filters.offset as i64],
+                |row| {
+                    Ok(SearchHit {
+                        uuid: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                        title: row.get(1)?,
+                        url: row.get(2)?,
+                        location: row.get(3)?,
+                        price: row.get(4)?,
+                        posted_at: row.get(5)?,
+                        status: ListingStatus::from_string(&row.get::<_, String>(6)?),
+                        snippet: row.get(7)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SearchResults { hits, total: total as usize })
+    }
+
+    /// Delete old unposted listings that are past the max age
+    /// This helps keep the database clean by removing stale listings that were never posted
+    /// Listings some notifier delivered are kept even when another one keeps failing
+    pub fn cleanup_old_listings(&self, max_listing_age_minutes: u64) -> Result<usize> {
+        let now = Utc::now();
+        let cutoff_time = now - chrono::Duration::minutes(max_listing_age_minutes as i64);
+
+        let deleted = self.conn.execute(
+            "DELETE FROM listings
+             WHERE notified_at IS NULL
+             AND main_channel_message_id IS NULL
+             AND NOT EXISTS (SELECT 1 FROM notifier_deliveries WHERE notifier_deliveries.uuid = listings.uuid)
+             AND posted_at IS NOT NULL
+             AND posted_at < ?1",
+            params![cutoff_time],
+        )?;
+
+        if deleted > 0 {
+            tracing::info!("Cleaned up {} old unposted listings from database", deleted);
+        }
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(price: Option<f64>) -> Listing {
+        Listing {
+            title: "Appartement T2 45m²".to_string(),
+            price,
+            url: "https://example.com/1".to_string(),
+            ..Listing::test("leboncoin_1", 0.0)
+        }
+    }
+
+    fn history_count(db: &Database) -> i64 {
+        db.conn.query_row("SELECT COUNT(*) FROM listing_history", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_upsert_new_listing() {
+        let db = Database::new(":memory:").unwrap();
+        let outcome = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        assert!(outcome.is_new);
+        assert!(outcome.changes.is_empty());
+    }
+
+    #[test]
+    fn test_upsert_unchanged_listing() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        let second = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        assert_eq!(first.uuid, second.uuid);
+        assert!(!second.is_new);
+        assert!(second.changes.is_empty());
+        assert_eq!(history_count(&db), 0);
+    }
+
+    #[test]
+    fn test_upsert_records_price_drop() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        let cheaper = listing(Some(820.0));
+        let outcome = db.upsert_listing(&cheaper).unwrap();
+
+        assert_eq!(outcome.changes, vec![FieldChange {
+            field: "price",
+            old_value: Some("900".to_string()),
+            new_value: Some("820".to_string()),
+        }]);
+        assert_eq!(outcome.price_drop(&cheaper), Some((900.0, 820.0)));
+        assert_eq!(history_count(&db), 1);
+
+        let record = db.get_listing_by_uuid(&first.uuid).unwrap().unwrap();
+        assert_eq!(record.price, Some(820.0));
+    }
+
+    #[test]
+    fn test_upsert_price_increase_is_not_a_drop() {
+        let db = Database::new(":memory:").unwrap();
+        db.upsert_listing(&listing(Some(820.0))).unwrap();
+        let pricier = listing(Some(900.0));
+        let outcome = db.upsert_listing(&pricier).unwrap();
+        assert_eq!(outcome.previous_price, Some(820.0));
+        assert_eq!(outcome.price_drop(&pricier), None);
+    }
+
+    #[test]
+    fn test_mark_expired() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        db.update_status(&uuid, ListingStatus::Interesting, Some(42), StatusSource::Button).unwrap();
+        assert_eq!(db.get_listings_by_status(&[ListingStatus::Interesting]).unwrap().len(), 1);
+
+        let now = Utc::now();
+        db.mark_expired(&uuid, now).unwrap();
+
+        let record = db.get_listing_by_uuid(&uuid).unwrap().unwrap();
+        assert_eq!(record.status, ListingStatus::Expired);
+        assert_eq!(db.get_disappeared_at(&uuid).unwrap(), Some(now));
+        assert!(db.get_listings_by_status(&[ListingStatus::Interesting]).unwrap().is_empty());
+
+        let events = db.get_status_events(&uuid).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].old_status, Some(ListingStatus::Interesting));
+        assert_eq!(events[1].source, StatusSource::System);
+        assert_eq!(events[1].user_id, None);
+    }
+
+    #[test]
+    fn test_restore_expired() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        db.update_status(&uuid, ListingStatus::Interesting, None, StatusSource::Button).unwrap();
+        db.update_status(&uuid, ListingStatus::Contacted, None, StatusSource::Button).unwrap();
+        db.mark_expired(&uuid, Utc::now()).unwrap();
+
+        // Only the status before expiring or Unchecked
+        let error = db.update_status(&uuid, ListingStatus::Applied, None, StatusSource::Button).unwrap_err();
+        assert!(error.downcast_ref::<lifecycle::InvalidTransition>().is_some());
+
+        db.update_status(&uuid, ListingStatus::Contacted, Some(42), StatusSource::Button).unwrap();
+        assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Contacted);
+        assert_eq!(db.get_disappeared_at(&uuid).unwrap(), None);
+
+        db.mark_expired(&uuid, Utc::now()).unwrap();
+        db.update_status(&uuid, ListingStatus::Unchecked, None, StatusSource::Dashboard).unwrap();
+        assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Unchecked);
+    }
+
+    #[test]
+    fn test_status_events() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        assert!(db.get_status_events(&uuid).unwrap().is_empty());
+
+        db.update_status(&uuid, ListingStatus::Interesting, Some(1), StatusSource::Button).unwrap();
+        // Setting the same status again is not a change
+        db.update_status(&uuid, ListingStatus::Interesting, Some(2), StatusSource::Button).unwrap();
+        db.update_status(&uuid, ListingStatus::Unchecked, Some(2), StatusSource::Reaction).unwrap();
+
+        let events = db.get_status_events(&uuid).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].old_status, Some(ListingStatus::Unchecked));
+        assert_eq!(events[0].new_status, ListingStatus::Interesting);
+        assert_eq!(events[0].user_id, Some(1));
+        assert_eq!(events[1].new_status, ListingStatus::Unchecked);
+        assert_eq!(events[1].source, StatusSource::Reaction);
+    }
+
+    #[test]
+    fn test_invalid_status_change_is_rejected() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+
+        let error = db.update_status(&uuid, ListingStatus::Applied, Some(1), StatusSource::Button).unwrap_err();
+        assert!(error.downcast_ref::<lifecycle::InvalidTransition>().is_some());
+        assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Unchecked);
+        assert!(db.get_status_events(&uuid).unwrap().is_empty());
+
+        db.mark_expired(&uuid, Utc::now()).unwrap();
+        assert!(db.update_status(&uuid, ListingStatus::Interesting, Some(1), StatusSource::Reaction).is_err());
+    }
+
+    #[test]
+    fn test_votes() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+
+        assert_eq!(db.toggle_vote(&uuid, 1, Vote::Yes).unwrap(), Some(Vote::Yes));
+        assert_eq!(db.toggle_vote(&uuid, 2, Vote::No).unwrap(), Some(Vote::No));
+        // Changing one's mind replaces the vote
+        assert_eq!(db.toggle_vote(&uuid, 2, Vote::Yes).unwrap(), Some(Vote::Yes));
+        assert_eq!(db.get_vote_tally(&uuid).unwrap().yes, vec![1, 2]);
+
+        // Clicking the same vote again withdraws it
+        assert_eq!(db.toggle_vote(&uuid, 1, Vote::Yes).unwrap(), None);
+        let tally = db.get_vote_tally(&uuid).unwrap();
+        assert_eq!(tally.yes, vec![2]);
+        assert!(tally.no.is_empty());
+    }
+
+    #[test]
+    fn test_notes() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        assert!(db.get_notes(&uuid).unwrap().is_empty());
+
+        db.add_note(&uuid, 1, "alice", "Appelé, visite possible samedi").unwrap();
+        db.add_note(&uuid, 2, "bob", "Trop loin du métro").unwrap();
+
+        let notes = db.get_notes(&uuid).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].author, "alice");
+        assert_eq!(notes[1].content, "Trop loin du métro");
+    }
+
+    #[test]
+    fn test_visits() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        let now = Utc::now();
+
+        let soon = db.add_visit(&uuid, now + chrono::Duration::hours(3), &[1, 2], 1).unwrap();
+        let later = db.add_visit(&uuid, now + chrono::Duration::days(3), &[1], 1).unwrap();
+
+        // Too late for the day-before reminder of the visit in 3 hours
+        let visit = db.get_visit(soon).unwrap().unwrap();
+        assert!(visit.reminded_day_before);
+        assert!(!visit.reminded_hour_before);
+        assert_eq!(visit.attendees, vec![1, 2]);
+        assert!(!db.get_visit(later).unwrap().unwrap().reminded_day_before);
+
+        let upcoming = db.get_upcoming_visits(now).unwrap();
+        assert_eq!(upcoming.iter().map(|v| v.id).collect::<Vec<_>>(), vec![soon, later]);
+
+        db.mark_visit_reminder_sent(soon, VisitReminder::RatingRequest).unwrap();
+        assert_eq!(db.get_visits_awaiting_reminders().unwrap().len(), 1);
+
+        db.rate_visit(soon, 4, 2).unwrap();
+        assert_eq!(db.get_visit(soon).unwrap().unwrap().rating, Some(4));
+    }
+
+    #[test]
+    fn test_find_listing() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+
+        for reference in [uuid.to_string(), "leboncoin_1".to_string(), " https://example.com/1 ".to_string()] {
+            assert_eq!(db.find_listing(&reference).unwrap().unwrap().uuid, uuid);
+        }
+        assert!(db.find_listing("leboncoin_2").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_listing_annotations_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        assert_eq!(db.get_listing_annotations(&uuid).unwrap(), (None, None));
+
+        let report = ScamReport { score: 5, reasons: vec!["Prix bas".to_string(), "Virement".to_string()] };
+        let reposts = RepostSummary {
+            previous_postings: 2,
+            first_posted_at: Utc::now() - chrono::Duration::days(10),
+            previous_price: Some(750.0),
+        };
+        db.set_listing_annotations(&uuid, Some(&report), Some(&reposts)).unwrap();
+        assert_eq!(db.get_listing_annotations(&uuid).unwrap(), (Some(report), Some(reposts)));
+
+        db.set_listing_annotations(&uuid, None, None).unwrap();
+        assert_eq!(db.get_listing_annotations(&uuid).unwrap(), (None, None));
+    }
+
+    #[test]
+    fn test_get_previous_price() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        assert_eq!(db.get_previous_price(&uuid).unwrap(), None);
+
+        db.upsert_listing(&listing(Some(850.0))).unwrap();
+        db.upsert_listing(&listing(Some(800.0))).unwrap();
+        assert_eq!(db.get_previous_price(&uuid).unwrap(), Some(850.0));
+    }
+
+    #[test]
+    fn test_get_listing_uuid_by_message_id() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        db.set_main_channel_message_id(&uuid, 111).unwrap();
+        db.set_interesting_channel_message_id(&uuid, 222).unwrap();
+
+        assert_eq!(db.get_listing_uuid_by_message_id(111).unwrap(), Some(uuid));
+        assert_eq!(db.get_listing_uuid_by_message_id(222).unwrap(), Some(uuid));
+        assert_eq!(db.get_listing_uuid_by_message_id(333).unwrap(), None);
+    }
+
+    #[test]
+    fn test_notified_listings_are_not_new() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        assert_eq!(db.get_new_listings(60).unwrap().len(), 1);
+        assert!(!db.is_notified(&uuid).unwrap());
+
+        db.mark_notified(&uuid).unwrap();
+        assert!(db.get_new_listings(60).unwrap().is_empty());
+        assert!(db.is_notified(&uuid).unwrap());
+    }
+
+    #[test]
+    fn test_get_posted_listings() {
+        let db = Database::new(":memory:").unwrap();
+        let posted = db.upsert_listing(&listing(Some(900.0))).unwrap().uuid;
+        db.set_main_channel_message_id(&posted, 111).unwrap();
+
+        let mut skipped_listing = listing(None);
+        skipped_listing.id = "leboncoin_2".to_string();
+        let skipped = db.upsert_listing(&skipped_listing).unwrap().uuid;
+        db.set_main_channel_message_id(&skipped, 0).unwrap();
+
+        let mut duplicate_listing = listing(Some(900.0));
+        duplicate_listing.id = "leboncoin_3".to_string();
+        let duplicate = db.upsert_listing(&duplicate_listing).unwrap().uuid;
+        db.set_main_channel_message_id(&duplicate, 111).unwrap();
+        db.link_duplicate(&duplicate, &posted, 0.95).unwrap();
+
+        let mut unposted_listing = listing(Some(900.0));
+        unposted_listing.id = "leboncoin_4".to_string();
+        db.upsert_listing(&unposted_listing).unwrap();
+
+        let uuids: Vec<Uuid> = db.get_posted_listings().unwrap().into_iter().map(|record| record.uuid).collect();
+        assert_eq!(uuids.len(), 2);
+        assert!(uuids.contains(&posted) && uuids.contains(&skipped));
+    }
+
+    #[test]
+    fn test_fts_query_quotes_words() {
+        assert_eq!(fts_query("balcon  lumineux"), "\"balcon\" \"lumineux\"");
+        assert_eq!(fts_query("a\"b OR -c"), "\"ab\" \"OR\" \"-c\"");
+        assert_eq!(fts_query("  \"  "), "");
+    }
+
+    #[test]
+    fn test_search_ranks_and_filters() {
+        let db = Database::new(":memory:").unwrap();
+        let mut with_balcony = listing(Some(900.0));
+        with_balcony.description = Some("Bel appartement avec balcon et vue dégagée".to_string());
+        let balcony_uuid = db.upsert_listing(&with_balcony).unwrap().uuid;
+
+        let mut other = listing(Some(700.0));
+        other.id = "leboncoin_2".to_string();
+        other.title = "Studio avec balcon".to_string();
+        other.location = "Paris".to_string();
+        db.upsert_listing(&other).unwrap();
+
+        let filters = SearchFilters { limit: 10, ..Default::default() };
+        let results = db.search("balcon", &filters).unwrap();
+        assert_eq!(results.total, 2);
+        assert!(results.hits.iter().any(|hit| hit.snippet.contains("**balcon**")));
+
+        // Accents are ignored and location filters apply
+        let rennes = SearchFilters { location: Some("rennes".to_string()), ..filters.clone() };
+        let results = db.search("degagee", &rennes).unwrap();
+        assert_eq!(results.total, 1);
+        assert_eq!(results.hits[0].uuid, balcony_uuid);
+
+        // Updated descriptions are re-indexed
+        with_balcony.description = Some("Appartement avec terrasse".to_string());
+        db.upsert_listing(&with_balcony).unwrap();
+        assert_eq!(db.search("balcon", &rennes).unwrap().total, 0);
+        assert_eq!(db.search("terrasse", &rennes).unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_upsert_ignores_missing_fields() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        let outcome = db.upsert_listing(&listing(None)).unwrap();
+        assert!(outcome.changes.is_empty());
+
+        let record = db.get_listing_by_uuid(&first.uuid).unwrap().unwrap();
+        assert_eq!(record.price, Some(900.0));
+    }
+
+    #[test]
+    fn test_upsert_captures_coordinates_once_known() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.upsert_listing(&listing(Some(900.0))).unwrap();
+        assert_eq!(db.get_listing_by_uuid(&first.uuid).unwrap().unwrap().coordinates, None);
+
+        let coordinates = Coordinates { latitude: 48.11198, longitude: -1.67429 };
+        let outcome = db.upsert_listing(&Listing { coordinates: Some(coordinates), ..listing(Some(900.0)) }).unwrap();
+        assert!(outcome.changes.is_empty());
+        assert_eq!(history_count(&db), 0);
+        assert_eq!(db.get_listing_by_uuid(&first.uuid).unwrap().unwrap().coordinates, Some(coordinates));
+
+        // A scrape without them keeps the known ones
+        db.upsert_listing(&listing(Some(900.0))).unwrap();
+        let record = db.get_listing_by_uuid(&first.uuid).unwrap().unwrap();
+        assert_eq!(record.coordinates, Some(coordinates));
+        assert_eq!(record.to_listing().coordinates, Some(coordinates));
     }
 }
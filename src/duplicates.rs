@@ -0,0 +1,273 @@
+use crate::config::DuplicateDetectionConfig;
+use crate::database::Database;
+use crate::image_hash::{hamming_distance, SIMILAR_IMAGE_MAX_DISTANCE};
+use crate::models::Listing;
+use crate::scrapers::LeboncoinScraper;
+use anyhow::Result;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Relative price difference under which two prices are considered equal
+const PRICE_TOLERANCE: f64 = 0.03;
+/// Surface difference (in m²) under which two surfaces are considered equal
+const SURFACE_TOLERANCE: f64 = 1.5;
+
+/// A stored listing that the new listing most likely duplicates
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateMatch {
+    pub group_uuid: Uuid,
+    pub similarity: f64,
+}
+
+/// Look for an already announced listing describing the same apartment
+/// `pending` holds the listings of the current batch that are about to be announced
+pub fn find_duplicate(
+    listing: &Listing,
+    database: &Database,
+    config: &DuplicateDetectionConfig,
+    pending: &[(Uuid, Listing)],
+) -> Result<Option<DuplicateMatch>> {
+    let candidates = database.get_duplicate_candidates(&listing.location, &listing.id, config.lookback_days)?;
+
+    let mut best: Option<DuplicateMatch> = None;
+    for (uuid, candidate) in candidates.iter().chain(pending.iter().filter(|(_, pending)| pending.id != listing.id)) {
+        let same_photo = shares_photo(database, listing, candidate)?;
+        let score = similarity(listing, candidate, same_photo);
+        if score >= config.similarity_threshold && best.as_ref().is_none_or(|b| score > b.similarity) {
+            best = Some(DuplicateMatch { group_uuid: *uuid, similarity: score });
+        }
+    }
+
+    if let Some(found) = &best {
+        tracing::info!("Listing '{}' looks like a duplicate of {} (similarity {:.2})",
+            listing.title, found.group_uuid, found.similarity);
+    }
+
+    Ok(best)
+}
+
+/// Original listing of a duplicate and the sources to show on its messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateLink {
+    pub group_uuid: Uuid,
+    pub sources: Vec<(String, String)>,
+}
+
+/// Link a new listing to an announced (or `pending`) original if it is a duplicate
+///
+/// The duplicate is never announced on its own: it is shown on the original's messages instead.
+pub fn link_to_original(
+    database: &Database,
+    uuid: Uuid,
+    listing: &Listing,
+    config: &DuplicateDetectionConfig,
+    pending: &[(Uuid, Listing)],
+) -> Result<Option<DuplicateLink>> {
+    let Some(found) = find_duplicate(listing, database, config, pending)? else {
+        return Ok(None);
+    };
+
+    database.link_duplicate(&uuid, &found.group_uuid, found.similarity)?;
+
+    Ok(Some(DuplicateLink {
+        group_uuid: found.group_uuid,
+        sources: database.get_duplicate_sources(&found.group_uuid)?,
+    }))
+}
+
+/// Whether two listings use the same photo, by URL or by perceptual hash
+fn shares_photo(database: &Database, a: &Listing, b: &Listing) -> Result<bool> {
+    let (Some(url_a), Some(url_b)) = (&a.image_url, &b.image_url) else {
+        return Ok(false);
+    };
+    if url_a == url_b {
+        return Ok(true);
+    }
+
+    match (database.get_image_hash(url_a)?.flatten(), database.get_image_hash(url_b)?.flatten()) {
+        (Some(hash_a), Some(hash_b)) => Ok(hamming_distance(hash_a, hash_b) <= SIMILAR_IMAGE_MAX_DISTANCE),
+        _ => Ok(false),
+    }
+}
+
+/// Similarity score between two listings, from 0.0 (different) to 1.0 (identical)
+///
+/// Listings in different cities are never considered duplicates. Price and surface
+/// carry most of the weight, the title and rooms refine it, and a shared photo is
+/// a strong signal on its own.
+pub fn similarity(a: &Listing, b: &Listing, same_photo: bool) -> f64 {
+    if !a.location.eq_ignore_ascii_case(&b.location) {
+        return 0.0;
+    }
+
+    let mut score = 0.0;
+
+    if let (Some(price_a), Some(price_b)) = (a.price, b.price) {
+        if (price_a - price_b).abs() <= price_a.max(price_b) * PRICE_TOLERANCE {
+            score += 0.3;
+        }
+    }
+
+    if let (Some(surface_a), Some(surface_b)) = (a.surface, b.surface) {
+        if (surface_a - surface_b).abs() <= SURFACE_TOLERANCE {
+            score += 0.3;
+        }
+    }
+
+    if let (Some(rooms_a), Some(rooms_b)) = (LeboncoinScraper::parse_rooms(&a.title), LeboncoinScraper::parse_rooms(&b.title)) {
+        if rooms_a == rooms_b {
+            score += 0.1;
+        }
+    }
+
+    score += 0.2 * title_similarity(&a.title, &b.title);
+
+    if same_photo {
+        score += 0.4;
+    }
+
+    f64::min(score, 1.0)
+}
+
+/// Lowercase the title, strip accents and punctuation, and collapse whitespace
+pub fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Jaccard index of the normalized title words
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = normalize_title(a);
+    let b = normalize_title(b);
+    let words_a: HashSet<&str> = a.split(' ').filter(|w| !w.is_empty()).collect();
+    let words_b: HashSet<&str> = b.split(' ').filter(|w| !w.is_empty()).collect();
+
+    let union = words_a.union(&words_b).count();
+    if union == 0 {
+        return 0.0;
+    }
+
+    words_a.intersection(&words_b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(id: &str, title: &str, price: f64, surface: f64) -> Listing {
+        Listing {
+            title: title.to_string(),
+            surface: Some(surface),
+            image_url: Some(format!("https://img.example.com/{}.jpg", id)),
+            ..Listing::test(id, price)
+        }
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title("  Appartement T2, 45m² - Centre-Ville! "), "appartement t2 45m² centre ville");
+        assert_eq!(normalize_title("Très belle pièce"), "tres belle piece");
+    }
+
+    #[test]
+    fn test_repost_is_similar() {
+        let original = listing("1", "Appartement T2 45m² centre", 750.0, 45.0);
+        let repost = listing("2", "Appartement T2 45 m² - centre", 760.0, 45.0);
+        assert!(similarity(&original, &repost, false) >= 0.75);
+    }
+
+    #[test]
+    fn test_different_apartments_are_not_similar() {
+        let a = listing("1", "Appartement T2 45m² centre", 750.0, 45.0);
+        let b = listing("2", "Studio meublé 20m² gare", 500.0, 20.0);
+        assert!(similarity(&a, &b, false) < 0.3);
+    }
+
+    #[test]
+    fn test_shared_image_is_a_strong_signal() {
+        let a = listing("1", "Appartement T2 45m²", 750.0, 45.0);
+        let b = listing("2", "Bel appartement lumineux", 760.0, 46.0);
+        assert!(similarity(&a, &b, false) < 0.75);
+        assert!(similarity(&a, &b, true) >= 0.75);
+    }
+
+    #[test]
+    fn test_different_cities_never_match() {
+        let a = listing("1", "Appartement T2 45m²", 750.0, 45.0);
+        let mut b = a.clone();
+        b.id = "2".to_string();
+        b.location = "Paris".to_string();
+        assert_eq!(similarity(&a, &b, true), 0.0);
+    }
+
+    #[test]
+    fn test_shares_photo_by_url_or_hash() {
+        let db = Database::new(":memory:").unwrap();
+        let a = listing("1", "Appartement", 750.0, 45.0);
+        let mut b = listing("2", "Appartement", 750.0, 45.0);
+        assert!(!shares_photo(&db, &a, &b).unwrap());
+
+        db.set_image_hash(a.image_url.as_ref().unwrap(), Some(0b1111_0000)).unwrap();
+        db.set_image_hash(b.image_url.as_ref().unwrap(), Some(0b1111_0001)).unwrap();
+        assert!(shares_photo(&db, &a, &b).unwrap());
+
+        b.image_url = a.image_url.clone();
+        assert!(shares_photo(&db, &a, &b).unwrap());
+    }
+
+    #[test]
+    fn test_find_duplicate_only_matches_announced_listings() {
+        let db = Database::new(":memory:").unwrap();
+        let config = DuplicateDetectionConfig::default();
+        let original = listing("1", "Appartement T2 45m² centre", 750.0, 45.0);
+        let original_uuid = db.upsert_listing(&original).unwrap().uuid;
+        let repost = listing("2", "Appartement T2 45m² centre", 750.0, 45.0);
+
+        // Not announced yet, so it can't be the original of a group
+        assert_eq!(find_duplicate(&repost, &db, &config, &[]).unwrap(), None);
+
+        db.mark_notified(&original_uuid).unwrap();
+        let found = find_duplicate(&repost, &db, &config, &[]).unwrap().unwrap();
+        assert_eq!(found.group_uuid, original_uuid);
+
+        let repost_uuid = db.upsert_listing(&repost).unwrap().uuid;
+        let link = link_to_original(&db, repost_uuid, &repost, &config, &[]).unwrap().unwrap();
+        assert_eq!(link.group_uuid, original_uuid);
+        assert_eq!(link.sources, vec![("Leboncoin".to_string(), "https://example.com/2".to_string())]);
+
+        // The duplicate is never a candidate itself
+        db.mark_notified(&repost_uuid).unwrap();
+        let third = listing("3", "Appartement T2 45m² centre", 750.0, 45.0);
+        assert_eq!(find_duplicate(&third, &db, &config, &[]).unwrap().unwrap().group_uuid, original_uuid);
+    }
+
+    #[test]
+    fn test_find_duplicate_matches_pending_listings() {
+        let db = Database::new(":memory:").unwrap();
+        let config = DuplicateDetectionConfig::default();
+        let original = listing("1", "Appartement T2 45m² centre", 750.0, 45.0);
+        let original_uuid = db.upsert_listing(&original).unwrap().uuid;
+        let repost = listing("2", "Appartement T2 45m² centre", 750.0, 45.0);
+
+        // Both found in the same cycle: the first one is about to be announced
+        let pending = vec![(original_uuid, original.clone())];
+        let found = find_duplicate(&repost, &db, &config, &pending).unwrap().unwrap();
+        assert_eq!(found.group_uuid, original_uuid);
+        assert_eq!(find_duplicate(&original, &db, &config, &pending).unwrap(), None);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::config::{Config, FeedProfile, ListingFilter};
+use crate::database::{Database, ListingRecord, ListingStatus};
+use crate::lifecycle;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs;
+use std::path::Path;
+
+/// Number of recently announced listings the profiles pick their entries from
+const FEED_SCAN_LIMIT: usize = 1000;
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn timestamp(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// "Rennes" -> "rennes", "Saint-Malo (35)" -> "saint-malo-35"
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The configured profiles, or one per city plus "all"
+pub fn profiles(config: &Config) -> Vec<FeedProfile> {
+    if !config.feeds.profiles.is_empty() {
+        return config.feeds.profiles.clone();
+    }
+
+    let mut profiles: Vec<FeedProfile> = config.cities.iter()
+        .map(|city| FeedProfile {
+            name: slug(city),
+            filter: ListingFilter { cities: vec![city.clone()], ..Default::default() },
+        })
+        .collect();
+    profiles.push(FeedProfile { name: "all".to_string(), filter: ListingFilter::default() });
+    profiles
+}
+
+fn image_type(image_url: &str) -> &'static str {
+    let path = image_url.split('?').next().unwrap_or_default().to_lowercase();
+    if path.ends_with(".png") {
+        "image/png"
+    } else if path.ends_with(".webp") {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
+fn render_entry(record: &ListingRecord, notified_at: &DateTime<Utc>) -> String {
+    let mut details = Vec::new();
+    if let Some(price) = record.price {
+        details.push(format!("💰 {:.0}€", price));
+    }
+    if let Some(surface) = record.surface {
+        details.push(format!("📐 {:.0}m²", surface));
+    }
+    details.push(format!("📍 {}", record.location));
+    if record.status != ListingStatus::Unchecked {
+        details.push(format!("{} {}", lifecycle::emoji(&record.status), lifecycle::label(&record.status)));
+    }
+    let summary = details.join(" · ");
+
+    let title = match record.price {
+        Some(price) => format!("{} – {:.0}€", record.title, price),
+        None => record.title.clone(),
+    };
+
+    let mut content = String::new();
+    if let Some(image_url) = &record.image_url {
+        content.push_str(&format!("<p><img src=\"{}\" alt=\"\"></p>", xml_escape(image_url)));
+    }
+    content.push_str(&format!("<p>{}</p>", xml_escape(&summary)));
+    if let Some(description) = &record.description {
+        content.push_str(&format!("<p>{}</p>", xml_escape(description).replace('\n', "<br>")));
+    }
+
+    let mut entry = format!(
+        "  <entry>\n    <id>urn:uuid:{}</id>\n    <title>{}</title>\n    <link rel=\"alternate\" href=\"{}\"/>\n",
+        record.uuid,
+        xml_escape(&title),
+        xml_escape(&record.url)
+    );
+    if let Some(image_url) = &record.image_url {
+        entry.push_str(&format!(
+            "    <link rel=\"enclosure\" type=\"{}\" href=\"{}\"/>\n",
+            image_type(image_url),
+            xml_escape(image_url)
+        ));
+    }
+    entry.push_str(&format!(
+        "    <published>{}</published>\n    <updated>{}</updated>\n    <author><name>{}</name></author>\n    <category term=\"{}\"/>\n    <summary>{}</summary>\n    <content type=\"html\">{}</content>\n  </entry>\n",
+        timestamp(&record.posted_at),
+        timestamp(notified_at),
+        xml_escape(&record.source),
+        xml_escape(&record.location),
+        xml_escape(&summary),
+        // The HTML is escaped once more to be carried as text
+        xml_escape(&content)
+    ));
+    entry
+}
+
+/// Atom feed of the newest listings matching a profile
+fn render_feed(profile: &FeedProfile, entries: &[&(ListingRecord, DateTime<Utc>)]) -> String {
+    let updated = entries.first().map(|(_, notified_at)| *notified_at).unwrap_or_else(Utc::now);
+
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <id>urn:colocfinder:feed:{}</id>\n  <title>Colocfinder – {}</title>\n  <updated>{}</updated>\n  <generator>colocfinder</generator>\n",
+        xml_escape(&profile.name),
+        xml_escape(&profile.name),
+        timestamp(&updated)
+    );
+    for (record, notified_at) in entries {
+        feed.push_str(&render_entry(record, notified_at));
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+/// Write one feed per profile to the feeds directory
+/// Only announced listings appear, and those marked "Pas bien" are left out
+pub fn write_feeds(database: &Database, config: &Config) -> Result<usize> {
+    let directory = Path::new(&config.feeds.directory);
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    let recent: Vec<(ListingRecord, DateTime<Utc>)> = database.get_recently_notified_listings(FEED_SCAN_LIMIT)?
+        .into_iter()
+        .filter(|(record, _)| record.status != ListingStatus::NotGood)
+        .collect();
+
+    let profiles = profiles(config);
+    for profile in &profiles {
+        let entries: Vec<_> = recent.iter()
+            .filter(|(record, _)| profile.filter.matches(record))
+            .take(config.feeds.max_entries)
+            .collect();
+
+        // Written next to the feed then renamed, so that readers never get half a file
+        let path = directory.join(format!("{}.xml", slug(&profile.name)));
+        let partial = path.with_extension("xml.tmp");
+        fs::write(&partial, render_feed(profile, &entries))?;
+        fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(profiles.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Listing;
+    use crate::database::StatusSource;
+    use chrono::TimeZone;
+
+    fn listing(id: &str, location: &str, price: f64) -> Listing {
+        Listing {
+            title: format!("T2 {} & balcon", id),
+            location: location.to_string(),
+            url: format!("https://example.com/{}?a=1&b=2", id),
+            image_url: Some(format!("https://img.example.com/{}.webp", id)),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+            ..Listing::test(id, price)
+        }
+    }
+
+    fn config(directory: &Path) -> Config {
+        let mut config: Config = serde_yaml::from_str(
+            "discord_token: test\nchannel_id: 0\ninteresting_channel_id: 0\ncheck_interval_seconds: 300\ncities: [Rennes, Saint-Malo]\n"
+        ).unwrap();
+        config.feeds.enabled = true;
+        config.feeds.directory = directory.to_string_lossy().to_string();
+        config
+    }
+
+    #[test]
+    fn test_default_profiles() {
+        let config = config(Path::new("feeds"));
+        let names: Vec<String> = profiles(&config).into_iter().map(|profile| profile.name).collect();
+        assert_eq!(names, vec!["rennes", "saint-malo", "all"]);
+    }
+
+    #[test]
+    fn test_write_feeds() {
+        let directory = std::env::temp_dir().join(format!("colocfinder-feeds-{}", uuid::Uuid::new_v4()));
+        let config = config(&directory);
+        let db = Database::new(":memory:").unwrap();
+
+        let mut uuids = Vec::new();
+        for listing in [listing("rennes_1", "Rennes", 900.0), listing("malo_1", "Saint-Malo", 700.0), listing("rennes_2", "Rennes", 650.0)] {
+            let uuid = db.upsert_listing(&listing).unwrap().uuid;
+            db.mark_notified(&uuid).unwrap();
+            uuids.push(uuid);
+        }
+        // Not announced yet
+        db.upsert_listing(&listing("rennes_3", "Rennes", 600.0)).unwrap();
+        // Marked "Pas bien"
+        db.update_status(&uuids[2], ListingStatus::NotGood, Some(1), StatusSource::Button).unwrap();
+
+        assert_eq!(write_feeds(&db, &config).unwrap(), 3);
+
+        let rennes = fs::read_to_string(directory.join("rennes.xml")).unwrap();
+        assert!(rennes.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert_eq!(rennes.matches("<entry>").count(), 1);
+        assert!(rennes.contains(&format!("<id>urn:uuid:{}</id>", uuids[0])));
+        assert!(rennes.contains("<title>T2 rennes_1 &amp; balcon – 900€</title>"));
+        assert!(rennes.contains("<link rel=\"alternate\" href=\"https://example.com/rennes_1?a=1&amp;b=2\"/>"));
+        assert!(rennes.contains("<link rel=\"enclosure\" type=\"image/webp\" href=\"https://img.example.com/rennes_1.webp\"/>"));
+        assert!(rennes.contains("<summary>💰 900€ · 📐 45m² · 📍 Rennes</summary>"));
+        assert!(rennes.contains("<published>2025-03-04T17:30:00Z</published>"));
+
+        let all = fs::read_to_string(directory.join("all.xml")).unwrap();
+        assert_eq!(all.matches("<entry>").count(), 2);
+        assert!(fs::read_to_string(directory.join("saint-malo.xml")).unwrap().contains("malo_1"));
+        assert!(!directory.join("all.xml.tmp").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
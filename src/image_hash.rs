@@ -0,0 +1,235 @@
+use crate::database::{Database, ImageMatch};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use image::imageops::FilterType;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Maximum hash distance for two photos to be considered the same picture
+pub const SIMILAR_IMAGE_MAX_DISTANCE: u32 = 6;
+
+/// How long a failed image is left alone before it is downloaded again
+const FAILED_IMAGE_RETRY_HOURS: i64 = 24;
+
+/// Downloads listing photos and computes their perceptual hash (dHash)
+///
+/// Hashes are cached in the database by image URL, so each photo is only
+/// downloaded once. Failures are cached too to avoid retrying them on every
+/// scraping cycle, and retried once they are old enough to be a passing outage.
+pub struct ImageHasher {
+    client: reqwest::Client,
+    max_image_bytes: usize,
+}
+
+impl ImageHasher {
+    pub fn new(user_agent: &str, max_image_bytes: usize) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .timeout(std::time::Duration::from_secs(15))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            client,
+            max_image_bytes,
+        }
+    }
+
+    /// Get the hash of an image, downloading it only if it isn't cached yet
+    /// Returns None if the image could not be downloaded or decoded
+    pub async fn hash_for_url(&self, database: &Arc<Mutex<Database>>, image_url: &str) -> Option<u64> {
+        {
+            let db = database.lock().await;
+            match db.get_image_hash(image_url) {
+                Ok(Some(Some(hash))) => return Some(hash),
+                Ok(Some(None)) => match db.get_image_hash_failure(image_url) {
+                    Ok(Some(failed_at)) if !should_retry(failed_at, Utc::now()) => return None,
+                    Ok(_) => tracing::debug!("Retrying image {}", image_url),
+                    Err(e) => tracing::warn!("Failed to read image hash cache: {}", e),
+                },
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to read image hash cache: {}", e),
+            }
+        }
+
+        // Download without holding the database lock
+        let result = match self.download(image_url).await {
+            Ok(bytes) => dhash(&bytes),
+            Err(e) => Err(e),
+        };
+
+        let hash = match result {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                tracing::warn!("Failed to hash image {}: {}", image_url, e);
+                None
+            }
+        };
+
+        let db = database.lock().await;
+        if let Err(e) = db.set_image_hash(image_url, hash) {
+            tracing::error!("Failed to store image hash: {}", e);
+        }
+
+        hash
+    }
+
+    /// Download an image, refusing anything larger than the configured limit
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+
+        if let Some(length) = response.content_length() {
+            if length as usize > self.max_image_bytes {
+                bail!("image is too large ({} bytes, max {})", length, self.max_image_bytes);
+            }
+        }
+
+        // The server may lie about (or omit) the length, so also check while streaming
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > self.max_image_bytes {
+                bail!("image is larger than {} bytes", self.max_image_bytes);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Whether an image that failed to be hashed at `failed_at` should be tried again
+fn should_retry(failed_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - failed_at >= chrono::Duration::hours(FAILED_IMAGE_RETRY_HOURS)
+}
+
+/// Compute the 64-bit difference hash of an image
+///
+/// The image is reduced to a 9x8 grayscale thumbnail and each bit records
+/// whether a pixel is brighter than its right neighbour. Resizing, recompression
+/// and small edits barely change the hash, unlike a byte-level checksum.
+pub fn dhash(bytes: &[u8]) -> Result<u64> {
+    let image = image::load_from_memory(bytes)?;
+    let thumbnail = image::imageops::resize(&image.to_luma8(), 9, 8, FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = thumbnail.get_pixel(x, y)[0];
+            let right = thumbnail.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    Ok(hash)
+}
+
+/// Number of differing bits between two hashes (0 = identical images)
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Previous postings of the same photo, shown on reposted listings
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepostSummary {
+    pub previous_postings: usize,
+    pub first_posted_at: DateTime<Utc>,
+    pub previous_price: Option<f64>,
+}
+
+impl RepostSummary {
+    /// Summarize similar images found in the database (sorted oldest first)
+    pub fn from_matches(matches: &[ImageMatch]) -> Option<Self> {
+        let first = matches.first()?;
+        let last = matches.last()?;
+
+        Some(Self {
+            previous_postings: matches.len(),
+            first_posted_at: first.posted_at,
+            previous_price: last.price,
+        })
+    }
+
+    /// Human readable summary, e.g. "Publiée 3 fois en 14 jours, précédemment à 750€"
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        let days = now.signed_duration_since(self.first_posted_at).num_days().max(1);
+        let mut text = format!("Publiée {} fois en {} jour(s)", self.previous_postings + 1, days);
+        if let Some(price) = self.previous_price {
+            text.push_str(&format!(", précédemment à {:.0}€", price));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    /// Encode a horizontal gradient, optionally mirrored, as PNG or JPEG
+    fn gradient(width: u32, height: u32, mirrored: bool, format: ImageFormat) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            let x = if mirrored { width - 1 - x } else { x };
+            let value = ((x * 255) / width) as u8;
+            image::Rgb([value, value / 2, ((y * 255) / height) as u8])
+        });
+
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_dhash_is_stable_across_resize_and_format() {
+        let original = dhash(&gradient(320, 240, false, ImageFormat::Png)).unwrap();
+        let resized = dhash(&gradient(160, 120, false, ImageFormat::Jpeg)).unwrap();
+        assert!(hamming_distance(original, resized) <= 4);
+    }
+
+    #[test]
+    fn test_dhash_differs_for_different_images() {
+        let original = dhash(&gradient(320, 240, false, ImageFormat::Png)).unwrap();
+        let mirrored = dhash(&gradient(320, 240, true, ImageFormat::Png)).unwrap();
+        assert!(hamming_distance(original, mirrored) > 32);
+    }
+
+    #[test]
+    fn test_dhash_rejects_invalid_data() {
+        assert!(dhash(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_repost_summary() {
+        let now = Utc::now();
+        let image_match = |days_ago: i64, price: f64| ImageMatch {
+            uuid: uuid::Uuid::new_v4(),
+            listing_id: format!("listing_{}", days_ago),
+            location: "Rennes".to_string(),
+            price: Some(price),
+            posted_at: now - chrono::Duration::days(days_ago),
+            distance: 0,
+        };
+
+        assert_eq!(RepostSummary::from_matches(&[]), None);
+
+        let summary = RepostSummary::from_matches(&[image_match(14, 790.0), image_match(5, 750.0)]).unwrap();
+        assert_eq!(summary.previous_postings, 2);
+        assert_eq!(summary.describe(now), "Publiée 3 fois en 14 jour(s), précédemment à 750€");
+    }
+
+    #[test]
+    fn test_failed_images_are_retried_later() {
+        let now = Utc::now();
+        assert!(!should_retry(now - chrono::Duration::hours(1), now));
+        assert!(should_retry(now - chrono::Duration::hours(FAILED_IMAGE_RETRY_HOURS), now));
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(u64::MAX, 0), 64);
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use crate::database::ListingStatus;
+use crate::votes::Vote;
+
+/// A status change the lifecycle doesn't allow
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTransition {
+    pub from: ListingStatus,
+    pub to: ListingStatus,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a listing can't go from \"{}\" to \"{}\"", label(&self.from), label(&self.to))
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+/// Statuses a listing can move to from the given one
+///
+/// ```text
+/// Unchecked → Interesting → Contacted → Visited → Applied → Accepted
+///                                                         ↘ Rejected
+/// ```
+///
+/// Open listings can also be marked NotGood (undone with ❌) or Expired (removed from the website).
+/// An expired listing can be put back to Unchecked, or to its status before expiring (see `Database::update_status`),
+/// in case the liveness check was wrong.
+pub fn next_statuses(from: &ListingStatus) -> &'static [ListingStatus] {
+    use ListingStatus::*;
+    match from {
+        Unchecked => &[Interesting, NotGood, Expired],
+        Interesting => &[Contacted, Visited, NotGood, Unchecked, Expired],
+        Contacted => &[Visited, Applied, NotGood, Unchecked, Expired],
+        Visited => &[Applied, NotGood, Unchecked, Expired],
+        Applied => &[Accepted, Rejected, Expired],
+        Rejected => &[Unchecked, Expired],
+        NotGood => &[Unchecked, Expired],
+        Expired => &[Unchecked],
+        Accepted => &[],
+    }
+}
+
+/// Check a status change; staying in the same status is always allowed
+pub fn check_transition(from: &ListingStatus, to: &ListingStatus) -> Result<(), InvalidTransition> {
+    if from == to || next_statuses(from).contains(to) {
+        Ok(())
+    } else {
+        Err(InvalidTransition { from: from.clone(), to: to.clone() })
+    }
+}
+
+/// Statuses whose listings are also posted in the interesting channel
+pub fn shown_in_interesting_channel(status: &ListingStatus) -> bool {
+    matches!(
+        status,
+        ListingStatus::Interesting
+            | ListingStatus::Contacted
+            | ListingStatus::Visited
+            | ListingStatus::Applied
+            | ListingStatus::Accepted
+            | ListingStatus::Rejected
+    )
+}
+
+/// Statuses in which the listing is still being considered
+pub fn is_open(status: &ListingStatus) -> bool {
+    !matches!(status, ListingStatus::NotGood | ListingStatus::Expired)
+}
+
+/// Human readable name of a status (in French, as shown on Discord)
+pub fn label(status: &ListingStatus) -> &'static str {
+    match status {
+        ListingStatus::Unchecked => "Non vérifiée",
+        ListingStatus::Interesting => "Intéressante",
+        ListingStatus::Contacted => "Contactée",
+        ListingStatus::Visited => "Visitée",
+        ListingStatus::Applied => "Dossier envoyé",
+        ListingStatus::Accepted => "Acceptée",
+        ListingStatus::Rejected => "Refusée",
+        ListingStatus::NotGood => "Pas bien",
+        ListingStatus::Expired => "Retirée",
+    }
+}
+
+pub fn emoji(status: &ListingStatus) -> &'static str {
+    match status {
+        ListingStatus::Unchecked => "🆕",
+        ListingStatus::Interesting => "⭐",
+        ListingStatus::Contacted => "📞",
+        ListingStatus::Visited => "✅",
+        ListingStatus::Applied => "📨",
+        ListingStatus::Accepted => "🎉",
+        ListingStatus::Rejected => "🚫",
+        ListingStatus::NotGood => "👎",
+        ListingStatus::Expired => "❌",
+    }
+}
+
+/// Embed colour of a status, as RGB
+pub fn colour(status: &ListingStatus) -> (u8, u8, u8) {
+    match status {
+        ListingStatus::Unchecked => (139, 0, 0),      // Dark red: not looked at yet
+        ListingStatus::Interesting => (128, 0, 128),  // Purple
+        ListingStatus::Contacted => (52, 152, 219),   // Blue
+        ListingStatus::Visited => (26, 188, 156),     // Teal
+        ListingStatus::Applied => (230, 126, 34),     // Orange
+        ListingStatus::Accepted => (46, 204, 113),    // Green
+        ListingStatus::Rejected => (90, 90, 90),      // Dark gray
+        ListingStatus::NotGood => (0, 0, 0),          // Black
+        ListingStatus::Expired => (128, 128, 128),    // Gray
+    }
+}
+
+/// A button changing the status of a listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListingAction {
+    MarkInteresting,
+    MarkNotGood,
+    RemoveFromInteresting,
+    MarkContacted,
+    MarkApplied,
+    MarkAccepted,
+    MarkRejected,
+}
+
+impl ListingAction {
+    pub const ALL: [ListingAction; 7] = [
+        ListingAction::MarkInteresting,
+        ListingAction::MarkNotGood,
+        ListingAction::RemoveFromInteresting,
+        ListingAction::MarkContacted,
+        ListingAction::MarkApplied,
+        ListingAction::MarkAccepted,
+        ListingAction::MarkRejected,
+    ];
+
+    pub fn target(self) -> ListingStatus {
+        match self {
+            ListingAction::MarkInteresting => ListingStatus::Interesting,
+            ListingAction::MarkNotGood => ListingStatus::NotGood,
+            ListingAction::RemoveFromInteresting => ListingStatus::Unchecked,
+            ListingAction::MarkContacted => ListingStatus::Contacted,
+            ListingAction::MarkApplied => ListingStatus::Applied,
+            ListingAction::MarkAccepted => ListingStatus::Accepted,
+            ListingAction::MarkRejected => ListingStatus::Rejected,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ListingAction::MarkInteresting => "Intéressant",
+            ListingAction::MarkNotGood => "Pas bien",
+            ListingAction::RemoveFromInteresting => "Retirer",
+            ListingAction::MarkContacted => "📞 Contacté",
+            ListingAction::MarkApplied => "📨 Dossier envoyé",
+            ListingAction::MarkAccepted => "🎉 Accepté",
+            ListingAction::MarkRejected => "🚫 Refusé",
+        }
+    }
+
+    /// custom_id of the buttons posted before it carried the listing UUID
+    pub fn custom_id(self) -> &'static str {
+        match self {
+            ListingAction::MarkInteresting => "interesting_listing",
+            ListingAction::MarkNotGood => "not_good_listing",
+            ListingAction::RemoveFromInteresting => "remove_from_interesting",
+            ListingAction::MarkContacted => "contacted_listing",
+            ListingAction::MarkApplied => "applied_listing",
+            ListingAction::MarkAccepted => "accepted_listing",
+            ListingAction::MarkRejected => "rejected_listing",
+        }
+    }
+
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        ListingAction::ALL.into_iter().find(|action| action.custom_id() == custom_id)
+    }
+}
+
+/// A button shown on a listing message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListingButton {
+    Action(ListingAction),
+    /// Shown but greyed out, e.g. "Intéressant" once the listing was sent to the interesting channel
+    Done(ListingAction),
+    Vote(Vote),
+    Note,
+    ScheduleVisit,
+}
+
+/// Buttons of a listing in the main channel
+/// With voting enabled, members vote 👍/👎/🤷 instead of changing the status directly
+pub fn main_channel_buttons(status: &ListingStatus, voting_enabled: bool) -> Vec<ListingButton> {
+    use ListingButton::*;
+    if !is_open(status) {
+        return vec![];
+    }
+    if matches!(status, ListingStatus::Accepted | ListingStatus::Rejected) {
+        return vec![Note];
+    }
+
+    let mut buttons: Vec<ListingButton> = if voting_enabled {
+        crate::votes::Vote::ALL.into_iter().map(Vote).collect()
+    } else {
+        let interesting = if *status == ListingStatus::Unchecked {
+            Action(ListingAction::MarkInteresting)
+        } else {
+            Done(ListingAction::MarkInteresting)
+        };
+        let mut buttons = vec![interesting];
+        if check_transition(status, &ListingStatus::NotGood).is_ok() {
+            buttons.push(Action(ListingAction::MarkNotGood));
+        }
+        buttons
+    };
+    buttons.push(Note);
+    buttons
+}
+
+/// Buttons of a listing in the interesting channel, moving it along the lifecycle
+pub fn interesting_channel_buttons(status: &ListingStatus) -> Vec<ListingButton> {
+    use ListingAction::*;
+    use ListingButton::*;
+    match status {
+        ListingStatus::Interesting => vec![Action(RemoveFromInteresting), Action(MarkContacted), ScheduleVisit, Note],
+        ListingStatus::Contacted => vec![Action(RemoveFromInteresting), ScheduleVisit, Action(MarkApplied), Note],
+        ListingStatus::Visited => vec![Action(RemoveFromInteresting), Action(MarkApplied), Note],
+        ListingStatus::Applied => vec![Action(MarkAccepted), Action(MarkRejected), Note],
+        ListingStatus::Rejected => vec![Action(RemoveFromInteresting), Note],
+        ListingStatus::Accepted => vec![Note],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_happy_path() {
+        let path = [
+            ListingStatus::Unchecked,
+            ListingStatus::Interesting,
+            ListingStatus::Contacted,
+            ListingStatus::Visited,
+            ListingStatus::Applied,
+            ListingStatus::Accepted,
+        ];
+        for step in path.windows(2) {
+            assert_eq!(check_transition(&step[0], &step[1]), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_invalid_transitions() {
+        assert!(check_transition(&ListingStatus::Unchecked, &ListingStatus::Applied).is_err());
+        assert!(check_transition(&ListingStatus::NotGood, &ListingStatus::Interesting).is_err());
+        assert!(check_transition(&ListingStatus::Accepted, &ListingStatus::Unchecked).is_err());
+        assert!(check_transition(&ListingStatus::Expired, &ListingStatus::Interesting).is_err());
+
+        let error = check_transition(&ListingStatus::Applied, &ListingStatus::Unchecked).unwrap_err();
+        assert_eq!(error.to_string(), "a listing can't go from \"Dossier envoyé\" to \"Non vérifiée\"");
+    }
+
+    #[test]
+    fn test_same_status_is_allowed() {
+        for status in ListingStatus::ALL {
+            assert_eq!(check_transition(&status, &status), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_buttons_only_offer_allowed_transitions() {
+        for status in ListingStatus::ALL {
+            let buttons = main_channel_buttons(&status, false)
+                .into_iter()
+                .chain(interesting_channel_buttons(&status));
+            for button in buttons {
+                if let ListingButton::Action(action) = button {
+                    assert!(
+                        check_transition(&status, &action.target()).is_ok(),
+                        "{:?} offers {:?}",
+                        status,
+                        action
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_closed_listings_have_no_buttons() {
+        assert!(main_channel_buttons(&ListingStatus::NotGood, true).is_empty());
+        assert!(main_channel_buttons(&ListingStatus::Expired, false).is_empty());
+        assert!(interesting_channel_buttons(&ListingStatus::Unchecked).is_empty());
+    }
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        for action in ListingAction::ALL {
+            assert_eq!(ListingAction::from_custom_id(action.custom_id()), Some(action));
+        }
+        assert_eq!(ListingAction::from_custom_id("vote_yes"), None);
+    }
+
+    #[test]
+    fn test_every_status_has_its_own_colour() {
+        for (i, a) in ListingStatus::ALL.iter().enumerate() {
+            for b in &ListingStatus::ALL[i + 1..] {
+                assert_ne!(colour(a), colour(b), "{:?} and {:?} share a colour", a, b);
+            }
+        }
+    }
+}
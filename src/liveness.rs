@@ -0,0 +1,139 @@
+use crate::database::{Database, ListingRecord, ListingStatus};
+use anyhow::Result;
+use chrono::Utc;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Texts shown by Leboncoin when an ad was taken down (matched case-insensitively)
+const REMOVED_MARKERS: &[&str] = &[
+    "annonce désactivée",
+    "cette annonce est désactivée",
+    "cette annonce n'est plus disponible",
+    "cette annonce n’est plus disponible",
+    "l'annonce n'existe plus",
+    "annonce expirée",
+    "cette annonce a été supprimée",
+];
+
+/// Statuses whose listings are periodically checked
+const CHECKED_STATUSES: &[ListingStatus] = &[
+    ListingStatus::Interesting,
+    ListingStatus::Contacted,
+    ListingStatus::Visited,
+    ListingStatus::Applied,
+];
+
+/// Whether a listing is still online
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Liveness {
+    Alive,
+    Gone,
+    /// Captcha, network error or server error: try again next time
+    Unknown,
+}
+
+/// Classify the response of a listing page
+pub fn classify_response(status: StatusCode, body: &str) -> Liveness {
+    if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+        return Liveness::Gone;
+    }
+    if !status.is_success() {
+        return Liveness::Unknown;
+    }
+
+    let body = body.to_lowercase();
+    if REMOVED_MARKERS.iter().any(|marker| body.contains(marker)) {
+        Liveness::Gone
+    } else {
+        Liveness::Alive
+    }
+}
+
+/// Re-fetches the pages of followed listings to find the ones that were taken down
+pub struct LivenessChecker {
+    client: reqwest::Client,
+    request_delay_ms: u64,
+}
+
+impl LivenessChecker {
+    pub fn new(client: reqwest::Client, request_delay_ms: u64) -> Self {
+        Self {
+            client,
+            request_delay_ms,
+        }
+    }
+
+    async fn check_url(&self, url: &str) -> Liveness {
+        match self.client.get(url).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                classify_response(status, &body)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to check listing {}: {}", url, e);
+                Liveness::Unknown
+            }
+        }
+    }
+
+    /// Check every followed listing (interesting up to applied) and mark the removed ones as Expired
+    /// Returns the records of the listings that just expired
+    pub async fn check_listings(&self, database: &Arc<Mutex<Database>>) -> Result<Vec<ListingRecord>> {
+        let records = database.lock().await.get_listings_by_status(CHECKED_STATUSES)?;
+        tracing::info!("Checking whether {} followed listings are still online", records.len());
+
+        let mut expired = Vec::new();
+        for mut record in records {
+            let liveness = self.check_url(&record.url).await;
+            tracing::debug!("Listing '{}' is {:?}", record.title, liveness);
+
+            if liveness == Liveness::Gone {
+                tracing::info!("Listing '{}' was removed from {}", record.title, record.source);
+                // Keep going, so that the listings already expired in this pass are still reported
+                match database.lock().await.mark_expired(&record.uuid, Utc::now()) {
+                    Ok(()) => {
+                        record.status = ListingStatus::Expired;
+                        expired.push(record);
+                    }
+                    Err(e) => tracing::error!("Failed to mark '{}' as expired: {}", record.title, e),
+                }
+            }
+
+            // Be nice to the server - use configured delay
+            tokio::time::sleep(tokio::time::Duration::from_millis(self.request_delay_ms)).await;
+        }
+
+        Ok(expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_found_is_gone() {
+        assert_eq!(classify_response(StatusCode::NOT_FOUND, ""), Liveness::Gone);
+        assert_eq!(classify_response(StatusCode::GONE, ""), Liveness::Gone);
+    }
+
+    #[test]
+    fn test_deactivated_page_is_gone() {
+        let body = "<html><h1>Cette annonce est désactivée</h1></html>";
+        assert_eq!(classify_response(StatusCode::OK, body), Liveness::Gone);
+    }
+
+    #[test]
+    fn test_regular_page_is_alive() {
+        let body = "<html><h1>Appartement T2 45m²</h1><p>850 €</p></html>";
+        assert_eq!(classify_response(StatusCode::OK, body), Liveness::Alive);
+    }
+
+    #[test]
+    fn test_blocked_page_is_unknown() {
+        assert_eq!(classify_response(StatusCode::FORBIDDEN, "captcha"), Liveness::Unknown);
+        assert_eq!(classify_response(StatusCode::INTERNAL_SERVER_ERROR, ""), Liveness::Unknown);
+    }
+}
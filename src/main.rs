@@ -1,22 +1,42 @@
+mod api;
 mod bot;
 mod config;
 mod database;
+mod duplicates;
+mod feeds;
 mod http_client;
+mod image_hash;
+mod lifecycle;
+mod liveness;
+mod migrations;
 mod models;
+mod notifier_trait;
+mod notifiers;
+mod pipeline;
+mod scam;
 mod scraper_trait;
 mod scrapers;
-mod tracker;
+mod visits;
+mod votes;
 
 use anyhow::Result;
-use bot::{get_intents, send_listing_notification, Bot};
-use clap::Parser;
+use api::ApiState;
+use bot::{get_intents, send_visit_reminders, reconcile, Bot, ListingChannels, NotifierRegistryKey};
+use clap::{Parser, Subcommand};
 use config::Config;
 use database::Database;
+use image_hash::ImageHasher;
+use liveness::LivenessChecker;
+use notifier_trait::NotifierRegistry;
+use notifiers::matrix::{MatrixApi, MatrixReactions};
+use notifiers::telegram::{TelegramApi, TelegramCallbacks};
+use notifiers::{DiscordNotifier, EmailDigest, MatrixNotifier, PushNotifier, TelegramNotifier, WebhookNotifier};
+use pipeline::run_scrape_cycle;
 use scraper_trait::ScraperRegistry;
 use scrapers::LeboncoinScraper;
 use serenity::Client;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 
 #[derive(Parser, Debug)]
 #[command(name = "colocfinder")]
@@ -33,12 +53,53 @@ struct Args {
     /// Save HTML to file when using --test-url
     #[arg(long)]
     save_html: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommands {
+    /// Apply pending schema migrations
+    Migrate {
+        /// Only show the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Path to the SQLite database
+        #[arg(long, default_value = "data/listings.db")]
+        database: String,
+    },
+    /// Show the webhook events that could not be delivered
+    DeadLetters {
+        /// Number of events to show, newest first
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Path to the SQLite database
+        #[arg(long, default_value = "data/listings.db")]
+        database: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Handle db subcommands
+    if let Some(Commands::Db { command }) = args.command {
+        return run_db_command(command);
+    }
+
     // Handle test-url command
     if let Some(url) = args.test_url {
         return test_url_fetch(&url, args.save_html.as_deref()).await;
@@ -140,6 +201,8 @@ async fn main() -> Result<()> {
     let bot = Bot::new();
     bot.set_channel_id(config.channel_id);
     bot.set_interesting_channel_id(config.interesting_channel_id);
+    bot.set_quarantine_channel_id(config.scam_detection.quarantine_channel_id);
+    bot.set_voting_config(config.voting.clone());
     let paused_state = bot.get_paused_state();
     let db_for_bot = db.clone();
     bot.set_database(db_for_bot);
@@ -151,18 +214,138 @@ async fn main() -> Result<()> {
 
     let http = client.http.clone();
 
+    // Every output the listings are delivered to
+    let mut notifiers = NotifierRegistry::new();
+    notifiers.register(Box::new(DiscordNotifier::new(http.clone(), db.clone(), ListingChannels::from_config(&config))));
+    let telegram_api = if config.telegram.is_configured() {
+        let api = TelegramApi::new(&config.telegram)?;
+        notifiers.register(Box::new(TelegramNotifier::new(api.clone(), config.telegram.chat_id, db.clone())));
+        Some(api)
+    } else {
+        None
+    };
+    let matrix_api = if config.matrix.is_configured() {
+        let api = MatrixApi::new(&config.matrix)?;
+        notifiers.register(Box::new(MatrixNotifier::new(api.clone(), config.matrix.room_id.clone(), db.clone())));
+        Some(api)
+    } else {
+        None
+    };
+    for webhook in &config.webhooks {
+        notifiers.register(Box::new(WebhookNotifier::new(webhook.clone(), db.clone())?));
+    }
+    if config.push.is_configured() {
+        notifiers.register(Box::new(PushNotifier::new(config.push.clone())?));
+    }
+    let email_digest = if config.email_digest.is_configured() {
+        let digest = EmailDigest::new(config.email_digest.clone(), db.clone())?;
+        notifiers.register(Box::new(digest.clone()));
+        Some(digest)
+    } else {
+        None
+    };
+    tracing::info!("Registered notifiers: {:?}", notifiers.list_notifiers());
+    let notifiers = Arc::new(notifiers);
+    // The buttons bring every notifier up to date
+    client.data.write().await.insert::<NotifierRegistryKey>(notifiers.clone());
+
+    // Spawn the task receiving the clicks on the Telegram buttons
+    if let Some(api) = telegram_api {
+        let db = db.clone();
+        let notifiers = notifiers.clone();
+
+        tokio::spawn(async move {
+            let mut callbacks = TelegramCallbacks::new(api);
+            loop {
+                if let Err(e) = callbacks.poll(&db, &notifiers).await {
+                    tracing::error!("Failed to fetch Telegram updates: {}", e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                }
+            }
+        });
+    }
+
+    // Spawn the task sending the email digests
+    if let Some(digest) = email_digest {
+        tokio::spawn(async move {
+            loop {
+                let now = chrono::Local::now().naive_local();
+                let wait = (digest.next_digest_at(now) - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                let sent = digest.send_digests().await;
+                tracing::info!("Sent {} email digest(s)", sent);
+            }
+        });
+    }
+
+    // Spawn the task receiving the reactions of the Matrix room
+    if let Some(api) = matrix_api {
+        let db = db.clone();
+        let notifiers = notifiers.clone();
+        let room_id = config.matrix.room_id.clone();
+
+        tokio::spawn(async move {
+            let mut reactions = MatrixReactions::new(api, room_id);
+            loop {
+                if let Err(e) = reactions.poll(&db, &notifiers).await {
+                    tracing::error!("Failed to sync with Matrix: {}", e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                }
+            }
+        });
+    }
+
+    let image_hasher = config.image_hashing.enabled
+        .then(|| ImageHasher::new(&config.user_agent, config.image_hashing.max_image_bytes));
+
     // Spawn scraping task
     let registry = Arc::new(registry);
+    let scrape_now = Arc::new(Notify::new());
+
+    if config.api.enabled {
+        let api_config = config.api.clone();
+        let state = ApiState {
+            database: db.clone(),
+            notifiers: notifiers.clone(),
+            scrapers: registry.clone(),
+            scrape_now: scrape_now.clone(),
+            paused: paused_state.clone(),
+            token: config.api.token.clone(),
+            map: config.map.clone(),
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(&api_config, state).await {
+                tracing::error!("REST API stopped: {}", e);
+            }
+        });
+    }
+
     let config_clone = config.clone();
     let db_clone = db.clone();
+    let channels = ListingChannels::from_config(&config);
+    let notifiers_clone = notifiers.clone();
 
     tokio::spawn(async move {
+        // Before the first cycle, so that listings posted right before a crash aren't posted twice
+        if config_clone.reconcile_on_startup {
+            match reconcile(&http, &db_clone, channels, false).await {
+                Ok(report) => tracing::info!("Startup reconciliation: {}", report.summary()),
+                Err(e) => tracing::error!("Startup reconciliation failed: {}", e),
+            }
+        }
+
         let mut interval = tokio::time::interval(
             tokio::time::Duration::from_secs(config_clone.check_interval_seconds)
         );
 
         loop {
-            interval.tick().await;
+            // Scrape on schedule, or right away when asked through the API
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scrape_now.notified() => {}
+            }
 
             // Check if the bot is paused
             let is_paused = *paused_state.lock().await;
@@ -173,82 +356,70 @@ async fn main() -> Result<()> {
 
             tracing::info!("Starting scraping cycle...");
 
-            match registry.scrape_all(&config_clone.cities).await {
-                Ok(listings) => {
-                    tracing::info!("Found {} total listings", listings.len());
-
-                    // Insert listings into database
-                    let db = db_clone.lock().await;
-                    let mut new_count = 0;
-
-                    for listing in listings {
-                        if !listing.has_sufficient_info() {
-                            tracing::debug!("Skipping listing '{}' - insufficient information", listing.title);
-                            continue;
-                        }
-
-                        match db.insert_or_get_listing(&listing) {
-                            Ok(uuid) => {
-                                // Check if this listing has been posted yet
-                                if let Ok(Some(record)) = db.get_listing_by_uuid(&uuid) {
-                                    if record.main_channel_message_id.is_none() {
-                                        new_count += 1;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to insert listing into database: {}", e);
-                            }
-                        }
-                    }
+            if let Err(e) = run_scrape_cycle(&registry, &db_clone, &notifiers_clone, image_hasher.as_ref(), &config_clone).await {
+                tracing::error!("Scraping failed: {}", e);
+            }
 
-                    if new_count == 0 {
-                        tracing::info!("No new listings to post");
-                    } else {
-                        tracing::info!("Found {} new listings to post!", new_count);
-
-                        // Get new listings from database
-                        match db.get_new_listings(config_clone.max_listing_age_minutes) {
-                            Ok(new_listings) => {
-                                drop(db); // Release lock before sending messages
-
-                                // Send notifications
-                                for (uuid, listing) in new_listings {
-                                    if let Err(e) = send_listing_notification(
-                                        &http,
-                                        config_clone.channel_id,
-                                        &listing,
-                                        uuid,
-                                        db_clone.clone(),
-                                    ).await {
-                                        tracing::error!("Failed to send notification: {}", e);
-                                    } else {
-                                        tracing::info!("Sent notification for: {}", listing.title);
-                                    }
-
-                                    // Small delay between messages
-                                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to get new listings from database: {}", e);
-                            }
-                        }
-
-                        // Clean up old unposted listings from database
-                        let db = db_clone.lock().await;
-                        if let Err(e) = db.cleanup_old_listings(config_clone.max_listing_age_minutes) {
-                            tracing::error!("Failed to cleanup old listings: {}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Scraping failed: {}", e);
+            if config_clone.feeds.enabled {
+                if let Err(e) = feeds::write_feeds(&*db_clone.lock().await, &config_clone) {
+                    tracing::error!("Failed to write the feeds: {}", e);
                 }
             }
         }
     });
 
+    // Spawn the task checking whether followed listings are still online
+    if config.liveness_check_interval_minutes > 0 {
+        let checker = LivenessChecker::new(
+            http_client::create_http_client(&config.user_agent)?,
+            config.request_delay_ms,
+        );
+        let config = config.clone();
+        let db = db.clone();
+        let notifiers = notifiers.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(
+                tokio::time::Duration::from_secs(config.liveness_check_interval_minutes * 60)
+            );
+
+            loop {
+                interval.tick().await;
+
+                let expired = match checker.check_listings(&db).await {
+                    Ok(expired) => expired,
+                    Err(e) => {
+                        tracing::error!("Liveness check failed: {}", e);
+                        continue;
+                    }
+                };
+
+                for record in expired {
+                    notifiers.update_listing(&record).await;
+                }
+            }
+        });
+    }
+
+    // Spawn the task posting visit reminders and rating requests
+    if config.interesting_channel_id != 0 {
+        let http = client.http.clone();
+        let interesting_channel_id = config.interesting_channel_id;
+        let db = db.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = send_visit_reminders(&http, interesting_channel_id, &db).await {
+                    tracing::error!("Failed to send visit reminders: {}", e);
+                }
+            }
+        });
+    }
+
     tracing::info!("Bot is starting...");
 
     // Start the bot
@@ -257,6 +428,58 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Run a `colocfinder db ...` subcommand
+fn run_db_command(command: DbCommands) -> Result<()> {
+    match command {
+        DbCommands::Migrate { dry_run, database } => {
+            let db = Database::open(&database)?;
+            println!("Database: {}", database);
+            println!("Current schema version: {}", db.schema_version()?);
+
+            let pending = db.pending_migrations()?;
+            if pending.is_empty() {
+                println!("Schema is up to date");
+                return Ok(());
+            }
+
+            println!("Pending migrations:");
+            for migration in &pending {
+                println!("  {:04} {}", migration.version, migration.name);
+            }
+
+            if dry_run {
+                println!("Dry run: no migration applied");
+            } else {
+                let applied = db.migrate()?;
+                println!("Applied {} migration(s), schema is now at version {}", applied.len(), db.schema_version()?);
+            }
+        }
+        DbCommands::DeadLetters { limit, database } => {
+            let db = Database::new(&database)?;
+            let letters = db.get_webhook_dead_letters(limit)?;
+            if letters.is_empty() {
+                println!("No undeliverable webhook event");
+            }
+            for letter in letters {
+                println!(
+                    "{} {} {} -> {} ({} attempt(s)): {}",
+                    letter.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    letter.event,
+                    letter.uuid,
+                    letter.url,
+                    letter.attempts,
+                    letter.error
+                );
+                if let Some(payload) = letter.payload {
+                    println!("  {}", payload);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Test URL fetching - downloads and prints HTML response
 async fn test_url_fetch(url: &str, save_path: Option<&str>) -> Result<()> {
     println!("Testing URL fetch: {}", url);
@@ -0,0 +1,305 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+
+/// A versioned schema change, embedded in the binary
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// All migrations, in the order they must be applied
+///
+/// Never edit a migration that was released: existing databases already ran it.
+/// Add a new one at the end instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_listings",
+        sql: include_str!("migrations/0001_create_listings.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "create_listing_duplicates",
+        sql: include_str!("migrations/0002_create_listing_duplicates.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_image_hashes",
+        sql: include_str!("migrations/0003_create_image_hashes.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "create_listing_history",
+        sql: include_str!("migrations/0004_create_listing_history.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "create_listing_removals",
+        sql: include_str!("migrations/0005_create_listing_removals.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_listings_fts",
+        sql: include_str!("migrations/0006_create_listings_fts.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "create_status_events",
+        sql: include_str!("migrations/0007_create_status_events.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "create_listing_votes",
+        sql: include_str!("migrations/0008_create_listing_votes.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "create_listing_notes",
+        sql: include_str!("migrations/0009_create_listing_notes.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "create_visits",
+        sql: include_str!("migrations/0010_create_visits.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "create_listing_annotations",
+        sql: include_str!("migrations/0011_create_listing_annotations.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "add_listings_notified_at",
+        sql: include_str!("migrations/0012_add_listings_notified_at.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "create_telegram_messages",
+        sql: include_str!("migrations/0013_create_telegram_messages.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "create_matrix_messages",
+        sql: include_str!("migrations/0014_create_matrix_messages.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "create_webhook_tables",
+        sql: include_str!("migrations/0015_create_webhook_tables.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "create_email_digest_entries",
+        sql: include_str!("migrations/0016_create_email_digest_entries.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "create_push_deliveries",
+        sql: include_str!("migrations/0017_create_push_deliveries.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "add_listings_coordinates",
+        sql: include_str!("migrations/0018_add_listings_coordinates.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "create_notifier_deliveries",
+        sql: include_str!("migrations/0019_create_notifier_deliveries.sql"),
+    },
+    Migration {
+        version: 20,
+        name: "add_matrix_messages_image_uri",
+        sql: include_str!("migrations/0020_add_matrix_messages_image_uri.sql"),
+    },
+    Migration {
+        version: 21,
+        name: "drop_webhook_deliveries",
+        sql: include_str!("migrations/0021_drop_webhook_deliveries.sql"),
+    },
+    Migration {
+        version: 22,
+        name: "drop_push_deliveries",
+        sql: include_str!("migrations/0022_drop_push_deliveries.sql"),
+    },
+];
+
+fn ensure_version_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version of the last applied migration (0 for a new or pre-migration database)
+/// Read-only, so it can be used for dry runs
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    let has_version_table: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_version_table {
+        return Ok(0);
+    }
+
+    let version: Option<u32> = conn.query_row(
+        "SELECT MAX(version) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Migrations that have not been applied yet
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    let current = current_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// Apply every pending migration in a single transaction
+/// Either all of them are applied or none is
+pub fn run(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    migrate_to(conn, u32::MAX)
+}
+
+/// Apply pending migrations up to (and including) the given version
+pub fn migrate_to(conn: &Connection, target_version: u32) -> Result<Vec<&'static Migration>> {
+    let to_apply: Vec<_> = pending(conn)?
+        .into_iter()
+        .filter(|m| m.version <= target_version)
+        .collect();
+
+    if to_apply.is_empty() {
+        return Ok(to_apply);
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    ensure_version_table(&tx)?;
+    for migration in &to_apply {
+        tracing::info!("Applying database migration {} ({})", migration.version, migration.name);
+        tx.execute_batch(migration.sql)
+            .with_context(|| format!("Migration {} ({}) failed", migration.version, migration.name))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, Utc::now()],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(to_apply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![name],
+            |row| row.get(0),
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_versions_are_sequential() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, index + 1, "migration {} is out of order", migration.name);
+        }
+    }
+
+    #[test]
+    fn test_new_database_gets_every_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(pending(&conn).unwrap().len(), MIGRATIONS.len());
+
+        let applied = run(&conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), MIGRATIONS.last().unwrap().version);
+        assert!(pending(&conn).unwrap().is_empty());
+
+        // Running again is a no-op
+        assert!(run(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_upgrade_from_every_older_version_keeps_data() {
+        for start_version in 1..MIGRATIONS.len() as u32 {
+            let conn = Connection::open_in_memory().unwrap();
+            migrate_to(&conn, start_version).unwrap();
+            conn.execute(
+                "INSERT INTO listings (uuid, listing_id, title, location, url, source, scraped_at)
+                 VALUES ('uuid-1', 'leboncoin_1', 'T2', 'Rennes', 'https://example.com', 'Leboncoin', '2025-01-01T00:00:00Z')",
+                [],
+            ).unwrap();
+
+            let applied = run(&conn).unwrap();
+            assert_eq!(applied.len(), MIGRATIONS.len() - start_version as usize);
+            assert_eq!(current_version(&conn).unwrap(), MIGRATIONS.last().unwrap().version);
+
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM listings", [], |row| row.get(0)).unwrap();
+            assert_eq!(count, 1, "data lost when upgrading from version {}", start_version);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_database_created_before_migrations() {
+        // Databases created by the old init_schema have tables but no schema_version
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO listings (uuid, listing_id, title, location, url, source, scraped_at)
+             VALUES ('uuid-1', 'leboncoin_1', 'T2', 'Rennes', 'https://example.com', 'Leboncoin', '2025-01-01T00:00:00Z')",
+            [],
+        ).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        run(&conn).unwrap();
+        assert!(table_exists(&conn, "listing_removals"));
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM listings", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_webhook_deliveries_move_to_notifier_deliveries() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 20).unwrap();
+        conn.execute(
+            "INSERT INTO listings (uuid, listing_id, title, location, url, source, scraped_at)
+             VALUES ('uuid-1', 'leboncoin_1', 'T2', 'Rennes', 'https://example.com', 'Leboncoin', '2025-01-01T00:00:00Z')",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO webhook_deliveries (url, uuid, delivered_at) VALUES ('https://example.com/hook', 'uuid-1', '2025-01-01T00:00:00Z')",
+            [],
+        ).unwrap();
+
+        run(&conn).unwrap();
+        assert!(!table_exists(&conn, "webhook_deliveries"));
+        let notifier: String = conn
+            .query_row("SELECT notifier FROM notifier_deliveries WHERE uuid = 'uuid-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(notifier, "Webhook https://example.com/hook");
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 1).unwrap();
+        // Migration 2 fails because views can't be indexed
+        conn.execute_batch("CREATE VIEW listing_duplicates AS SELECT 1 AS group_uuid;").unwrap();
+
+        assert!(run(&conn).is_err());
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        assert!(!table_exists(&conn, "image_hashes"));
+    }
+}
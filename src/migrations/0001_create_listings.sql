@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS listings (
+    uuid TEXT PRIMARY KEY,
+    listing_id TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    price REAL,
+    surface REAL,
+    location TEXT NOT NULL,
+    url TEXT NOT NULL,
+    image_url TEXT,
+    description TEXT,
+    posted_at TEXT,
+    source TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'unchecked',
+    scraped_at TEXT NOT NULL,
+    main_channel_message_id INTEGER,
+    interesting_channel_message_id INTEGER
+);
+
+-- Create index on listing_id for faster lookups
+CREATE INDEX IF NOT EXISTS idx_listing_id ON listings(listing_id);
+
+-- Create index on status for filtering
+CREATE INDEX IF NOT EXISTS idx_status ON listings(status);
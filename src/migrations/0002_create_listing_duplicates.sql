@@ -0,0 +1,9 @@
+-- Duplicate groups: each duplicate points at the first posted listing of its group
+CREATE TABLE IF NOT EXISTS listing_duplicates (
+    uuid TEXT PRIMARY KEY,
+    group_uuid TEXT NOT NULL,
+    similarity REAL NOT NULL,
+    linked_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_duplicate_group ON listing_duplicates(group_uuid);
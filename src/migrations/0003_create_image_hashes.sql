@@ -0,0 +1,6 @@
+-- Perceptual hashes of listing photos, NULL when the image could not be hashed
+CREATE TABLE IF NOT EXISTS image_hashes (
+    image_url TEXT PRIMARY KEY,
+    hash INTEGER,
+    computed_at TEXT NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Field changes detected when a known listing is scraped again
+CREATE TABLE IF NOT EXISTS listing_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    changed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_history_uuid ON listing_history(uuid);
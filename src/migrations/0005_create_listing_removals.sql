@@ -0,0 +1,6 @@
+-- When listings were detected as removed from their website
+CREATE TABLE IF NOT EXISTS listing_removals (
+    uuid TEXT PRIMARY KEY,
+    previous_status TEXT NOT NULL,
+    disappeared_at TEXT NOT NULL
+);
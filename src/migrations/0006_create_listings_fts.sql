@@ -0,0 +1,29 @@
+-- Full-text index over the listings, kept in sync by triggers
+CREATE VIRTUAL TABLE IF NOT EXISTS listings_fts USING fts5(
+    title,
+    description,
+    location,
+    content = 'listings',
+    content_rowid = 'rowid',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS listings_fts_insert AFTER INSERT ON listings BEGIN
+    INSERT INTO listings_fts (rowid, title, description, location)
+    VALUES (new.rowid, new.title, new.description, new.location);
+END;
+
+CREATE TRIGGER IF NOT EXISTS listings_fts_delete AFTER DELETE ON listings BEGIN
+    INSERT INTO listings_fts (listings_fts, rowid, title, description, location)
+    VALUES ('delete', old.rowid, old.title, old.description, old.location);
+END;
+
+CREATE TRIGGER IF NOT EXISTS listings_fts_update AFTER UPDATE OF title, description, location ON listings BEGIN
+    INSERT INTO listings_fts (listings_fts, rowid, title, description, location)
+    VALUES ('delete', old.rowid, old.title, old.description, old.location);
+    INSERT INTO listings_fts (rowid, title, description, location)
+    VALUES (new.rowid, new.title, new.description, new.location);
+END;
+
+-- Index the listings stored before this migration
+INSERT INTO listings_fts (listings_fts) VALUES ('rebuild');
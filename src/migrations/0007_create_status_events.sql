@@ -0,0 +1,13 @@
+-- Who changed the status of a listing, when, and from where
+CREATE TABLE IF NOT EXISTS status_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    old_status TEXT,
+    new_status TEXT NOT NULL,
+    user_id INTEGER,
+    source TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_status_events_uuid ON status_events(uuid, created_at);
@@ -0,0 +1,9 @@
+-- One vote per Discord member and listing
+CREATE TABLE IF NOT EXISTS listing_votes (
+    uuid TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    vote TEXT NOT NULL,
+    voted_at TEXT NOT NULL,
+    PRIMARY KEY (uuid, user_id),
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
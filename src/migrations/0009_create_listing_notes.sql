@@ -0,0 +1,12 @@
+-- Free-text notes left by members on a listing
+CREATE TABLE IF NOT EXISTS listing_notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    author TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_listing_notes_uuid ON listing_notes(uuid, created_at);
@@ -0,0 +1,17 @@
+-- Scheduled visits, their reminders and the post-visit rating
+CREATE TABLE IF NOT EXISTS visits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    scheduled_at TEXT NOT NULL,
+    attendees TEXT NOT NULL,
+    created_by INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    reminded_day_before INTEGER NOT NULL DEFAULT 0,
+    reminded_hour_before INTEGER NOT NULL DEFAULT 0,
+    rating_requested INTEGER NOT NULL DEFAULT 0,
+    rating INTEGER,
+    rated_by INTEGER,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_visits_scheduled_at ON visits(scheduled_at);
@@ -0,0 +1,10 @@
+-- Scam warning and repost summary computed when a listing was posted, shown again whenever its messages are re-rendered
+CREATE TABLE IF NOT EXISTS listing_annotations (
+    uuid TEXT PRIMARY KEY,
+    scam_score INTEGER,
+    scam_reasons TEXT,
+    repost_count INTEGER,
+    repost_first_posted_at TEXT,
+    repost_previous_price REAL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
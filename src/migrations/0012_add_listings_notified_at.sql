@@ -0,0 +1,3 @@
+-- When a listing was handed to the notifiers, independently of the Discord message ids
+ALTER TABLE listings ADD COLUMN notified_at TEXT;
+UPDATE listings SET notified_at = scraped_at WHERE main_channel_message_id IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Telegram message of each listing, to edit it when the listing changes
+CREATE TABLE IF NOT EXISTS telegram_messages (
+    uuid TEXT PRIMARY KEY,
+    chat_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    -- Photo messages are edited through their caption, text messages through their text
+    has_photo INTEGER NOT NULL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
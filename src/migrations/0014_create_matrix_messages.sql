@@ -0,0 +1,9 @@
+-- Matrix event of each listing, to edit it and to find the listing its reactions are about
+CREATE TABLE IF NOT EXISTS matrix_messages (
+    uuid TEXT PRIMARY KEY,
+    room_id TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_matrix_messages_event_id ON matrix_messages(event_id);
@@ -0,0 +1,21 @@
+-- New listings each webhook received, so that they are not posted twice
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    url TEXT NOT NULL,
+    uuid TEXT NOT NULL,
+    delivered_at TEXT NOT NULL,
+    PRIMARY KEY (url, uuid),
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
+
+-- Webhook events given up on after every retry failed
+CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    event TEXT NOT NULL,
+    uuid TEXT NOT NULL,
+    -- Rendered body, NULL when the template failed
+    payload TEXT,
+    attempts INTEGER NOT NULL,
+    error TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+-- Listings of each recipient's email digest; sent_at stays NULL until the digest went out
+CREATE TABLE IF NOT EXISTS email_digest_entries (
+    uuid TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    queued_at TEXT NOT NULL,
+    sent_at TEXT,
+    PRIMARY KEY (uuid, recipient),
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
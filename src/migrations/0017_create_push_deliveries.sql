@@ -0,0 +1,6 @@
+-- Listings already pushed to the phones, so that they are not pushed twice
+CREATE TABLE IF NOT EXISTS push_deliveries (
+    uuid TEXT PRIMARY KEY,
+    pushed_at TEXT NOT NULL,
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
@@ -0,0 +1,3 @@
+-- Where the flat is, for the map of the dashboard (NULL when the website doesn't tell)
+ALTER TABLE listings ADD COLUMN latitude REAL;
+ALTER TABLE listings ADD COLUMN longitude REAL;
@@ -0,0 +1,8 @@
+-- Notifiers each new listing was announced on, so that a failing one doesn't get the others announced again
+CREATE TABLE IF NOT EXISTS notifier_deliveries (
+    uuid TEXT NOT NULL,
+    notifier TEXT NOT NULL,
+    delivered_at TEXT NOT NULL,
+    PRIMARY KEY (uuid, notifier),
+    FOREIGN KEY (uuid) REFERENCES listings(uuid)
+);
@@ -0,0 +1,2 @@
+-- mxc:// URI of the photo uploaded with the first message, reused by its edits
+ALTER TABLE matrix_messages ADD COLUMN image_uri TEXT;
@@ -0,0 +1,5 @@
+-- Webhooks rely on notifier_deliveries like the other notifiers, under their "Webhook <url>" name
+INSERT OR IGNORE INTO notifier_deliveries (uuid, notifier, delivered_at)
+SELECT uuid, 'Webhook ' || url, delivered_at FROM webhook_deliveries;
+
+DROP TABLE IF EXISTS webhook_deliveries;
@@ -0,0 +1,8 @@
+-- Push notifiers rely on notifier_deliveries like the other notifiers
+-- The service isn't known here, so the listings count as pushed by both
+INSERT OR IGNORE INTO notifier_deliveries (uuid, notifier, delivered_at)
+SELECT uuid, 'ntfy', pushed_at FROM push_deliveries;
+INSERT OR IGNORE INTO notifier_deliveries (uuid, notifier, delivered_at)
+SELECT uuid, 'Gotify', pushed_at FROM push_deliveries;
+
+DROP TABLE IF EXISTS push_deliveries;
@@ -1,6 +1,25 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Where the flat is, as published by the website (often only approximate)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Distance as the crow flies, in kilometres
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Listing {
     pub id: String,
@@ -13,6 +32,8 @@ pub struct Listing {
     pub description: Option<String>,
     pub posted_at: DateTime<Utc>, // Mandatory - listings without time should be filtered out
     pub source: String,
+    #[serde(default)]
+    pub coordinates: Option<Coordinates>,
 }
 
 impl Listing {
@@ -22,29 +43,24 @@ impl Listing {
         !self.title.trim().is_empty()
             && (self.price.is_some() || self.surface.is_some())
     }
+}
 
-    pub fn format_discord_message(&self) -> String {
-        let mut message = format!("🏠 **New Apartment Listing**\n");
-        message.push_str(&format!("**{}**\n", self.title));
-
-        if let Some(price) = self.price {
-            message.push_str(&format!("💰 Price: {:.2}€\n", price));
-        }
-
-        message.push_str(&format!("📍 Location: {}\n", self.location));
-
-        if let Some(desc) = &self.description {
-            let truncated = if desc.len() > 200 {
-                format!("{}...", &desc[..200])
-            } else {
-                desc.clone()
-            };
-            message.push_str(&format!("📝 {}\n", truncated));
+#[cfg(test)]
+impl Listing {
+    /// A complete listing for the tests, to adjust with the struct update syntax
+    pub fn test(id: &str, price: f64) -> Self {
+        Listing {
+            id: id.to_string(),
+            title: format!("Appartement {}", id),
+            price: Some(price),
+            surface: Some(45.0),
+            location: "Rennes".to_string(),
+            url: format!("https://example.com/{}", id),
+            image_url: None,
+            description: None,
+            posted_at: Utc::now(),
+            source: "Leboncoin".to_string(),
+            coordinates: None,
         }
-
-        message.push_str(&format!("🔗 {}\n", self.url));
-        message.push_str(&format!("🌐 Source: {}", self.source));
-
-        message
     }
 }
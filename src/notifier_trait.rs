@@ -0,0 +1,145 @@
+use crate::database::{Database, ListingRecord, ListingStatus, StatusSource};
+use crate::image_hash::RepostSummary;
+use crate::scam::ScamReport;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Extra information computed before posting a listing
+#[derive(Debug, Default)]
+pub struct ListingAnnotations {
+    /// Set when the listing is posted to the quarantine channel
+    pub quarantined: bool,
+    pub scam_warning: Option<ScamReport>,
+    pub reposts: Option<RepostSummary>,
+}
+
+/// Something worth telling about a listing besides its own message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alert {
+    PriceDrop { old_price: f64 },
+}
+
+/// Trait that all notification sinks must implement
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Returns the name of the notifier
+    fn name(&self) -> &str;
+
+    /// Announces a listing seen for the first time
+    async fn notify_new_listing(&self, record: &ListingRecord, annotations: &ListingAnnotations) -> Result<()>;
+
+    /// Brings an announced listing up to date, e.g. after a price or status change
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()>;
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()>;
+
+    /// Returns whether this notifier is enabled
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+/// Registry fanning out the notifications to every configured notifier
+pub struct NotifierRegistry {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl NotifierRegistry {
+    pub fn new() -> Self {
+        Self {
+            notifiers: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, notifier: Box<dyn Notifier>) {
+        self.notifiers.push(notifier);
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &dyn Notifier> {
+        self.notifiers.iter().map(|notifier| notifier.as_ref()).filter(|notifier| notifier.is_enabled())
+    }
+
+    /// Errors if any notifier failed, after giving every one of them the listing
+    /// Notifiers that already announced the listing in a previous attempt are skipped
+    pub async fn notify_new_listing(
+        &self,
+        database: &Arc<Mutex<Database>>,
+        record: &ListingRecord,
+        annotations: &ListingAnnotations,
+    ) -> Result<()> {
+        let mut failed = Vec::new();
+        for notifier in self.enabled() {
+            match database.lock().await.is_delivered(&record.uuid, notifier.name()) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to check deliveries of '{}': {}", record.title, e),
+            }
+
+            if let Err(e) = notifier.notify_new_listing(record, annotations).await {
+                tracing::error!("Failed to notify {} of '{}': {}", notifier.name(), record.title, e);
+                failed.push(notifier.name().to_string());
+            } else if let Err(e) = database.lock().await.mark_delivered(&record.uuid, notifier.name()) {
+                tracing::error!("Failed to record delivery of '{}' to {}: {}", record.title, notifier.name(), e);
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("notifier(s) failed: {}", failed.join(", ")))
+        }
+    }
+
+    pub async fn update_listing(&self, record: &ListingRecord) {
+        for notifier in self.enabled() {
+            if let Err(e) = notifier.update_listing(record).await {
+                tracing::error!("Failed to update '{}' on {}: {}", record.title, notifier.name(), e);
+            }
+        }
+    }
+
+    pub async fn send_alert(&self, record: &ListingRecord, alert: &Alert) {
+        for notifier in self.enabled() {
+            if let Err(e) = notifier.send_alert(record, alert).await {
+                tracing::error!("Failed to send {:?} for '{}' to {}: {}", alert, record.title, notifier.name(), e);
+            }
+        }
+    }
+
+    /// Change the status of a listing and bring it up to date on every notifier
+    /// Fails with `InvalidTransition` when the lifecycle doesn't allow the change, leaving everything untouched
+    pub async fn change_status(
+        &self,
+        database: &Arc<Mutex<Database>>,
+        uuid: &Uuid,
+        status: ListingStatus,
+        user_id: Option<u64>,
+        source: StatusSource,
+    ) -> Result<ListingRecord> {
+        let record = {
+            let db = database.lock().await;
+            db.update_status(uuid, status, user_id, source)?;
+            db.get_listing_by_uuid(uuid)?
+                .ok_or_else(|| anyhow::anyhow!("listing {} disappeared", uuid))?
+        };
+
+        tracing::info!("Listing {} is now {}", uuid, crate::lifecycle::label(&record.status));
+        self.update_listing(&record).await;
+        Ok(record)
+    }
+
+    pub fn list_notifiers(&self) -> Vec<String> {
+        self.notifiers.iter()
+            .map(|n| n.name().to_string())
+            .collect()
+    }
+}
+
+impl Default for NotifierRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::bot::{refresh_listing_messages, send_listing_notification, send_price_drop_notification, ListingChannels};
+use crate::database::{Database, ListingRecord};
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier};
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::all::Http;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Posts listings to the Discord channels, with buttons to triage them
+pub struct DiscordNotifier {
+    http: Arc<Http>,
+    database: Arc<Mutex<Database>>,
+    channels: ListingChannels,
+}
+
+impl DiscordNotifier {
+    pub fn new(http: Arc<Http>, database: Arc<Mutex<Database>>, channels: ListingChannels) -> Self {
+        Self { http, database, channels }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    async fn notify_new_listing(&self, record: &ListingRecord, annotations: &ListingAnnotations) -> Result<()> {
+        // Suspicious listings go to the quarantine channel when configured
+        let channel_id = if annotations.quarantined {
+            tracing::warn!("Quarantining suspicious listing: {}", record.title);
+            self.channels.quarantine.or(self.channels.main)
+        } else {
+            self.channels.main
+        };
+        let Some(channel_id) = channel_id else {
+            return Ok(());
+        };
+
+        send_listing_notification(
+            &self.http,
+            channel_id,
+            &record.to_listing(),
+            record.uuid,
+            self.database.clone(),
+            annotations,
+            self.channels.voting_enabled,
+        ).await?;
+        Ok(())
+    }
+
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()> {
+        refresh_listing_messages(&self.http, &self.database, record.uuid, self.channels).await?;
+        Ok(())
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        let Some(interesting_channel_id) = self.channels.interesting else {
+            return Ok(());
+        };
+        match *alert {
+            Alert::PriceDrop { old_price } => {
+                send_price_drop_notification(&self.http, interesting_channel_id, record, old_price).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use super::escape_html;
+use crate::config::{DigestRecipient, DigestSchedule, EmailDigestConfig, SmtpSecurity};
+use crate::database::{Database, ListingRecord};
+use crate::lifecycle;
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use lettre::message::MultiPart;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// When the next digest is due, in local time
+pub fn next_digest_at(schedule: DigestSchedule, send_at_hour: u32, now: NaiveDateTime) -> NaiveDateTime {
+    let this_hour = now.date().and_hms_opt(now.hour(), 0, 0).unwrap_or(now);
+    match schedule {
+        DigestSchedule::Hourly => this_hour + Duration::hours(1),
+        DigestSchedule::Daily => {
+            let today = now.date().and_hms_opt(send_at_hour.min(23), 0, 0).unwrap_or(now);
+            if today > now { today } else { today + Duration::days(1) }
+        }
+    }
+}
+
+/// Cheapest per m² first, listings without a price or a surface last
+fn by_price_per_m2(a: &&ListingRecord, b: &&ListingRecord) -> Ordering {
+    match (a.price_per_m2(), b.price_per_m2()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn details(record: &ListingRecord) -> String {
+    let mut details = Vec::new();
+    if let Some(price) = record.price {
+        details.push(format!("{:.0}€", price));
+    }
+    if let Some(surface) = record.surface {
+        details.push(format!("{:.0}m²", surface));
+    }
+    if let Some(price_per_m2) = record.price_per_m2() {
+        details.push(format!("{:.1}€/m²", price_per_m2));
+    }
+    details.join(" · ")
+}
+
+/// Subject, plain text and HTML of a digest, grouped by city and sorted by price per m²
+fn render_digest(records: &[ListingRecord]) -> (String, String, String) {
+    let mut cities: BTreeMap<&str, Vec<&ListingRecord>> = BTreeMap::new();
+    for record in records {
+        cities.entry(record.location.as_str()).or_default().push(record);
+    }
+
+    let count = match records.len() {
+        1 => "1 nouvelle annonce".to_string(),
+        count => format!("{} nouvelles annonces", count),
+    };
+    let subject = format!("🏠 {} ({})", count, cities.keys().copied().collect::<Vec<_>>().join(", "));
+
+    let mut plain = String::new();
+    let mut html = format!(
+        "<html><body style=\"font-family: sans-serif\"><h1>🏠 {}</h1>",
+        escape_html(&count)
+    );
+    for (city, mut listings) in cities {
+        listings.sort_by(by_price_per_m2);
+
+        plain.push_str(&format!("== {} ({}) ==\n\n", city, listings.len()));
+        html.push_str(&format!("<h2>{} ({})</h2><table cellpadding=\"6\">", escape_html(city), listings.len()));
+        for record in listings {
+            let posted = format!("Publiée le {}", record.posted_at.format("%d/%m/%Y à %H:%M"));
+            let status = if lifecycle::shown_in_interesting_channel(&record.status) {
+                format!(" · {} {}", lifecycle::emoji(&record.status), lifecycle::label(&record.status))
+            } else {
+                String::new()
+            };
+
+            plain.push_str(&format!("{}\n{}{}\n{}\n{}\n\n", record.title, details(record), status, posted, record.url));
+
+            let thumbnail = record.image_url.as_ref()
+                .map(|image_url| format!("<img src=\"{}\" width=\"120\" alt=\"\">", escape_html(image_url)))
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<tr><td valign=\"top\">{}</td><td valign=\"top\"><a href=\"{}\"><b>{}</b></a><br>{}{}<br><small>{} · {}</small></td></tr>",
+                thumbnail,
+                escape_html(&record.url),
+                escape_html(&record.title),
+                escape_html(&details(record)),
+                escape_html(&status),
+                posted,
+                escape_html(&record.source)
+            ));
+        }
+        html.push_str("</table>");
+    }
+    html.push_str("</body></html>");
+
+    (subject, plain, html)
+}
+
+/// Collects the new listings and emails them as a periodic digest
+/// The notifier only queues the listings; `send_digests` is called on the configured schedule
+#[derive(Clone)]
+pub struct EmailDigest {
+    config: EmailDigestConfig,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    database: Arc<Mutex<Database>>,
+}
+
+impl EmailDigest {
+    pub fn new(config: EmailDigestConfig, database: Arc<Mutex<Database>>) -> Result<Self> {
+        let mut builder = match config.smtp_security {
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+        }
+        .port(config.smtp_port);
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(config.smtp_username.clone(), config.smtp_password.clone()));
+        }
+
+        Ok(Self { transport: builder.build(), config, database })
+    }
+
+    pub fn next_digest_at(&self, now: NaiveDateTime) -> NaiveDateTime {
+        next_digest_at(self.config.schedule, self.config.send_at_hour, now)
+    }
+
+    /// Email every recipient the listings queued since their last digest
+    /// Returns the number of emails sent
+    pub async fn send_digests(&self) -> usize {
+        let mut sent = 0;
+        for recipient in &self.config.recipients {
+            match self.send_digest(recipient).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to send the digest to {}: {}", recipient.email, e),
+            }
+        }
+        sent
+    }
+
+    async fn send_digest(&self, recipient: &DigestRecipient) -> Result<bool> {
+        let (uuids, records) = {
+            let db = self.database.lock().await;
+            let uuids = db.get_pending_digest(&recipient.email)?;
+            let mut records = Vec::new();
+            for uuid in &uuids {
+                // Listings marked "Pas bien" or removed in the meantime are left out
+                if let Some(record) = db.get_listing_by_uuid(uuid)?.filter(|record| lifecycle::is_open(&record.status)) {
+                    records.push(record);
+                }
+            }
+            (uuids, records)
+        };
+
+        if !records.is_empty() {
+            let (subject, plain, html) = render_digest(&records);
+            let message = Message::builder()
+                .from(self.config.from.parse()?)
+                .to(recipient.email.parse()?)
+                .subject(subject)
+                .multipart(MultiPart::alternative_plain_html(plain, html))?;
+            self.transport.send(message).await?;
+            tracing::info!("Sent a digest of {} listing(s) to {}", records.len(), recipient.email);
+        }
+
+        self.database.lock().await.mark_digest_sent(&recipient.email, &uuids)?;
+        Ok(!records.is_empty())
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailDigest {
+    fn name(&self) -> &str {
+        "Email digest"
+    }
+
+    /// Quarantined listings are kept out of the digests
+    async fn notify_new_listing(&self, record: &ListingRecord, annotations: &ListingAnnotations) -> Result<()> {
+        if annotations.quarantined {
+            return Ok(());
+        }
+
+        let db = self.database.lock().await;
+        for recipient in self.config.recipients.iter().filter(|recipient| recipient.filter.matches(record)) {
+            db.queue_for_digest(&record.uuid, &recipient.email)?;
+        }
+        Ok(())
+    }
+
+    /// The digest shows the listings as they are when it is sent
+    async fn update_listing(&self, _record: &ListingRecord) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send_alert(&self, _record: &ListingRecord, _alert: &Alert) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ListingFilter;
+    use crate::models::Listing;
+    use crate::notifiers::smtp_sink::SmtpSink;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn listing(id: &str, location: &str, price: f64, surface: f64) -> Listing {
+        Listing {
+            surface: Some(surface),
+            location: location.to_string(),
+            image_url: Some(format!("https://img.example.com/{}.jpg", id)),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+            ..Listing::test(id, price)
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, 4).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_next_digest_at() {
+        assert_eq!(next_digest_at(DigestSchedule::Hourly, 8, at(10, 25)), at(11, 0));
+        assert_eq!(next_digest_at(DigestSchedule::Daily, 8, at(7, 59)), at(8, 0));
+        assert_eq!(next_digest_at(DigestSchedule::Daily, 8, at(8, 0)), at(8, 0) + Duration::days(1));
+        assert_eq!(next_digest_at(DigestSchedule::Daily, 8, at(20, 0)), at(8, 0) + Duration::days(1));
+    }
+
+    #[test]
+    fn test_digest_is_grouped_by_city_and_sorted_by_price_per_m2() {
+        let db = Database::new(":memory:").unwrap();
+        let records: Vec<ListingRecord> = [
+            listing("pricey", "Rennes", 900.0, 30.0),
+            listing("nantes", "Nantes", 700.0, 40.0),
+            listing("cheap", "Rennes", 800.0, 50.0),
+        ]
+        .iter()
+        .map(|listing| {
+            let uuid = db.upsert_listing(listing).unwrap().uuid;
+            db.get_listing_by_uuid(&uuid).unwrap().unwrap()
+        })
+        .collect();
+
+        let (subject, plain, html) = render_digest(&records);
+        assert_eq!(subject, "🏠 3 nouvelles annonces (Nantes, Rennes)");
+        assert!(plain.starts_with("== Nantes (1) ==\n\nAppartement nantes\n700€ · 40m² · 17.5€/m²\n"));
+        let position = |text: &str| html.find(text).unwrap();
+        assert!(position("<h2>Nantes (1)</h2>") < position("<h2>Rennes (2)</h2>"));
+        // 16€/m² before 30€/m²
+        assert!(position("https://example.com/cheap") < position("https://example.com/pricey"));
+        assert!(html.contains("<img src=\"https://img.example.com/cheap.jpg\" width=\"120\" alt=\"\">"));
+    }
+
+    #[tokio::test]
+    async fn test_digest_is_sent_per_recipient_with_filters() {
+        let sink = SmtpSink::start().await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let config = EmailDigestConfig {
+            smtp_host: sink.host.clone(),
+            smtp_port: sink.port,
+            smtp_security: SmtpSecurity::None,
+            from: "Colocfinder <bot@example.org>".to_string(),
+            recipients: vec![
+                DigestRecipient {
+                    email: "parent@example.org".to_string(),
+                    filter: ListingFilter { cities: vec!["rennes".to_string()], ..Default::default() },
+                },
+                DigestRecipient {
+                    email: "budget@example.org".to_string(),
+                    filter: ListingFilter { max_price: Some(500.0), ..Default::default() },
+                },
+            ],
+            ..Default::default()
+        };
+        let digest = EmailDigest::new(config, database.clone()).unwrap();
+
+        for listing in [listing("rennes_1", "Rennes", 800.0, 50.0), listing("nantes_1", "Nantes", 700.0, 40.0)] {
+            let record = {
+                let db = database.lock().await;
+                let uuid = db.upsert_listing(&listing).unwrap().uuid;
+                db.get_listing_by_uuid(&uuid).unwrap().unwrap()
+            };
+            digest.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+            // Announced again after another notifier failed: still a single entry
+            digest.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+        }
+
+        // Nothing matches the budget recipient
+        assert_eq!(digest.send_digests().await, 1);
+        let emails = sink.emails();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].from, "bot@example.org");
+        assert_eq!(emails[0].to, vec!["parent@example.org"]);
+        // Undo the quoted-printable line wrapping
+        let data = emails[0].data.replace("=\r\n", "");
+        assert!(data.contains("https://example.com/rennes_1"));
+        assert!(!data.contains("https://example.com/nantes_1"));
+
+        // Already sent
+        assert_eq!(digest.send_digests().await, 0);
+        assert_eq!(sink.emails().len(), 1);
+    }
+}
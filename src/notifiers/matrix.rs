@@ -0,0 +1,519 @@
+use super::escape_html;
+use crate::config::MatrixConfig;
+use crate::database::{Database, ListingRecord, ListingStatus, StatusSource};
+use crate::lifecycle::{self, InvalidTransition, ListingAction};
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier, NotifierRegistry};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// How long /sync waits for new reactions before returning
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+
+/// Listing photos larger than this are not copied to the homeserver
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// The Matrix event of a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixMessage {
+    pub room_id: String,
+    pub event_id: String,
+    /// mxc:// URI of the photo uploaded with the listing, reused when the event is edited
+    pub image_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SentEvent {
+    event_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Upload {
+    content_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: SyncRooms,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SyncRooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinedRoom {
+    timeline: Timeline,
+}
+
+#[derive(Debug, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    #[serde(default)]
+    content: Value,
+}
+
+/// Client of the Matrix client-server API
+#[derive(Clone)]
+pub struct MatrixApi {
+    client: reqwest::Client,
+    homeserver_url: String,
+    access_token: String,
+}
+
+impl MatrixApi {
+    pub fn new(config: &MatrixConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(SYNC_TIMEOUT_MS + 30_000))
+            .build()?;
+        Ok(Self {
+            client,
+            homeserver_url: config.homeserver_url.trim_end_matches('/').to_string(),
+            access_token: config.access_token.clone(),
+        })
+    }
+
+    async fn send_request<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder, what: &str) -> Result<T> {
+        let response = request
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .with_context(|| format!("Matrix {} request failed", what))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            // Errors are {"errcode": "M_FORBIDDEN", "error": "..."}
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Matrix {} failed with {}: {}", what, status, body);
+        }
+        response.json().await.with_context(|| format!("Invalid Matrix {} response", what))
+    }
+
+    /// Send an event to a room; the transaction id makes retries of the same event harmless
+    async fn send_message(&self, room_id: &str, transaction_id: &str, content: &Value) -> Result<String> {
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            self.homeserver_url,
+            urlencoding::encode(room_id),
+            urlencoding::encode(transaction_id)
+        );
+        let sent: SentEvent = self.send_request(self.client.put(url).json(content), "send").await?;
+        Ok(sent.event_id)
+    }
+
+    /// Upload a file to the media repository and return its mxc:// URI
+    async fn upload(&self, filename: &str, content_type: &str, data: Vec<u8>) -> Result<String> {
+        let url = format!("{}/_matrix/media/v3/upload?filename={}", self.homeserver_url, urlencoding::encode(filename));
+        let request = self.client.post(url).header(reqwest::header::CONTENT_TYPE, content_type).body(data);
+        let upload: Upload = self.send_request(request, "upload").await?;
+        Ok(upload.content_uri)
+    }
+
+    async fn sync(&self, since: Option<&str>, filter: &Value, timeout_ms: u64) -> Result<SyncResponse> {
+        let mut url = format!(
+            "{}/_matrix/client/v3/sync?filter={}&timeout={}",
+            self.homeserver_url,
+            urlencoding::encode(&filter.to_string()),
+            timeout_ms
+        );
+        if let Some(since) = since {
+            url.push_str(&format!("&since={}", urlencoding::encode(since)));
+        }
+        self.send_request(self.client.get(url), "sync").await
+    }
+
+    /// Download a listing photo, to re-upload it to the homeserver
+    async fn download(&self, url: &str) -> Result<(String, Vec<u8>)> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if let Some(length) = response.content_length() {
+            if length as usize > MAX_IMAGE_BYTES {
+                anyhow::bail!("image is too large ({} bytes, max {})", length, MAX_IMAGE_BYTES);
+            }
+        }
+        let content_type = response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+
+        // The server may lie about (or omit) the length, so also check while streaming
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+                anyhow::bail!("image is larger than {} bytes", MAX_IMAGE_BYTES);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok((content_type, bytes))
+    }
+}
+
+/// Plain text and HTML bodies of a listing message
+fn format_message(record: &ListingRecord, image_uri: Option<&str>) -> Value {
+    let mut details = Vec::new();
+    if let Some(price) = record.price {
+        details.push(format!("💰 {:.0}€", price));
+    }
+    if let Some(surface) = record.surface {
+        details.push(format!("📐 {:.0}m²", surface));
+    }
+    details.push(format!("📍 {}", record.location));
+    let details = details.join(" · ");
+    let posted = format!("🕐 Publiée le {}", record.posted_at.format("%d/%m/%Y à %H:%M"));
+
+    let mut body = format!("{}\n{}\n{}", record.title, details, posted);
+    let mut html = format!(
+        "<h4><a href=\"{}\">{}</a></h4>",
+        escape_html(&record.url),
+        escape_html(&record.title)
+    );
+    if let Some(image_uri) = image_uri {
+        html.push_str(&format!("<img src=\"{}\" alt=\"{}\" height=\"300\"><br>", escape_html(image_uri), escape_html(&record.title)));
+    }
+    html.push_str(&format!("{}<br>{}", escape_html(&details), posted));
+
+    if record.status != ListingStatus::Unchecked {
+        let status = format!("{} {}", lifecycle::emoji(&record.status), lifecycle::label(&record.status));
+        body.push_str(&format!("\n{}", status));
+        html.push_str(&format!("<br><b>{}</b>", status));
+    } else {
+        let hint = format!("👍 {} · 👎 {}", ListingAction::MarkInteresting.label(), ListingAction::MarkNotGood.label());
+        body.push_str(&format!("\n{}", hint));
+        html.push_str(&format!("<br><i>{}</i>", hint));
+    }
+    body.push_str(&format!("\n{}", record.url));
+
+    json!({
+        "msgtype": "m.text",
+        "body": body,
+        "format": "org.matrix.custom.html",
+        "formatted_body": html,
+    })
+}
+
+/// Status change a reaction asks for
+fn reaction_action(key: &str) -> Option<ListingAction> {
+    // Clients may add the emoji variation selector
+    match key.trim_end_matches('\u{fe0f}') {
+        "👍" => Some(ListingAction::MarkInteresting),
+        "👎" => Some(ListingAction::MarkNotGood),
+        _ => None,
+    }
+}
+
+/// Posts the listings to a Matrix room
+pub struct MatrixNotifier {
+    api: MatrixApi,
+    room_id: String,
+    database: Arc<Mutex<Database>>,
+}
+
+impl MatrixNotifier {
+    pub fn new(api: MatrixApi, room_id: String, database: Arc<Mutex<Database>>) -> Self {
+        Self { api, room_id, database }
+    }
+
+    /// Copy the listing photo to the homeserver, as clients only show mxc:// images
+    async fn upload_image(&self, record: &ListingRecord) -> Result<Option<String>> {
+        let Some(image_url) = &record.image_url else {
+            return Ok(None);
+        };
+        let (content_type, data) = self.api.download(image_url).await?;
+        let filename = format!("{}.{}", record.uuid, content_type.rsplit('/').next().unwrap_or("jpg"));
+        Ok(Some(self.api.upload(&filename, &content_type, data).await?))
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    async fn notify_new_listing(&self, record: &ListingRecord, _annotations: &ListingAnnotations) -> Result<()> {
+        if self.database.lock().await.get_matrix_message(&record.uuid)?.is_some() {
+            return Ok(());
+        }
+
+        let image_uri = self.upload_image(record).await.unwrap_or_else(|e| {
+            tracing::warn!("Sending '{}' to Matrix without its photo: {}", record.title, e);
+            None
+        });
+        let content = format_message(record, image_uri.as_deref());
+        let event_id = self.api.send_message(&self.room_id, &format!("listing-{}", record.uuid), &content).await?;
+
+        let message = MatrixMessage { room_id: self.room_id.clone(), event_id, image_uri };
+        self.database.lock().await.set_matrix_message(&record.uuid, &message)?;
+        Ok(())
+    }
+
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()> {
+        let Some(message) = self.database.lock().await.get_matrix_message(&record.uuid)? else {
+            return Ok(());
+        };
+
+        // The edit replaces the whole content, so it points again at the photo uploaded with the listing
+        let new_content = format_message(record, message.image_uri.as_deref());
+        let content = json!({
+            "msgtype": "m.text",
+            "body": format!("* {}", new_content["body"].as_str().unwrap_or_default()),
+            "m.new_content": new_content,
+            "m.relates_to": { "rel_type": "m.replace", "event_id": message.event_id },
+        });
+        self.api.send_message(&message.room_id, &format!("edit-{}", Uuid::new_v4()), &content).await?;
+        Ok(())
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        let (body, html) = match *alert {
+            Alert::PriceDrop { old_price } => {
+                let Some(new_price) = record.price else {
+                    return Ok(());
+                };
+                let percent = (old_price - new_price) / old_price * 100.0;
+                (
+                    format!("📉 Baisse de prix : {}\n{:.0}€ → {:.0}€ (-{:.0}%)", record.title, old_price, new_price, percent),
+                    format!(
+                        "📉 Baisse de prix : <a href=\"{}\">{}</a><br><del>{:.0}€</del> → <b>{:.0}€</b> (-{:.0}%)",
+                        escape_html(&record.url),
+                        escape_html(&record.title),
+                        old_price,
+                        new_price,
+                        percent
+                    ),
+                )
+            }
+        };
+
+        let mut content = json!({
+            "msgtype": "m.text",
+            "body": body,
+            "format": "org.matrix.custom.html",
+            "formatted_body": html,
+        });
+        // Shown as a reply to the listing when it was posted to this room
+        if let Some(message) = self.database.lock().await.get_matrix_message(&record.uuid)? {
+            if message.room_id == self.room_id {
+                content["m.relates_to"] = json!({ "m.in_reply_to": { "event_id": message.event_id } });
+            }
+        }
+        self.api.send_message(&self.room_id, &format!("alert-{}", Uuid::new_v4()), &content).await?;
+        Ok(())
+    }
+}
+
+/// Receives the reactions of the room through /sync
+pub struct MatrixReactions {
+    api: MatrixApi,
+    room_id: String,
+    /// Sync token of the last batch, None until the first sync
+    since: Option<String>,
+    sync_timeout_ms: u64,
+}
+
+impl MatrixReactions {
+    pub fn new(api: MatrixApi, room_id: String) -> Self {
+        Self { api, room_id, since: None, sync_timeout_ms: SYNC_TIMEOUT_MS }
+    }
+
+    /// Only the reactions of the listing room are fetched
+    fn filter(&self) -> Value {
+        json!({
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+            "room": {
+                "rooms": [self.room_id],
+                "timeline": { "types": ["m.reaction"] },
+                "state": { "types": [] },
+                "ephemeral": { "types": [] },
+                "account_data": { "types": [] },
+            },
+        })
+    }
+
+    /// Wait for reactions and apply them
+    /// The reactions made before the first sync are skipped, they were handled by the previous run
+    pub async fn poll(&mut self, database: &Arc<Mutex<Database>>, notifiers: &NotifierRegistry) -> Result<()> {
+        let Some(since) = self.since.clone() else {
+            let response = self.api.sync(None, &self.filter(), 0).await?;
+            self.since = Some(response.next_batch);
+            return Ok(());
+        };
+
+        let mut response = self.api.sync(Some(&since), &self.filter(), self.sync_timeout_ms).await?;
+        let events = response.rooms.join.remove(&self.room_id).map(|room| room.timeline.events).unwrap_or_default();
+        for event in events {
+            if let Err(e) = self.handle_event(&event, database, notifiers).await {
+                tracing::error!("Failed to handle Matrix reaction: {}", e);
+            }
+        }
+        self.since = Some(response.next_batch);
+        Ok(())
+    }
+
+    async fn handle_event(&self, event: &RoomEvent, database: &Arc<Mutex<Database>>, notifiers: &NotifierRegistry) -> Result<()> {
+        let relation = &event.content["m.relates_to"];
+        if event.kind != "m.reaction" || relation["rel_type"] != "m.annotation" {
+            return Ok(());
+        }
+        let (Some(event_id), Some(action)) = (relation["event_id"].as_str(), relation["key"].as_str().and_then(reaction_action)) else {
+            return Ok(());
+        };
+        let Some(uuid) = database.lock().await.get_listing_uuid_by_matrix_event(event_id)? else {
+            return Ok(());
+        };
+
+        tracing::info!("{} reacted \"{}\" on Matrix for listing {}", event.sender, action.label(), uuid);
+        if let Err(e) = notifiers.change_status(database, &uuid, action.target(), None, StatusSource::Matrix).await {
+            let invalid = e.downcast::<InvalidTransition>()?;
+            tracing::info!("Ignoring Matrix reaction of {}: {}", event.sender, invalid);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Listing;
+    use crate::notifiers::mock_server::{MockServer, RecordedRequest};
+    use chrono::{TimeZone, Utc};
+
+    fn listing(image_url: String) -> Listing {
+        Listing {
+            title: "T2 lumineux".to_string(),
+            url: "https://example.com/1".to_string(),
+            image_url: Some(image_url),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+            ..Listing::test("leboncoin_1", 900.0)
+        }
+    }
+
+    /// Answers like a homeserver; the second /sync returns a 👍 on the listing event
+    async fn homeserver_mock() -> MockServer {
+        MockServer::start(|request: &RecordedRequest| {
+            let result = if request.path.starts_with("/photo.jpg") {
+                return (200, "jpeg bytes".to_string());
+            } else if request.path.starts_with("/_matrix/media/v3/upload") {
+                json!({ "content_uri": "mxc://example.org/photo" })
+            } else if request.path.starts_with("/_matrix/client/v3/sync") {
+                if request.path.contains("since=") {
+                    json!({
+                        "next_batch": "s2",
+                        "rooms": { "join": { "!room:example.org": { "timeline": { "events": [
+                            { "type": "m.reaction", "sender": "@alice:example.org", "content": {
+                                "m.relates_to": { "rel_type": "m.annotation", "event_id": "$listing", "key": "👍️" },
+                            } },
+                        ] } } } },
+                    })
+                } else {
+                    json!({ "next_batch": "s1" })
+                }
+            } else if request.path.contains("/send/m.room.message/listing-") {
+                json!({ "event_id": "$listing" })
+            } else {
+                json!({ "event_id": "$other" })
+            };
+            (200, result.to_string())
+        }).await
+    }
+
+    fn api(server: &MockServer) -> MatrixApi {
+        MatrixApi::new(&MatrixConfig {
+            homeserver_url: server.url.clone(),
+            access_token: "secret".to_string(),
+            room_id: "!room:example.org".to_string(),
+        }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_new_listing_is_sent_with_uploaded_image() {
+        let server = homeserver_mock().await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let record = {
+            let db = database.lock().await;
+            let uuid = db.upsert_listing(&listing(format!("{}/photo.jpg", server.url))).unwrap().uuid;
+            db.get_listing_by_uuid(&uuid).unwrap().unwrap()
+        };
+        let notifier = MatrixNotifier::new(api(&server), "!room:example.org".to_string(), database.clone());
+
+        notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+        // Already delivered: not sent twice
+        notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+
+        let upload = &server.requests_to("/_matrix/media/v3/upload")[0];
+        assert_eq!(upload.header("authorization"), Some("Bearer secret"));
+        assert_eq!(upload.body, b"jpeg bytes");
+
+        let sent = server.requests_to("/_matrix/client/v3/rooms/");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].method, "PUT");
+        assert_eq!(sent[0].path, format!("/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/listing-{}", record.uuid));
+        let content = sent[0].json();
+        assert_eq!(content["format"], "org.matrix.custom.html");
+        let html = content["formatted_body"].as_str().unwrap();
+        assert!(html.contains("<a href=\"https://example.com/1\">T2 lumineux</a>"));
+        assert!(html.contains("<img src=\"mxc://example.org/photo\""));
+        assert!(html.contains("💰 900€ · 📐 45m² · 📍 Rennes"));
+
+        let stored = database.lock().await.get_matrix_message(&record.uuid).unwrap();
+        assert_eq!(stored, Some(MatrixMessage {
+            room_id: "!room:example.org".to_string(),
+            event_id: "$listing".to_string(),
+            image_uri: Some("mxc://example.org/photo".to_string()),
+        }));
+
+        // Edits reuse the uploaded photo
+        notifier.update_listing(&record).await.unwrap();
+        assert_eq!(server.requests_to("/_matrix/media/v3/upload").len(), 1);
+        let edit = server.requests_to("/_matrix/client/v3/rooms/").pop().unwrap().json();
+        assert!(edit["m.new_content"]["formatted_body"].as_str().unwrap().contains("<img src=\"mxc://example.org/photo\""));
+    }
+
+    #[tokio::test]
+    async fn test_reaction_updates_status() {
+        let server = homeserver_mock().await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let uuid = database.lock().await.upsert_listing(&listing(format!("{}/photo.jpg", server.url))).unwrap().uuid;
+        let message = MatrixMessage { room_id: "!room:example.org".to_string(), event_id: "$listing".to_string(), image_uri: None };
+        database.lock().await.set_matrix_message(&uuid, &message).unwrap();
+        let mut notifiers = NotifierRegistry::new();
+        notifiers.register(Box::new(MatrixNotifier::new(api(&server), "!room:example.org".to_string(), database.clone())));
+
+        let mut reactions = MatrixReactions::new(api(&server), "!room:example.org".to_string());
+        reactions.sync_timeout_ms = 0;
+        // The first sync only skips the past reactions
+        reactions.poll(&database, &notifiers).await.unwrap();
+        assert_eq!(database.lock().await.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Unchecked);
+        reactions.poll(&database, &notifiers).await.unwrap();
+
+        let db = database.lock().await;
+        assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Interesting);
+        assert_eq!(db.get_status_events(&uuid).unwrap().last().unwrap().source, StatusSource::Matrix);
+        assert_eq!(reactions.since.as_deref(), Some("s2"));
+
+        // The listing message is edited to show its new status
+        let edit = server.requests_to("/_matrix/client/v3/rooms/").pop().unwrap().json();
+        assert_eq!(edit["m.relates_to"]["rel_type"], "m.replace");
+        assert_eq!(edit["m.relates_to"]["event_id"], "$listing");
+        assert!(edit["m.new_content"]["formatted_body"].as_str().unwrap().contains("⭐ Intéressante"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::database::ListingRecord;
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// A notification received by the in-memory notifier
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    NewListing(Uuid),
+    Update(Uuid),
+    Alert(Uuid, Alert),
+}
+
+/// Keeps the notifications it receives, to test the pipeline without any external service
+/// Clones share the same notifications
+#[derive(Clone)]
+pub struct InMemoryNotifier {
+    name: &'static str,
+    notifications: Arc<Mutex<Vec<Notification>>>,
+    /// Makes every new listing fail, as an unreachable service would
+    failing: Arc<AtomicBool>,
+}
+
+impl Default for InMemoryNotifier {
+    fn default() -> Self {
+        Self {
+            name: "In-memory",
+            notifications: Arc::default(),
+            failing: Arc::default(),
+        }
+    }
+}
+
+impl InMemoryNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Named apart from the working ones, as deliveries are recorded by notifier name
+    pub fn failing() -> Self {
+        Self {
+            name: "Failing in-memory",
+            failing: Arc::new(AtomicBool::new(true)),
+            ..Self::default()
+        }
+    }
+
+    /// Brings a failing notifier back up, or takes it down
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    pub fn notifications(&self) -> Vec<Notification> {
+        self.notifications.lock().unwrap().clone()
+    }
+
+    fn push(&self, notification: Notification) {
+        self.notifications.lock().unwrap().push(notification);
+    }
+}
+
+#[async_trait]
+impl Notifier for InMemoryNotifier {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn notify_new_listing(&self, record: &ListingRecord, _annotations: &ListingAnnotations) -> Result<()> {
+        if self.failing.load(Ordering::SeqCst) {
+            anyhow::bail!("notifier unavailable");
+        }
+        self.push(Notification::NewListing(record.uuid));
+        Ok(())
+    }
+
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()> {
+        self.push(Notification::Update(record.uuid));
+        Ok(())
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        self.push(Notification::Alert(record.uuid, *alert));
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+pub mod discord;
+pub mod email;
+pub mod matrix;
+#[cfg(test)]
+pub mod memory;
+#[cfg(test)]
+pub mod mock_server;
+pub mod push;
+#[cfg(test)]
+pub mod smtp_sink;
+pub mod telegram;
+pub mod webhook;
+
+pub use discord::DiscordNotifier;
+pub use email::EmailDigest;
+pub use matrix::MatrixNotifier;
+pub use push::PushNotifier;
+pub use telegram::TelegramNotifier;
+pub use webhook::WebhookNotifier;
+
+/// Escape text for the HTML of Telegram, Matrix and emails
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,206 @@
+use crate::config::{PushConfig, PushService};
+use crate::database::ListingRecord;
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::json;
+
+/// A notification for the phones
+#[derive(Debug, Clone, PartialEq)]
+struct Push {
+    title: String,
+    message: String,
+    priority: u8,
+    /// Opened when the notification is tapped
+    click_url: String,
+    image_url: Option<String>,
+}
+
+fn summary(record: &ListingRecord) -> String {
+    let mut details = Vec::new();
+    if let Some(price) = record.price {
+        details.push(format!("{:.0}€", price));
+    }
+    if let Some(surface) = record.surface {
+        details.push(format!("{:.0}m²", surface));
+    }
+    details.push(record.location.clone());
+    details.join(" · ")
+}
+
+/// Pushes the urgent listings to the phones through ntfy or Gotify, so that someone calls right away
+pub struct PushNotifier {
+    config: PushConfig,
+    client: reqwest::Client,
+}
+
+impl PushNotifier {
+    pub fn new(config: PushConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+        Ok(Self { config, client })
+    }
+
+    async fn send(&self, push: &Push) -> Result<()> {
+        let server_url = self.config.server_url.trim_end_matches('/');
+        let request = match self.config.service {
+            // https://docs.ntfy.sh/publish/#publish-as-json
+            PushService::Ntfy => {
+                let mut body = json!({
+                    "topic": self.config.topic,
+                    "title": push.title,
+                    "message": push.message,
+                    "priority": push.priority.clamp(1, 5),
+                    "tags": ["house"],
+                    "click": push.click_url,
+                    "actions": [{ "action": "view", "label": "Voir l'annonce", "url": push.click_url }],
+                });
+                if let Some(image_url) = &push.image_url {
+                    body["attach"] = json!(image_url);
+                }
+                let request = self.client.post(server_url).json(&body);
+                if self.config.token.is_empty() {
+                    request
+                } else {
+                    request.bearer_auth(&self.config.token)
+                }
+            }
+            // https://gotify.net/docs/msgextras
+            PushService::Gotify => {
+                let body = json!({
+                    "title": push.title,
+                    "message": push.message,
+                    "priority": push.priority.min(10),
+                    "extras": {
+                        "client::notification": { "click": { "url": push.click_url } },
+                    },
+                });
+                self.client.post(format!("{}/message", server_url))
+                    .header("X-Gotify-Key", &self.config.token)
+                    .json(&body)
+            }
+        };
+
+        request.send().await?
+            .error_for_status()
+            .context("push server refused the notification")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for PushNotifier {
+    fn name(&self) -> &str {
+        match self.config.service {
+            PushService::Ntfy => "ntfy",
+            PushService::Gotify => "Gotify",
+        }
+    }
+
+    /// Only the listings matching the urgent filter are pushed, quarantined ones never are
+    async fn notify_new_listing(&self, record: &ListingRecord, annotations: &ListingAnnotations) -> Result<()> {
+        if annotations.quarantined || !self.config.urgent.matches(record) {
+            return Ok(());
+        }
+
+        self.send(&Push {
+            title: format!("🚨 {}", record.title),
+            message: summary(record),
+            priority: self.config.urgent_priority,
+            click_url: record.url.clone(),
+            image_url: record.image_url.clone(),
+        }).await?;
+        Ok(())
+    }
+
+    async fn update_listing(&self, _record: &ListingRecord) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        if self.config.price_drop_priority == 0 || !self.config.urgent.matches(record) {
+            return Ok(());
+        }
+
+        match *alert {
+            Alert::PriceDrop { old_price } => {
+                self.send(&Push {
+                    title: format!("📉 {}", record.title),
+                    message: format!("Avant {:.0}€ · {}", old_price, summary(record)),
+                    priority: self.config.price_drop_priority,
+                    click_url: record.url.clone(),
+                    image_url: record.image_url.clone(),
+                }).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ListingFilter;
+    use crate::database::Database;
+    use crate::models::Listing;
+    use crate::notifiers::mock_server::{MockServer, RecordedRequest};
+
+    fn record(database: &Database, id: &str, price: f64) -> ListingRecord {
+        let listing = Listing::test(id, price);
+        let uuid = database.upsert_listing(&listing).unwrap().uuid;
+        database.get_listing_by_uuid(&uuid).unwrap().unwrap()
+    }
+
+    fn push_config(server: &MockServer, service: PushService) -> PushConfig {
+        PushConfig {
+            service,
+            server_url: server.url.clone(),
+            topic: "colocs".to_string(),
+            token: "tk_secret".to_string(),
+            urgent: ListingFilter { max_price: Some(800.0), ..Default::default() },
+            urgent_priority: 5,
+            price_drop_priority: 4,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ntfy_pushes_urgent_listings_only() {
+        let server = MockServer::start(|_: &RecordedRequest| (200, "{}".to_string())).await;
+        let database = Database::new(":memory:").unwrap();
+        let notifier = PushNotifier::new(push_config(&server, PushService::Ntfy)).unwrap();
+        let (urgent, regular) = (record(&database, "urgent", 750.0), record(&database, "regular", 950.0));
+
+        for record in [&urgent, &regular] {
+            notifier.notify_new_listing(record, &ListingAnnotations::default()).await.unwrap();
+        }
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer tk_secret"));
+        let body = requests[0].json();
+        assert_eq!(body["topic"], "colocs");
+        assert_eq!(body["title"], "🚨 Appartement urgent");
+        assert_eq!(body["message"], "750€ · 45m² · Rennes");
+        assert_eq!(body["priority"], 5);
+        assert_eq!(body["click"], "https://example.com/urgent");
+    }
+
+    #[tokio::test]
+    async fn test_gotify_price_drop() {
+        let server = MockServer::start(|_: &RecordedRequest| (200, "{}".to_string())).await;
+        let database = Database::new(":memory:").unwrap();
+        let notifier = PushNotifier::new(push_config(&server, PushService::Gotify)).unwrap();
+        let record = record(&database, "dropped", 780.0);
+
+        notifier.send_alert(&record, &Alert::PriceDrop { old_price: 850.0 }).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/message");
+        assert_eq!(requests[0].header("x-gotify-key"), Some("tk_secret"));
+        let body = requests[0].json();
+        assert_eq!(body["message"], "Avant 850€ · 780€ · 45m² · Rennes");
+        assert_eq!(body["priority"], 4);
+        assert_eq!(body["extras"]["client::notification"]["click"]["url"], "https://example.com/dropped");
+    }
+}
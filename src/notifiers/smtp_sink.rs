@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// An email received by the sink
+#[derive(Debug, Clone)]
+pub struct ReceivedEmail {
+    pub from: String,
+    pub to: Vec<String>,
+    /// Headers and body, as sent after DATA
+    pub data: String,
+}
+
+/// Minimal SMTP server accepting every email, to test the digest without a mail server
+pub struct SmtpSink {
+    pub host: String,
+    pub port: u16,
+    emails: Arc<Mutex<Vec<ReceivedEmail>>>,
+}
+
+impl SmtpSink {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let emails = Arc::new(Mutex::new(Vec::new()));
+
+        let received = emails.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, received).await;
+                });
+            }
+        });
+
+        Self { host: address.ip().to_string(), port: address.port(), emails }
+    }
+
+    pub fn emails(&self) -> Vec<ReceivedEmail> {
+        self.emails.lock().unwrap().clone()
+    }
+}
+
+/// Strip the angle brackets of "MAIL FROM:<a@b>" and "RCPT TO:<a@b>"
+fn address(argument: &str) -> String {
+    argument.split_once(':')
+        .map(|(_, address)| address.trim().trim_start_matches('<').split('>').next().unwrap_or_default().to_string())
+        .unwrap_or_default()
+}
+
+async fn serve(stream: TcpStream, received: Arc<Mutex<Vec<ReceivedEmail>>>) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    writer.write_all(b"220 sink ESMTP\r\n").await?;
+
+    let mut from = String::new();
+    let mut to = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        let command = line.to_uppercase();
+        let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            b"250-sink\r\n250 8BITMIME\r\n"
+        } else if command.starts_with("MAIL FROM") {
+            from = address(&line);
+            to.clear();
+            b"250 OK\r\n"
+        } else if command.starts_with("RCPT TO") {
+            to.push(address(&line));
+            b"250 OK\r\n"
+        } else if command.starts_with("DATA") {
+            writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await?;
+            let mut data = Vec::new();
+            while let Some(line) = lines.next_line().await? {
+                if line == "." {
+                    break;
+                }
+                // Undo the dot-stuffing of lines starting with a dot
+                data.push(if line.starts_with("..") { line[1..].to_string() } else { line });
+            }
+            received.lock().unwrap().push(ReceivedEmail { from: from.clone(), to: to.clone(), data: data.join("\r\n") });
+            b"250 OK\r\n"
+        } else if command.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").await?;
+            return Ok(());
+        } else {
+            b"250 OK\r\n"
+        };
+        writer.write_all(reply).await?;
+    }
+    Ok(())
+}
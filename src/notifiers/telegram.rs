@@ -0,0 +1,424 @@
+use super::escape_html;
+use crate::bot::{ComponentAction, ListingComponent};
+use crate::config::TelegramConfig;
+use crate::database::{Database, ListingRecord, ListingStatus, StatusSource};
+use crate::lifecycle::{self, InvalidTransition, ListingButton};
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier, NotifierRegistry};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Telegram limits photo captions to 1024 characters
+const MAX_CAPTION_CHARS: usize = 1024;
+/// How long getUpdates waits for a button click before returning
+const POLL_TIMEOUT_SECONDS: u64 = 30;
+
+/// The Telegram message of a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelegramMessage {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub has_photo: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SentMessage {
+    message_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: TelegramUser,
+    data: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUser {
+    first_name: String,
+}
+
+/// Client of the Telegram Bot API
+#[derive(Clone)]
+pub struct TelegramApi {
+    client: reqwest::Client,
+    /// e.g. "https://api.telegram.org/bot<token>"
+    bot_url: String,
+}
+
+impl TelegramApi {
+    pub fn new(config: &TelegramConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(POLL_TIMEOUT_SECONDS + 30))
+            .build()?;
+        Ok(Self {
+            client,
+            bot_url: format!("{}/bot{}", config.api_base_url.trim_end_matches('/'), config.bot_token),
+        })
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: &Value) -> Result<T> {
+        let response: ApiResponse<T> = self.client
+            .post(format!("{}/{}", self.bot_url, method))
+            .json(params)
+            .send()
+            .await
+            .with_context(|| format!("Telegram {} request failed", method))?
+            .json()
+            .await
+            .with_context(|| format!("Invalid Telegram {} response", method))?;
+
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => anyhow::bail!(
+                "Telegram {} failed: {}",
+                method,
+                response.description.unwrap_or_else(|| "no description".to_string())
+            ),
+        }
+    }
+}
+
+/// Caption of a listing message, in Telegram's HTML subset
+fn format_caption(record: &ListingRecord) -> String {
+    let mut details = Vec::new();
+    if let Some(price) = record.price {
+        details.push(format!("💰 {:.0}€", price));
+    }
+    if let Some(surface) = record.surface {
+        details.push(format!("📐 {:.0}m²", surface));
+    }
+    details.push(format!("📍 {}", escape_html(&record.location)));
+
+    let mut caption = format!(
+        "<b>{}</b>\n{}\n🕐 Publiée le {}",
+        escape_html(&record.title),
+        details.join(" · "),
+        record.posted_at.format("%d/%m/%Y à %H:%M")
+    );
+    if record.status != ListingStatus::Unchecked {
+        caption.push_str(&format!("\n{} {}", lifecycle::emoji(&record.status), lifecycle::label(&record.status)));
+    }
+    let link = format!("\n<a href=\"{}\">Voir l'annonce sur {}</a>", escape_html(&record.url), escape_html(&record.source));
+
+    // The link matters more than a long title
+    if caption.chars().count() + link.chars().count() > MAX_CAPTION_CHARS {
+        let kept = MAX_CAPTION_CHARS - link.chars().count() - 3;
+        caption = format!("{}...", caption.chars().take(kept).collect::<String>());
+    }
+    caption + &link
+}
+
+/// Inline keyboard mirroring the "Intéressant" / "Pas bien" buttons of the main Discord channel
+fn keyboard(record: &ListingRecord) -> Value {
+    let buttons: Vec<Value> = lifecycle::main_channel_buttons(&record.status, false)
+        .into_iter()
+        .filter_map(|button| match button {
+            ListingButton::Action(action) => Some(json!({
+                "text": action.label(),
+                "callback_data": ListingComponent::custom_id(ComponentAction::Status(action), record.uuid),
+            })),
+            _ => None,
+        })
+        .collect();
+    json!({ "inline_keyboard": if buttons.is_empty() { vec![] } else { vec![buttons] } })
+}
+
+/// Posts the listings to a Telegram chat, with inline buttons to triage them
+pub struct TelegramNotifier {
+    api: TelegramApi,
+    chat_id: i64,
+    database: Arc<Mutex<Database>>,
+}
+
+impl TelegramNotifier {
+    pub fn new(api: TelegramApi, chat_id: i64, database: Arc<Mutex<Database>>) -> Self {
+        Self { api, chat_id, database }
+    }
+
+    async fn send(&self, record: &ListingRecord) -> Result<TelegramMessage> {
+        let caption = format_caption(record);
+        if let Some(image_url) = &record.image_url {
+            let params = json!({
+                "chat_id": self.chat_id,
+                "photo": image_url,
+                "caption": caption,
+                "parse_mode": "HTML",
+                "reply_markup": keyboard(record),
+            });
+            // Telegram downloads the photo itself and refuses some of them
+            match self.api.call::<SentMessage>("sendPhoto", &params).await {
+                Ok(sent) => return Ok(TelegramMessage { chat_id: self.chat_id, message_id: sent.message_id, has_photo: true }),
+                Err(e) => tracing::warn!("Sending '{}' without its photo: {}", record.title, e),
+            }
+        }
+
+        let params = json!({
+            "chat_id": self.chat_id,
+            "text": caption,
+            "parse_mode": "HTML",
+            "reply_markup": keyboard(record),
+        });
+        let sent: SentMessage = self.api.call("sendMessage", &params).await?;
+        Ok(TelegramMessage { chat_id: self.chat_id, message_id: sent.message_id, has_photo: false })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    async fn notify_new_listing(&self, record: &ListingRecord, _annotations: &ListingAnnotations) -> Result<()> {
+        if self.database.lock().await.get_telegram_message(&record.uuid)?.is_some() {
+            return Ok(());
+        }
+
+        let message = self.send(record).await?;
+        self.database.lock().await.set_telegram_message(&record.uuid, &message)?;
+        Ok(())
+    }
+
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()> {
+        let Some(message) = self.database.lock().await.get_telegram_message(&record.uuid)? else {
+            return Ok(());
+        };
+
+        let (method, text_field) = if message.has_photo {
+            ("editMessageCaption", "caption")
+        } else {
+            ("editMessageText", "text")
+        };
+        let params = json!({
+            "chat_id": message.chat_id,
+            "message_id": message.message_id,
+            text_field: format_caption(record),
+            "parse_mode": "HTML",
+            "reply_markup": keyboard(record),
+        });
+        match self.api.call::<Value>(method, &params).await {
+            // Nothing shown on Telegram changed, e.g. after a vote
+            Err(e) if e.to_string().contains("message is not modified") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        let text = match *alert {
+            Alert::PriceDrop { old_price } => {
+                let Some(new_price) = record.price else {
+                    return Ok(());
+                };
+                format!(
+                    "📉 Baisse de prix : <b>{}</b>\n<s>{:.0}€</s> → <b>{:.0}€</b> (-{:.0}%)",
+                    escape_html(&record.title),
+                    old_price,
+                    new_price,
+                    (old_price - new_price) / old_price * 100.0
+                )
+            }
+        };
+
+        let mut params = json!({ "chat_id": self.chat_id, "text": text, "parse_mode": "HTML" });
+        // Shown as a reply to the listing when it was posted to this chat
+        if let Some(message) = self.database.lock().await.get_telegram_message(&record.uuid)? {
+            if message.chat_id == self.chat_id {
+                params["reply_to_message_id"] = json!(message.message_id);
+            }
+        }
+        self.api.call::<SentMessage>("sendMessage", &params).await?;
+        Ok(())
+    }
+}
+
+/// Receives the clicks on the inline buttons through long polling
+pub struct TelegramCallbacks {
+    api: TelegramApi,
+    /// Identifier of the next update to fetch
+    offset: i64,
+    poll_timeout_seconds: u64,
+}
+
+impl TelegramCallbacks {
+    pub fn new(api: TelegramApi) -> Self {
+        Self { api, offset: 0, poll_timeout_seconds: POLL_TIMEOUT_SECONDS }
+    }
+
+    /// Wait for button clicks and apply them
+    /// Status changes go through the same database method as the Discord buttons, then reach every notifier
+    pub async fn poll(&mut self, database: &Arc<Mutex<Database>>, notifiers: &NotifierRegistry) -> Result<()> {
+        let params = json!({
+            "offset": self.offset,
+            "timeout": self.poll_timeout_seconds,
+            "allowed_updates": ["callback_query"],
+        });
+        let updates: Vec<Update> = self.api.call("getUpdates", &params).await?;
+
+        for update in updates {
+            self.offset = self.offset.max(update.update_id + 1);
+            if let Some(query) = update.callback_query {
+                if let Err(e) = self.handle_callback(&query, database, notifiers).await {
+                    tracing::error!("Failed to handle Telegram button: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_callback(&self, query: &CallbackQuery, database: &Arc<Mutex<Database>>, notifiers: &NotifierRegistry) -> Result<()> {
+        let component = query.data.as_deref().and_then(ListingComponent::parse);
+        let answer = match component {
+            Some(ListingComponent { action: ComponentAction::Status(action), uuid: Some(uuid) }) => {
+                tracing::info!("{} clicked \"{}\" on Telegram for listing {}", query.from.first_name, action.label(), uuid);
+                match notifiers.change_status(database, &uuid, action.target(), None, StatusSource::Telegram).await {
+                    Ok(record) => format!("{} {}", lifecycle::emoji(&record.status), lifecycle::label(&record.status)),
+                    Err(e) => format!("❌ Impossible : {}", e.downcast::<InvalidTransition>()?),
+                }
+            }
+            _ => "❌ Bouton inconnu".to_string(),
+        };
+
+        self.api.call::<bool>("answerCallbackQuery", &json!({ "callback_query_id": query.id, "text": answer })).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Listing;
+    use crate::notifiers::mock_server::{MockServer, RecordedRequest};
+    use chrono::{TimeZone, Utc};
+
+    fn listing() -> Listing {
+        Listing {
+            title: "T2 <lumineux> & calme".to_string(),
+            url: "https://example.com/1".to_string(),
+            image_url: Some("https://example.com/1.jpg".to_string()),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+            ..Listing::test("leboncoin_1", 900.0)
+        }
+    }
+
+    /// Answers like the Bot API; getUpdates returns a click on "Intéressant" once
+    async fn telegram_mock(clicked: String) -> MockServer {
+        let delivered = std::sync::atomic::AtomicBool::new(false);
+        MockServer::start(move |request: &RecordedRequest| {
+            let result = if request.path.ends_with("/getUpdates") {
+                if delivered.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    json!([])
+                } else {
+                    json!([{
+                        "update_id": 7,
+                        "callback_query": { "id": "q1", "from": { "id": 42, "first_name": "Alice" }, "data": clicked },
+                    }])
+                }
+            } else if request.path.ends_with("/answerCallbackQuery") {
+                json!(true)
+            } else {
+                json!({ "message_id": 99 })
+            };
+            (200, json!({ "ok": true, "result": result }).to_string())
+        }).await
+    }
+
+    fn api(server: &MockServer) -> TelegramApi {
+        TelegramApi::new(&TelegramConfig {
+            bot_token: "123:abc".to_string(),
+            chat_id: -100,
+            api_base_url: server.url.clone(),
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_format_caption() {
+        let db = Database::new(":memory:").unwrap();
+        let uuid = db.upsert_listing(&listing()).unwrap().uuid;
+        let record = db.get_listing_by_uuid(&uuid).unwrap().unwrap();
+        assert_eq!(
+            format_caption(&record),
+            "<b>T2 &lt;lumineux&gt; &amp; calme</b>\n💰 900€ · 📐 45m² · 📍 Rennes\n🕐 Publiée le 04/03/2025 à 17:30\n\
+             <a href=\"https://example.com/1\">Voir l'annonce sur Leboncoin</a>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_listing_is_sent_as_photo_with_buttons() {
+        let server = telegram_mock(String::new()).await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let record = {
+            let db = database.lock().await;
+            let uuid = db.upsert_listing(&listing()).unwrap().uuid;
+            db.get_listing_by_uuid(&uuid).unwrap().unwrap()
+        };
+        let notifier = TelegramNotifier::new(api(&server), -100, database.clone());
+
+        notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+        // Already delivered: not sent twice
+        notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/bot123:abc/sendPhoto");
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+        let body = requests[0].json();
+        assert_eq!(body["chat_id"], -100);
+        assert_eq!(body["photo"], "https://example.com/1.jpg");
+        assert_eq!(body["parse_mode"], "HTML");
+        let buttons = &body["reply_markup"]["inline_keyboard"][0];
+        assert_eq!(buttons[0]["text"], "Intéressant");
+        assert_eq!(buttons[0]["callback_data"], format!("listing:interesting:{}", record.uuid));
+        assert_eq!(buttons[1]["text"], "Pas bien");
+
+        let stored = database.lock().await.get_telegram_message(&record.uuid).unwrap();
+        assert_eq!(stored, Some(TelegramMessage { chat_id: -100, message_id: 99, has_photo: true }));
+    }
+
+    #[tokio::test]
+    async fn test_callback_updates_status() {
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let uuid = database.lock().await.upsert_listing(&listing()).unwrap().uuid;
+        let server = telegram_mock(format!("listing:interesting:{}", uuid)).await;
+        let mut notifiers = NotifierRegistry::new();
+        notifiers.register(Box::new(TelegramNotifier::new(api(&server), -100, database.clone())));
+        database.lock().await.set_telegram_message(&uuid, &TelegramMessage { chat_id: -100, message_id: 99, has_photo: true }).unwrap();
+
+        let mut callbacks = TelegramCallbacks::new(api(&server));
+        callbacks.poll_timeout_seconds = 0;
+        callbacks.poll(&database, &notifiers).await.unwrap();
+
+        let db = database.lock().await;
+        assert_eq!(db.get_listing_by_uuid(&uuid).unwrap().unwrap().status, ListingStatus::Interesting);
+        let events = db.get_status_events(&uuid).unwrap();
+        assert_eq!(events.last().unwrap().source, StatusSource::Telegram);
+
+        // The message now shows the status, without the "Intéressant" button
+        let edit = server.requests_to("/bot123:abc/editMessageCaption")[0].json();
+        assert_eq!(edit["message_id"], 99);
+        assert!(edit["caption"].as_str().unwrap().contains("Intéressante"));
+        assert_eq!(edit["reply_markup"]["inline_keyboard"][0][0]["text"], "Pas bien");
+
+        let answer = server.requests_to("/bot123:abc/answerCallbackQuery")[0].json();
+        assert_eq!(answer["callback_query_id"], "q1");
+        assert_eq!(callbacks.offset, 8);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::config::WebhookConfig;
+use crate::database::{Database, ListingRecord};
+use crate::lifecycle;
+use crate::notifier_trait::{Alert, ListingAnnotations, Notifier};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use minijinja::value::Serde;
+use minijinja::Environment;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use uuid::Uuid;
+
+const TEMPLATE_NAME: &str = "body";
+/// Sends the event and the whole listing
+const DEFAULT_TEMPLATE: &str = r#"{"event": {{ event|tojson }}, "listing": {{ listing|tojson }}}"#;
+pub const SIGNATURE_HEADER: &str = "X-Colocfinder-Signature";
+pub const EVENT_HEADER: &str = "X-Colocfinder-Event";
+
+/// An event no attempt managed to deliver
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    pub url: String,
+    /// "new_listing", "listing_updated" or "price_drop"
+    pub event: String,
+    pub uuid: Uuid,
+    /// None when the template could not be rendered
+    pub payload: Option<String>,
+    pub attempts: u32,
+    pub error: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event waiting for the background task
+struct QueuedEvent {
+    event: &'static str,
+    uuid: Uuid,
+    title: String,
+    /// Why the template could not be rendered
+    payload: Result<String, String>,
+}
+
+/// Why the last attempt failed, and whether trying again may help
+struct AttemptError {
+    message: String,
+    retryable: bool,
+}
+
+/// Fields of a listing available to the templates
+fn listing_context(record: &ListingRecord) -> Value {
+    json!({
+        "uuid": record.uuid,
+        "id": record.listing_id,
+        "title": record.title,
+        "price": record.price,
+        "surface": record.surface,
+        "location": record.location,
+        "url": record.url,
+        "image_url": record.image_url,
+        "description": record.description,
+        "posted_at": record.posted_at.to_rfc3339(),
+        "source": record.source,
+        "status": record.status.as_str(),
+        "status_label": lifecycle::label(&record.status),
+    })
+}
+
+/// "sha256=<hex HMAC-SHA256 of the body>", as GitHub signs its webhooks
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POSTs the listings to a URL, with a user-defined body
+/// Updates and alerts are sent by a background task, so that their retries don't hold up the status changes
+pub struct WebhookNotifier {
+    name: String,
+    templates: Environment<'static>,
+    sender: Arc<WebhookSender>,
+    queue: mpsc::UnboundedSender<QueuedEvent>,
+}
+
+/// Sends the events to the URL
+struct WebhookSender {
+    config: WebhookConfig,
+    client: reqwest::Client,
+    database: Arc<Mutex<Database>>,
+}
+
+impl WebhookNotifier {
+    /// Fails when the template has a syntax error
+    /// Starts the background task, so it must be called within the tokio runtime
+    pub fn new(config: WebhookConfig, database: Arc<Mutex<Database>>) -> Result<Self> {
+        let mut templates = Environment::new();
+        let template = config.template.clone().unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+        templates.add_template_owned(TEMPLATE_NAME, template)
+            .with_context(|| format!("Invalid template for webhook {}", config.url))?;
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+        let name = format!("Webhook {}", config.url);
+        let sender = Arc::new(WebhookSender { config, client, database });
+
+        // One event at a time, so that the updates of a listing arrive in order
+        let (queue, mut queued) = mpsc::unbounded_channel::<QueuedEvent>();
+        let background = sender.clone();
+        tokio::spawn(async move {
+            while let Some(queued) = queued.recv().await {
+                if let Err(e) = background.deliver(queued.event, &queued.uuid, &queued.title, queued.payload).await {
+                    tracing::error!("Failed to send {} webhook for '{}': {}", queued.event, queued.title, e);
+                }
+            }
+        });
+
+        Ok(Self { name, templates, sender, queue })
+    }
+
+    /// Render the body of an event, which must be valid JSON
+    fn render(&self, event: &str, record: &ListingRecord, mut context: Value) -> Result<String> {
+        context["event"] = json!(event);
+        context["listing"] = listing_context(record);
+        let body = self.templates.get_template(TEMPLATE_NAME)?.render(Serde(&context))?;
+        serde_json::from_str::<Value>(&body).context("the template did not render valid JSON")?;
+        Ok(body)
+    }
+
+    /// Hand an event over to the background task
+    fn enqueue(&self, event: &'static str, record: &ListingRecord, context: Value) -> Result<()> {
+        let payload = self.render(event, record, context).map_err(|e| format!("{:#}", e));
+        self.queue
+            .send(QueuedEvent { event, uuid: record.uuid, title: record.title.clone(), payload })
+            .map_err(|_| anyhow::anyhow!("the background task of webhook {} stopped", self.sender.config.url))
+    }
+}
+
+impl WebhookSender {
+    async fn attempt(&self, event: &str, body: &str) -> Result<(), AttemptError> {
+        let mut request = self.client
+            .post(&self.config.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event)
+            .body(body.to_string());
+        if let Some(secret) = &self.config.secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, body.as_bytes()));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                Err(AttemptError {
+                    message: format!("HTTP {}", status),
+                    // Other client errors won't go away by sending the same body again
+                    retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+                })
+            }
+            Err(e) => Err(AttemptError { message: e.to_string(), retryable: true }),
+        }
+    }
+
+    /// Send an event, retrying with exponential backoff, and keep it in the dead-letter log if it never got through
+    /// Returns whether it was delivered
+    async fn deliver(&self, event: &str, uuid: &Uuid, title: &str, payload: Result<String, String>) -> Result<bool> {
+        let (payload, attempts, error) = match payload {
+            Ok(body) => {
+                let mut attempts = 0;
+                let mut delay = std::time::Duration::from_millis(self.config.retry_delay_ms);
+                let error = loop {
+                    attempts += 1;
+                    match self.attempt(event, &body).await {
+                        Ok(()) => return Ok(true),
+                        Err(e) if e.retryable && attempts < self.config.max_attempts => {
+                            tracing::warn!("Webhook {} failed ({}), retrying in {:?}", self.config.url, e.message, delay);
+                            tokio::time::sleep(delay).await;
+                            delay *= 2;
+                        }
+                        Err(e) => break e.message,
+                    }
+                };
+                (Some(body), attempts, error)
+            }
+            Err(error) => (None, 0, error),
+        };
+
+        tracing::error!("Giving up on {} webhook for '{}' to {}: {}", event, title, self.config.url, error);
+        self.database.lock().await.add_webhook_dead_letter(&DeadLetter {
+            url: self.config.url.clone(),
+            event: event.to_string(),
+            uuid: *uuid,
+            payload,
+            attempts,
+            error,
+            created_at: Utc::now(),
+        })?;
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fails when the listing ended up in the dead-letter log, so that it is sent again with the next new listings
+    async fn notify_new_listing(&self, record: &ListingRecord, annotations: &ListingAnnotations) -> Result<()> {
+        let payload = self.render("new_listing", record, json!({ "quarantined": annotations.quarantined }))
+            .map_err(|e| format!("{:#}", e));
+        if !self.sender.deliver("new_listing", &record.uuid, &record.title, payload).await? {
+            anyhow::bail!("webhook {} did not accept the listing", self.sender.config.url);
+        }
+        Ok(())
+    }
+
+    async fn update_listing(&self, record: &ListingRecord) -> Result<()> {
+        self.enqueue("listing_updated", record, json!({}))
+    }
+
+    async fn send_alert(&self, record: &ListingRecord, alert: &Alert) -> Result<()> {
+        match *alert {
+            Alert::PriceDrop { old_price } => self.enqueue("price_drop", record, json!({ "old_price": old_price })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Listing;
+    use crate::notifiers::mock_server::{MockServer, RecordedRequest};
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn record(database: &Database) -> ListingRecord {
+        let listing = Listing {
+            title: "T2 \"lumineux\"".to_string(),
+            url: "https://example.com/1".to_string(),
+            posted_at: Utc.with_ymd_and_hms(2025, 3, 4, 17, 30, 0).unwrap(),
+            ..Listing::test("leboncoin_1", 900.0)
+        };
+        let uuid = database.upsert_listing(&listing).unwrap().uuid;
+        database.get_listing_by_uuid(&uuid).unwrap().unwrap()
+    }
+
+    fn webhook_config(server: &MockServer) -> WebhookConfig {
+        WebhookConfig {
+            url: format!("{}/hook", server.url),
+            template: Some(r#"{"event": "{{ event }}", "title": {{ listing.title|tojson }}, "price": {{ listing.price }}}"#.to_string()),
+            secret: Some("secret".to_string()),
+            max_attempts: 3,
+            retry_delay_ms: 1,
+        }
+    }
+
+    /// Wait for the background task to give up on the queued events
+    async fn dead_letters(database: &Mutex<Database>, count: usize) -> Vec<DeadLetter> {
+        for _ in 0..200 {
+            let letters = database.lock().await.get_webhook_dead_letters(10).unwrap();
+            if letters.len() >= count {
+                return letters;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("expected {} dead letter(s)", count);
+    }
+
+    #[test]
+    fn test_signature() {
+        // Known HMAC-SHA256 test vector (RFC 4231, test case 2)
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_listing_is_signed_and_retried() {
+        let calls = AtomicUsize::new(0);
+        // Fails once, then accepts
+        let server = MockServer::start(move |_: &RecordedRequest| {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 { (503, String::new()) } else { (200, String::new()) }
+        }).await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let record = record(&*database.lock().await);
+        let notifier = WebhookNotifier::new(webhook_config(&server), database.clone()).unwrap();
+
+        notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let body = r#"{"event": "new_listing", "title": "T2 \"lumineux\"", "price": 900.0}"#;
+        assert_eq!(requests[1].path, "/hook");
+        assert_eq!(String::from_utf8_lossy(&requests[1].body), body);
+        assert_eq!(requests[1].header("x-colocfinder-event"), Some("new_listing"));
+        assert_eq!(requests[1].header("x-colocfinder-signature"), Some(signature("secret", body.as_bytes()).as_str()));
+        assert!(database.lock().await.get_webhook_dead_letters(10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undeliverable_event_goes_to_dead_letters() {
+        let server = MockServer::start(|_: &RecordedRequest| (500, String::new())).await;
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let record = record(&*database.lock().await);
+        let notifier = WebhookNotifier::new(webhook_config(&server), database.clone()).unwrap();
+
+        notifier.send_alert(&record, &Alert::PriceDrop { old_price: 950.0 }).await.unwrap();
+
+        let letters = dead_letters(&database, 1).await;
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].event, "price_drop");
+        assert_eq!(letters[0].uuid, record.uuid);
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(letters[0].error, "HTTP 500 Internal Server Error");
+        assert!(letters[0].payload.as_deref().unwrap().contains("price_drop"));
+
+        // A new listing is not marked as delivered, to be sent again later
+        assert!(notifier.notify_new_listing(&record, &ListingAnnotations::default()).await.is_err());
+        assert_eq!(database.lock().await.get_webhook_dead_letters(10).unwrap()[0].event, "new_listing");
+    }
+
+    #[tokio::test]
+    async fn test_default_template_sends_the_listing() {
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let record = record(&*database.lock().await);
+        let config = WebhookConfig {
+            url: "http://localhost/hook".to_string(),
+            template: None,
+            secret: None,
+            max_attempts: 1,
+            retry_delay_ms: 0,
+        };
+        let notifier = WebhookNotifier::new(config, database).unwrap();
+
+        let body = notifier.render("new_listing", &record, json!({})).unwrap();
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["event"], "new_listing");
+        assert_eq!(body["listing"]["title"], "T2 \"lumineux\"");
+        assert_eq!(body["listing"]["status"], "unchecked");
+        assert_eq!(body["listing"]["uuid"], record.uuid.to_string());
+    }
+}
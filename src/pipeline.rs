@@ -0,0 +1,312 @@
+use crate::config::Config;
+use crate::database::{Database, ListingStatus};
+use crate::duplicates;
+use crate::image_hash::{self, ImageHasher, RepostSummary};
+use crate::models::Listing;
+use crate::notifier_trait::{Alert, ListingAnnotations, NotifierRegistry};
+use crate::scam;
+use crate::scraper_trait::ScraperRegistry;
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Find earlier postings of the same photo for a listing about to be posted
+fn find_reposts(db: &Database, listing: &Listing) -> Option<RepostSummary> {
+    let image_url = listing.image_url.as_ref()?;
+    let hash = db.get_image_hash(image_url).ok().flatten().flatten()?;
+
+    match db.find_similar_images(hash, image_hash::SIMILAR_IMAGE_MAX_DISTANCE, &listing.id) {
+        Ok(matches) => RepostSummary::from_matches(&matches),
+        Err(e) => {
+            tracing::error!("Failed to look up reposts for '{}': {}", listing.title, e);
+            None
+        }
+    }
+}
+
+/// One scraping cycle: scrape every website, store the listings, then hand the new listings
+/// and the price drops to the notifiers
+pub async fn run_scrape_cycle(
+    scrapers: &ScraperRegistry,
+    database: &Arc<Mutex<Database>>,
+    notifiers: &NotifierRegistry,
+    image_hasher: Option<&ImageHasher>,
+    config: &Config,
+) -> Result<()> {
+    let listings = scrapers.scrape_all(&config.cities).await?;
+    tracing::info!("Found {} total listings", listings.len());
+
+    // Insert listings into database
+    let db = database.lock().await;
+    let mut price_drops = Vec::new();
+
+    for listing in listings {
+        if !listing.has_sufficient_info() {
+            tracing::debug!("Skipping listing '{}' - insufficient information", listing.title);
+            continue;
+        }
+
+        match db.upsert_listing(&listing) {
+            Ok(outcome) => {
+                // Listings not announced yet are announced with their current price below
+                if let Some((old_price, _)) = outcome.price_drop(&listing).filter(|_| db.is_notified(&outcome.uuid).unwrap_or(false)) {
+                    // Duplicates share the original's message, which shows the original's price
+                    if let (Ok(Some(record)), Ok(None)) = (db.get_listing_by_uuid(&outcome.uuid), db.get_duplicate_group(&outcome.uuid)) {
+                        price_drops.push((record, old_price));
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to insert listing into database: {}", e);
+            }
+        }
+    }
+
+    drop(db); // Release lock before notifying
+
+    for (record, old_price) in price_drops {
+        tracing::info!("Price drop for '{}': {:.0}€ -> {:?}", record.title, old_price, record.price);
+        notifiers.update_listing(&record).await;
+
+        if config.price_drop_notifications && record.status != ListingStatus::NotGood {
+            notifiers.send_alert(&record, &Alert::PriceDrop { old_price }).await;
+        }
+    }
+
+    let new_listings = database.lock().await.get_new_listings(config.max_listing_age_minutes)?;
+    if new_listings.is_empty() {
+        tracing::info!("No new listings to post");
+    } else {
+        tracing::info!("Found {} new listings to post!", new_listings.len());
+
+        // Hash the photos first, downloads must not hold the database lock
+        if let Some(hasher) = image_hasher {
+            for (_, listing) in &new_listings {
+                if let Some(image_url) = &listing.image_url {
+                    hasher.hash_for_url(database, image_url).await;
+                }
+            }
+        }
+        let db = database.lock().await;
+
+        // Run duplicate detection and the scam heuristics while we hold the database
+        let scam_config = &config.scam_detection;
+        let duplicate_config = &config.duplicate_detection;
+        let mut to_post = Vec::new();
+        // Listings of this batch going to be announced, so that their copies in the same batch aren't
+        let mut pending = Vec::new();
+        let mut originals = Vec::new();
+
+        for (uuid, listing) in new_listings {
+            if duplicate_config.enabled {
+                match duplicates::link_to_original(&db, uuid, &listing, duplicate_config, &pending) {
+                    Ok(Some(link)) => {
+                        tracing::info!("Listing {} now lists {} other source(s)", link.group_uuid, link.sources.len());
+                        // The duplicate is shown on the original's messages instead
+                        if let Err(e) = db.mark_notified(&uuid) {
+                            tracing::error!("Failed to mark duplicate '{}' as notified: {}", listing.title, e);
+                        }
+                        // A pending original is announced with its sources already
+                        if !pending.iter().any(|(pending_uuid, _)| *pending_uuid == link.group_uuid) {
+                            if let Ok(Some(original)) = db.get_listing_by_uuid(&link.group_uuid) {
+                                originals.push(original);
+                            }
+                        }
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to check duplicates for '{}': {}", listing.title, e),
+                }
+            }
+
+            let report = scam::assess_listing(&listing, &db, scam_config)
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to assess listing '{}': {}", listing.title, e);
+                    scam::ScamReport::default()
+                });
+            let reposts = find_reposts(&db, &listing);
+            let annotations = ListingAnnotations {
+                quarantined: report.should_quarantine(scam_config),
+                scam_warning: report.is_suspicious(scam_config).then_some(report),
+                reposts,
+            };
+            match db.get_listing_by_uuid(&uuid) {
+                Ok(Some(record)) => {
+                    pending.push((uuid, listing));
+                    to_post.push((record, annotations));
+                }
+                Ok(None) => tracing::warn!("Listing '{}' is not in the database, skipping", listing.title),
+                Err(e) => tracing::error!("Failed to load listing '{}': {}", listing.title, e),
+            }
+        }
+
+        drop(db); // Release lock before sending messages
+
+        // Add the new sources to the messages of the original listings
+        for original in originals {
+            notifiers.update_listing(&original).await;
+        }
+
+        for (record, annotations) in to_post {
+            // Listings some notifier failed to receive are announced again next cycle, on that notifier only
+            if notifiers.notify_new_listing(database, &record, &annotations).await.is_ok() {
+                tracing::info!("Sent notification for: {}", record.title);
+                if let Err(e) = database.lock().await.mark_notified(&record.uuid) {
+                    tracing::error!("Failed to mark '{}' as notified: {}", record.title, e);
+                }
+            }
+
+            // Small delay between messages
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        }
+    }
+
+    // Clean up old unposted listings from database
+    database.lock().await.cleanup_old_listings(config.max_listing_age_minutes)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifiers::memory::{InMemoryNotifier, Notification};
+    use crate::scraper_trait::Scraper;
+    use async_trait::async_trait;
+
+    /// Returns the listings it is given, as a website would
+    /// Clones share the same listings, so that a test can change them between cycles
+    #[derive(Clone, Default)]
+    struct StubScraper {
+        listings: Arc<std::sync::Mutex<Vec<Listing>>>,
+    }
+
+    impl StubScraper {
+        fn set_listings(&self, listings: Vec<Listing>) {
+            *self.listings.lock().unwrap() = listings;
+        }
+    }
+
+    #[async_trait]
+    impl Scraper for StubScraper {
+        fn name(&self) -> &str {
+            "Stub"
+        }
+
+        async fn scrape(&self, _cities: &[String]) -> Result<Vec<Listing>> {
+            Ok(self.listings.lock().unwrap().clone())
+        }
+    }
+
+    fn listing(id: &str, price: f64) -> Listing {
+        Listing {
+            source: "Stub".to_string(),
+            ..Listing::test(id, price)
+        }
+    }
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            "discord_token: test\nchannel_id: 0\ninteresting_channel_id: 0\ncheck_interval_seconds: 300\ncities: [Rennes]\n"
+        ).unwrap()
+    }
+
+    fn registries(scraper: &StubScraper, notifiers: &[&InMemoryNotifier]) -> (ScraperRegistry, NotifierRegistry) {
+        let mut scraper_registry = ScraperRegistry::new();
+        scraper_registry.register(Box::new(scraper.clone()));
+        let mut notifier_registry = NotifierRegistry::new();
+        for notifier in notifiers {
+            notifier_registry.register(Box::new((*notifier).clone()));
+        }
+        (scraper_registry, notifier_registry)
+    }
+
+    #[tokio::test]
+    async fn test_scrape_store_notify() {
+        let scraper = StubScraper::default();
+        scraper.set_listings(vec![listing("stub_1", 900.0), listing("stub_2", 750.0)]);
+        let notifier = InMemoryNotifier::new();
+        let (scrapers, notifiers) = registries(&scraper, &[&notifier]);
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let config = config();
+
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config).await.unwrap();
+        let (first, second) = {
+            let db = database.lock().await;
+            (db.get_listing_uuid_by_id("stub_1").unwrap().unwrap(), db.get_listing_uuid_by_id("stub_2").unwrap().unwrap())
+        };
+        let notifications = notifier.notifications();
+        assert_eq!(notifications.len(), 2);
+        assert!(notifications.contains(&Notification::NewListing(first)));
+        assert!(notifications.contains(&Notification::NewListing(second)));
+
+        // Next cycle: nothing new, but the first listing got cheaper
+        scraper.set_listings(vec![listing("stub_1", 850.0), listing("stub_2", 750.0)]);
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config).await.unwrap();
+        assert_eq!(notifier.notifications()[2..], [
+            Notification::Update(first),
+            Notification::Alert(first, Alert::PriceDrop { old_price: 900.0 }),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_notification_is_retried() {
+        let scraper = StubScraper::default();
+        scraper.set_listings(vec![listing("stub_1", 900.0)]);
+        let notifier = InMemoryNotifier::new();
+        let failing = InMemoryNotifier::failing();
+        let (scrapers, notifiers) = registries(&scraper, &[&notifier, &failing]);
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+        let config = config();
+
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config).await.unwrap();
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config).await.unwrap();
+        let uuid = database.lock().await.get_listing_uuid_by_id("stub_1").unwrap().unwrap();
+        assert!(!database.lock().await.is_notified(&uuid).unwrap());
+
+        // Only the notifier that failed gets the listing again
+        failing.set_failing(false);
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config).await.unwrap();
+        assert_eq!(notifier.notifications(), vec![Notification::NewListing(uuid)]);
+        assert_eq!(failing.notifications(), vec![Notification::NewListing(uuid)]);
+        assert!(database.lock().await.is_notified(&uuid).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_partly_delivered_listing_survives_cleanup() {
+        let scraper = StubScraper::default();
+        scraper.set_listings(vec![listing("stub_1", 900.0)]);
+        let (scrapers, notifiers) = registries(&scraper, &[&InMemoryNotifier::new(), &InMemoryNotifier::failing()]);
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config()).await.unwrap();
+
+        let db = database.lock().await;
+        let uuid = db.get_listing_uuid_by_id("stub_1").unwrap().unwrap();
+        assert!(!db.is_notified(&uuid).unwrap());
+        assert_eq!(db.cleanup_old_listings(0).unwrap(), 0);
+        assert!(db.get_listing_by_uuid(&uuid).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_copies_found_in_the_same_cycle_are_announced_once() {
+        let scraper = StubScraper::default();
+        let mut copy = listing("stub_2", 900.0);
+        copy.title = "Appartement stub_1".to_string();
+        scraper.set_listings(vec![listing("stub_1", 900.0), copy]);
+        let notifier = InMemoryNotifier::new();
+        let (scrapers, notifiers) = registries(&scraper, &[&notifier]);
+        let database = Arc::new(Mutex::new(Database::new(":memory:").unwrap()));
+
+        run_scrape_cycle(&scrapers, &database, &notifiers, None, &config()).await.unwrap();
+
+        let db = database.lock().await;
+        let uuids = [db.get_listing_uuid_by_id("stub_1").unwrap().unwrap(), db.get_listing_uuid_by_id("stub_2").unwrap().unwrap()];
+        let [Notification::NewListing(original)] = notifier.notifications()[..] else {
+            panic!("expected a single announcement, got {:?}", notifier.notifications());
+        };
+        let copy = *uuids.iter().find(|uuid| **uuid != original).unwrap();
+        assert_eq!(db.get_duplicate_group(&copy).unwrap(), Some(original));
+        assert!(db.is_notified(&copy).unwrap());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::config::ScamDetectionConfig;
+use crate::database::Database;
+use crate::image_hash::SIMILAR_IMAGE_MAX_DISTANCE;
+use crate::models::Listing;
+use anyhow::Result;
+
+/// Phrases commonly found in rental scams (matched case-insensitively)
+const SCAM_PHRASES: &[&str] = &[
+    "western union",
+    "moneygram",
+    "mandat cash",
+    "transcash",
+    "coupon pcs",
+    "je suis à l'étranger",
+    "je suis actuellement à l'étranger",
+    "je vis à l'étranger",
+    "muté à l'étranger",
+    "hors de france",
+    "envoi des clés",
+    "envoyer les clés",
+    "clés par la poste",
+    "visite impossible",
+    "pas de visite",
+    "paiement par airbnb",
+    "via airbnb",
+    "contactez-moi par mail",
+    "contactez moi par email",
+];
+
+/// Score added for each scam phrase found in the title or description
+const PHRASE_SCORE: u32 = 30;
+/// Score added when the price per m² is far below the city median
+const PRICE_OUTLIER_SCORE: u32 = 40;
+/// Score added when the photo is already used by another listing
+const REUSED_IMAGE_SCORE: u32 = 30;
+
+/// Result of the suspicious listing heuristics
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScamReport {
+    pub score: u32,
+    pub reasons: Vec<String>,
+}
+
+impl ScamReport {
+    fn flag(&mut self, score: u32, reason: String) {
+        self.score += score;
+        self.reasons.push(reason);
+    }
+
+    /// Whether the listing should carry a warning in its embed
+    pub fn is_suspicious(&self, config: &ScamDetectionConfig) -> bool {
+        self.score > 0 && self.score >= config.warning_threshold
+    }
+
+    /// Whether the listing should be routed to the quarantine channel
+    pub fn should_quarantine(&self, config: &ScamDetectionConfig) -> bool {
+        config.quarantine_channel_id != 0 && self.score >= config.quarantine_threshold
+    }
+
+    /// Format the reasons for the embed warning field
+    pub fn format_reasons(&self) -> String {
+        self.reasons.iter()
+            .map(|reason| format!("• {}", reason))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Run every heuristic on a listing
+///
+/// Leboncoin search results do not expose the seller account, so account age
+/// cannot be checked yet. Scrapers that provide it can add a heuristic here.
+pub fn assess_listing(listing: &Listing, database: &Database, config: &ScamDetectionConfig) -> Result<ScamReport> {
+    let mut report = ScamReport::default();
+
+    check_phrases(listing, config, &mut report);
+    check_price_outlier(listing, database, config, &mut report)?;
+    check_reused_image(listing, database, &mut report)?;
+
+    if report.score > 0 {
+        tracing::info!("Listing '{}' has scam score {}: {:?}", listing.title, report.score, report.reasons);
+    }
+
+    Ok(report)
+}
+
+fn check_phrases(listing: &Listing, config: &ScamDetectionConfig, report: &mut ScamReport) {
+    let text = format!(
+        "{} {}",
+        listing.title,
+        listing.description.as_deref().unwrap_or("")
+    ).to_lowercase();
+
+    let extra_phrases = config.extra_phrases.iter().map(|p| p.to_lowercase());
+    let phrases = SCAM_PHRASES.iter().map(|p| p.to_string()).chain(extra_phrases);
+
+    for phrase in phrases {
+        if !phrase.is_empty() && text.contains(&phrase) {
+            report.flag(PHRASE_SCORE, format!("Phrase suspecte : « {} »", phrase));
+        }
+    }
+}
+
+fn check_price_outlier(listing: &Listing, database: &Database, config: &ScamDetectionConfig, report: &mut ScamReport) -> Result<()> {
+    let (Some(price), Some(surface)) = (listing.price, listing.surface) else {
+        return Ok(());
+    };
+    if price <= 0.0 || surface <= 0.0 {
+        return Ok(());
+    }
+
+    let history = database.get_price_per_m2_for_location(&listing.location, &listing.id)?;
+    if history.len() < config.min_price_samples {
+        tracing::trace!("Not enough price history for {} ({} samples)", listing.location, history.len());
+        return Ok(());
+    }
+
+    let median = median(history);
+    let price_per_m2 = price / surface;
+    if price_per_m2 < median * config.price_outlier_ratio {
+        report.flag(
+            PRICE_OUTLIER_SCORE,
+            format!("Prix anormalement bas : {:.1}€/m² (médiane {} : {:.1}€/m²)", price_per_m2, listing.location, median),
+        );
+    }
+
+    Ok(())
+}
+
+fn check_reused_image(listing: &Listing, database: &Database, report: &mut ScamReport) -> Result<()> {
+    let Some(image_url) = &listing.image_url else {
+        return Ok(());
+    };
+
+    // Prefer the perceptual hash, which also catches re-uploads of the same photo
+    let reuses = match database.get_image_hash(image_url)?.flatten() {
+        Some(hash) => database.find_similar_images(hash, SIMILAR_IMAGE_MAX_DISTANCE, &listing.id)?.len(),
+        None => database.count_other_listings_with_image(image_url, &listing.id)?,
+    };
+    if reuses > 0 {
+        report.flag(
+            REUSED_IMAGE_SCORE,
+            format!("Photo déjà utilisée par {} autre(s) annonce(s)", reuses),
+        );
+    }
+
+    Ok(())
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(id: &str, price: f64, surface: f64) -> Listing {
+        Listing {
+            title: format!("Appartement T2 {}m²", surface),
+            surface: Some(surface),
+            image_url: Some(format!("https://img.example.com/{}.jpg", id)),
+            ..Listing::test(id, price)
+        }
+    }
+
+    fn database_with_history() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        for i in 0..6 {
+            db.upsert_listing(&listing(&format!("history_{}", i), 800.0, 40.0)).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_clean_listing_has_zero_score() {
+        let db = database_with_history();
+        let report = assess_listing(&listing("new", 780.0, 40.0), &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report, ScamReport::default());
+    }
+
+    #[test]
+    fn test_scam_phrase_in_description() {
+        let db = database_with_history();
+        let mut candidate = listing("new", 780.0, 40.0);
+        candidate.description = Some("Je suis à l'étranger, paiement par Western Union".to_string());
+
+        let report = assess_listing(&candidate, &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report.score, 2 * PHRASE_SCORE);
+        assert_eq!(report.reasons.len(), 2);
+    }
+
+    #[test]
+    fn test_extra_phrases_from_config() {
+        let db = Database::new(":memory:").unwrap();
+        let config = ScamDetectionConfig {
+            extra_phrases: vec!["Caution en Bitcoin".to_string()],
+            ..Default::default()
+        };
+        let mut candidate = listing("new", 780.0, 40.0);
+        candidate.title = "Studio, caution en bitcoin".to_string();
+
+        let report = assess_listing(&candidate, &db, &config).unwrap();
+        assert_eq!(report.score, PHRASE_SCORE);
+    }
+
+    #[test]
+    fn test_price_outlier_against_city_median() {
+        let db = database_with_history();
+        let report = assess_listing(&listing("new", 300.0, 40.0), &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report.score, PRICE_OUTLIER_SCORE);
+    }
+
+    #[test]
+    fn test_price_outlier_needs_enough_samples() {
+        let db = Database::new(":memory:").unwrap();
+        db.upsert_listing(&listing("history", 800.0, 40.0)).unwrap();
+
+        let report = assess_listing(&listing("new", 100.0, 40.0), &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report.score, 0);
+    }
+
+    #[test]
+    fn test_reused_image_across_listings() {
+        let db = database_with_history();
+        let mut candidate = listing("new", 780.0, 40.0);
+        candidate.image_url = Some("https://img.example.com/history_0.jpg".to_string());
+
+        let report = assess_listing(&candidate, &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report.score, REUSED_IMAGE_SCORE);
+    }
+
+    #[test]
+    fn test_reused_image_by_perceptual_hash() {
+        let db = database_with_history();
+        db.set_image_hash("https://img.example.com/history_0.jpg", Some(0xFF00)).unwrap();
+        db.set_image_hash("https://img.example.com/new.jpg", Some(0xFF01)).unwrap();
+
+        let report = assess_listing(&listing("new", 780.0, 40.0), &db, &ScamDetectionConfig::default()).unwrap();
+        assert_eq!(report.score, REUSED_IMAGE_SCORE);
+    }
+
+    #[test]
+    fn test_quarantine_requires_channel() {
+        let report = ScamReport { score: 100, reasons: vec![] };
+        let mut config = ScamDetectionConfig::default();
+        assert!(report.is_suspicious(&config));
+        assert!(!report.should_quarantine(&config));
+
+        config.quarantine_channel_id = 42;
+        assert!(report.should_quarantine(&config));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(vec![4.0, 1.0, 2.0, 3.0]), 2.5);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::models::Listing;
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 /// Trait that all scrapers must implement
 #[async_trait]
@@ -17,15 +20,29 @@ pub trait Scraper: Send + Sync {
     }
 }
 
+/// How the last runs of a scraper went
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScraperHealth {
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Listings found by the last successful run
+    pub last_listing_count: usize,
+    pub consecutive_failures: u32,
+}
+
 /// Registry to manage all scrapers
 pub struct ScraperRegistry {
     scrapers: Vec<Box<dyn Scraper>>,
+    /// Keyed by scraper name
+    health: Mutex<HashMap<String, ScraperHealth>>,
 }
 
 impl ScraperRegistry {
     pub fn new() -> Self {
         Self {
             scrapers: Vec::new(),
+            health: Mutex::new(HashMap::new()),
         }
     }
 
@@ -43,13 +60,25 @@ impl ScraperRegistry {
 
             tracing::info!("Scraping from {}", scraper.name());
 
-            match scraper.scrape(cities).await {
+            let result = scraper.scrape(cities).await;
+            let now = Utc::now();
+            let mut health = self.health.lock().unwrap();
+            let health = health.entry(scraper.name().to_string()).or_default();
+            health.last_run = Some(now);
+
+            match result {
                 Ok(mut listings) => {
                     tracing::info!("Found {} listings from {}", listings.len(), scraper.name());
+                    health.last_success = Some(now);
+                    health.last_error = None;
+                    health.last_listing_count = listings.len();
+                    health.consecutive_failures = 0;
                     all_listings.append(&mut listings);
                 }
                 Err(e) => {
                     tracing::error!("Failed to scrape from {}: {}", scraper.name(), e);
+                    health.last_error = Some(e.to_string());
+                    health.consecutive_failures += 1;
                 }
             }
         }
@@ -57,6 +86,14 @@ impl ScraperRegistry {
         Ok(all_listings)
     }
 
+    /// Name, whether it is enabled, and health of every scraper
+    pub fn health(&self) -> Vec<(String, bool, ScraperHealth)> {
+        let health = self.health.lock().unwrap();
+        self.scrapers.iter()
+            .map(|s| (s.name().to_string(), s.is_enabled(), health.get(s.name()).cloned().unwrap_or_default()))
+            .collect()
+    }
+
     pub fn list_scrapers(&self) -> Vec<String> {
         self.scrapers.iter()
             .map(|s| s.name().to_string())
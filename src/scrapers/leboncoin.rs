@@ -1,10 +1,11 @@
 use crate::http_client;
-use crate::models::Listing;
+use crate::models::{Coordinates, Listing};
 use crate::scraper_trait::Scraper;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc, Duration, NaiveDateTime, TimeZone};
 use scraper::{Html, Selector};
+use std::collections::HashMap;
 use std::sync::Arc;
 use reqwest::cookie::Jar;
 
@@ -17,6 +18,8 @@ pub struct LeboncoinScraper {
 }
 
 impl LeboncoinScraper {
+    /// Scraper with the defaults, for the tests against the live website
+    #[cfg(test)]
     pub fn new() -> Self {
         Self::with_config(
             "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
@@ -40,11 +43,6 @@ impl LeboncoinScraper {
         }
     }
 
-    /// Get the cookie jar for inspection or manual cookie management
-    pub fn cookie_jar(&self) -> &Arc<Jar> {
-        &self.cookie_jar
-    }
-
     /// Load cookies from a JSON file exported from browser
     /// Expected format: Array of cookies with "name", "value", "domain" fields
     /// You can export cookies using browser extensions like "EditThisCookie"
@@ -137,7 +135,7 @@ impl LeboncoinScraper {
 
     /// Extract number of rooms from title (e.g., "T2", "T3", "2 pièces", "3 chambres", "F2")
     /// Returns the number of rooms if found
-    fn parse_rooms(title: &str) -> Option<u32> {
+    pub(crate) fn parse_rooms(title: &str) -> Option<u32> {
         if title.is_empty() {
             return None;
         }
@@ -198,15 +196,46 @@ impl LeboncoinScraper {
 
         full_url
             .split('/')
-            .last()
+            .next_back()
             .and_then(|s| s.split('.').next())
             .unwrap_or(fallback)
             .to_string()
     }
 
+    /// Coordinates of the ads of a search page, by ad id
+    /// They are only in the JSON state embedded by Next.js: `{"list_id": 123, "location": {"lat": .., "lng": ..}}`
+    fn extract_coordinates(document: &Html) -> HashMap<String, Coordinates> {
+        fn collect(value: &serde_json::Value, coordinates: &mut HashMap<String, Coordinates>) {
+            match value {
+                serde_json::Value::Object(object) => {
+                    let id = object.get("list_id").map(|id| id.to_string().trim_matches('"').to_string());
+                    let location = object.get("location");
+                    let latitude = location.and_then(|location| location.get("lat")).and_then(|lat| lat.as_f64());
+                    let longitude = location.and_then(|location| location.get("lng")).and_then(|lng| lng.as_f64());
+                    if let (Some(id), Some(latitude), Some(longitude)) = (id, latitude, longitude) {
+                        coordinates.insert(id, Coordinates { latitude, longitude });
+                    }
+                    object.values().for_each(|value| collect(value, coordinates));
+                }
+                serde_json::Value::Array(values) => values.iter().for_each(|value| collect(value, coordinates)),
+                _ => {}
+            }
+        }
+
+        let mut coordinates = HashMap::new();
+        let selector = Selector::parse("script#__NEXT_DATA__").unwrap();
+        if let Some(script) = document.select(&selector).next() {
+            match serde_json::from_str::<serde_json::Value>(&script.text().collect::<String>()) {
+                Ok(state) => collect(&state, &mut coordinates),
+                Err(e) => tracing::debug!("Could not parse the page state for coordinates: {}", e),
+            }
+        }
+        coordinates
+    }
+
     /// Extract title from an HTML element
     fn extract_title(element: &scraper::ElementRef) -> String {
-        let title_selectors = vec![
+        let title_selectors = [
             "p[data-qa-id='aditem_title']",
             "div[data-qa-id='aditem_title']",
             "span[data-qa-id='aditem_title']",
@@ -229,7 +258,7 @@ impl LeboncoinScraper {
 
     /// Extract price text from an HTML element
     fn extract_price_text(element: &scraper::ElementRef) -> String {
-        let price_selectors = vec![
+        let price_selectors = [
             "p[data-test-id='price']",
             "div[data-test-id='price']",
             "span[data-test-id='price']",
@@ -256,7 +285,7 @@ impl LeboncoinScraper {
 
     /// Extract image URL from an HTML element
     fn extract_image_url(element: &scraper::ElementRef) -> Option<String> {
-        let image_selectors = vec![
+        let image_selectors = [
             "img[src*='leboncoin.fr']",
             "img[data-test-id='adcard-image']",
             "img",
@@ -274,7 +303,7 @@ impl LeboncoinScraper {
 
     /// Extract relative URL from an HTML element
     fn extract_relative_url(element: &scraper::ElementRef) -> String {
-        let link_selectors = vec!["a"];
+        let link_selectors = ["a"];
 
         link_selectors.iter()
             .find_map(|sel_str| {
@@ -291,7 +320,7 @@ impl LeboncoinScraper {
     /// The title contains the full datetime like "Aujourd'hui, 14:30" or "13 février 2026, 10:15"
     fn extract_posted_at(element: &scraper::ElementRef) -> Option<DateTime<Utc>> {
         // Look for p tags with time information
-        let time_selectors = vec![
+        let time_selectors = [
             "p[title]",
             "time[datetime]",
         ];
@@ -480,7 +509,7 @@ impl Scraper for LeboncoinScraper {
 
                     // Leboncoin uses <article> tags for each listing
                     // Try multiple possible selectors
-                    let possible_selectors = vec![
+                    let possible_selectors = [
                         "article[data-qa-id='aditem']",
                         "article",
                         "div[data-qa-id='aditem']",
@@ -503,6 +532,8 @@ impl Scraper for LeboncoinScraper {
                         }
                     }
 
+                    let coordinates = Self::extract_coordinates(&document);
+
                     let mut listings_for_city = 0;
                     let mut filtered_by_age = 0;
                     let mut filtered_by_rooms = 0;
@@ -589,6 +620,7 @@ impl Scraper for LeboncoinScraper {
                                     description: None,
                                     posted_at,
                                     source: "Leboncoin".to_string(),
+                                    coordinates: coordinates.get(&id).copied(),
                                 });
                                 listings_for_city += 1;
                             } else {
@@ -897,6 +929,27 @@ mod tests {
         assert!(image_url.unwrap().contains("leboncoin.fr"));
     }
 
+    #[test]
+    fn test_extract_coordinates_from_page_state() {
+        let html = r#"
+            <html>
+                <body>
+                    <article data-qa-id="aditem"><a href="/colocations/2456789123.htm"></a></article>
+                    <script id="__NEXT_DATA__" type="application/json">
+                        {"props":{"pageProps":{"searchData":{"ads":[
+                            {"list_id":2456789123,"subject":"Colocation","location":{"city":"Rennes","lat":48.11198,"lng":-1.67429}},
+                            {"list_id":2456789124,"location":{"city":"Rennes"}}
+                        ]}}}}
+                    </script>
+                </body>
+            </html>
+        "#;
+
+        let coordinates = LeboncoinScraper::extract_coordinates(&Html::parse_document(html));
+        assert_eq!(coordinates.len(), 1);
+        assert_eq!(coordinates["2456789123"], Coordinates { latitude: 48.11198, longitude: -1.67429 });
+    }
+
     #[test]
     fn test_parse_real_leboncoin_listing_with_high_price() {
         // Test parsing of listing with thousands separator
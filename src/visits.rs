@@ -0,0 +1,107 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+/// A scheduled visit of a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub scheduled_at: DateTime<Utc>,
+    /// Discord user ids pinged by the reminders
+    pub attendees: Vec<u64>,
+    pub created_by: u64,
+    pub reminded_day_before: bool,
+    pub reminded_hour_before: bool,
+    pub rating_requested: bool,
+    /// Post-visit rating, from 1 to 5
+    pub rating: Option<u8>,
+}
+
+/// Messages posted by the bot around a visit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisitReminder {
+    DayBefore,
+    HourBefore,
+    /// Ask attendees to rate the visit once it is over
+    RatingRequest,
+}
+
+/// Visits are assumed to be over this long after they started
+const VISIT_DURATION_HOURS: i64 = 1;
+
+impl Visit {
+    /// The reminder to post now, if any
+    /// A reminder whose window was missed (e.g. bot offline) is skipped rather than sent late
+    pub fn due_reminder(&self, now: DateTime<Utc>) -> Option<VisitReminder> {
+        let until_visit = self.scheduled_at.signed_duration_since(now);
+
+        if until_visit <= -Duration::hours(VISIT_DURATION_HOURS) {
+            (!self.rating_requested).then_some(VisitReminder::RatingRequest)
+        } else if until_visit <= Duration::zero() {
+            None
+        } else if until_visit <= Duration::hours(1) {
+            (!self.reminded_hour_before).then_some(VisitReminder::HourBefore)
+        } else if until_visit <= Duration::hours(24) {
+            (!self.reminded_day_before).then_some(VisitReminder::DayBefore)
+        } else {
+            None
+        }
+    }
+
+    /// Mentions of the attendees, e.g. "<@1> <@2>"
+    pub fn mentions(&self) -> String {
+        self.attendees.iter().map(|id| format!("<@{}>", id)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Store attendees as a comma-separated list of ids
+pub fn format_attendees(attendees: &[u64]) -> String {
+    attendees.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+pub fn parse_attendees(attendees: &str) -> Vec<u64> {
+    attendees.split(',').filter_map(|id| id.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(now: DateTime<Utc>, in_minutes: i64) -> Visit {
+        Visit {
+            id: 1,
+            uuid: Uuid::new_v4(),
+            scheduled_at: now + Duration::minutes(in_minutes),
+            attendees: vec![1, 2],
+            created_by: 1,
+            reminded_day_before: false,
+            reminded_hour_before: false,
+            rating_requested: false,
+            rating: None,
+        }
+    }
+
+    #[test]
+    fn test_due_reminders() {
+        let now = Utc::now();
+        assert_eq!(visit(now, 48 * 60).due_reminder(now), None);
+        assert_eq!(visit(now, 20 * 60).due_reminder(now), Some(VisitReminder::DayBefore));
+        assert_eq!(Visit { reminded_day_before: true, ..visit(now, 20 * 60) }.due_reminder(now), None);
+
+        // Missed the day-before window: only the hour-before reminder is sent
+        assert_eq!(visit(now, 50).due_reminder(now), Some(VisitReminder::HourBefore));
+        assert_eq!(Visit { reminded_hour_before: true, ..visit(now, 50) }.due_reminder(now), None);
+
+        // During the visit, then once it is over
+        assert_eq!(visit(now, -30).due_reminder(now), None);
+        assert_eq!(visit(now, -120).due_reminder(now), Some(VisitReminder::RatingRequest));
+        assert_eq!(Visit { rating_requested: true, ..visit(now, -120) }.due_reminder(now), None);
+    }
+
+    #[test]
+    fn test_attendees_round_trip() {
+        assert_eq!(parse_attendees(&format_attendees(&[12, 34])), vec![12, 34]);
+        assert!(parse_attendees("").is_empty());
+        assert_eq!(visit(Utc::now(), 60).mentions(), "<@1> <@2>");
+    }
+}
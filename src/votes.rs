@@ -0,0 +1,164 @@
+use crate::config::VotingConfig;
+
+/// A member's opinion on a listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vote {
+    Yes,
+    No,
+    Maybe,
+}
+
+impl Vote {
+    pub const ALL: [Vote; 3] = [Vote::Yes, Vote::No, Vote::Maybe];
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Vote::Yes => "👍",
+            Vote::No => "👎",
+            Vote::Maybe => "🤷",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Vote::Yes => "Pour",
+            Vote::No => "Contre",
+            Vote::Maybe => "Sans avis",
+        }
+    }
+
+    /// Value stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Vote::Yes => "yes",
+            Vote::No => "no",
+            Vote::Maybe => "maybe",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Vote::ALL.into_iter().find(|vote| vote.as_str() == s)
+    }
+
+    /// Parse the custom_id of the vote buttons posted before it carried the listing UUID, e.g. "vote_yes"
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        Self::parse(custom_id.strip_prefix("vote_")?)
+    }
+}
+
+/// Discord user ids of the members who voted on a listing, by vote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VoteTally {
+    pub yes: Vec<u64>,
+    pub no: Vec<u64>,
+    pub maybe: Vec<u64>,
+}
+
+/// What a new vote does to the listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoteOutcome {
+    /// Quorum of 👍 reached: move to the interesting channel
+    Promote,
+    /// Veto threshold of 👎 reached: mark as not good
+    Veto,
+    None,
+}
+
+impl VoteTally {
+    pub fn add(&mut self, vote: Vote, user_id: u64) {
+        self.voters_mut(vote).push(user_id);
+    }
+
+    pub fn voters(&self, vote: Vote) -> &[u64] {
+        match vote {
+            Vote::Yes => &self.yes,
+            Vote::No => &self.no,
+            Vote::Maybe => &self.maybe,
+        }
+    }
+
+    fn voters_mut(&mut self, vote: Vote) -> &mut Vec<u64> {
+        match vote {
+            Vote::Yes => &mut self.yes,
+            Vote::No => &mut self.no,
+            Vote::Maybe => &mut self.maybe,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        Vote::ALL.iter().all(|vote| self.voters(*vote).is_empty())
+    }
+
+    /// The veto wins over the quorum, so that a single enthusiastic majority
+    /// can't push a listing someone strongly objects to
+    pub fn outcome(&self, config: &VotingConfig) -> VoteOutcome {
+        if config.veto_threshold > 0 && self.no.len() >= config.veto_threshold {
+            VoteOutcome::Veto
+        } else if config.quorum > 0 && self.yes.len() >= config.quorum {
+            VoteOutcome::Promote
+        } else {
+            VoteOutcome::None
+        }
+    }
+
+    /// Embed field value, e.g. "👍 2 (<@1> <@2>)\n👎 0\n🤷 1 (<@3>)"
+    pub fn format(&self) -> String {
+        Vote::ALL
+            .iter()
+            .map(|vote| {
+                let voters = self.voters(*vote);
+                if voters.is_empty() {
+                    format!("{} 0", vote.emoji())
+                } else {
+                    let mentions: Vec<String> = voters.iter().map(|id| format!("<@{}>", id)).collect();
+                    format!("{} {} ({})", vote.emoji(), voters.len(), mentions.join(" "))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(quorum: usize, veto_threshold: usize) -> VotingConfig {
+        VotingConfig { enabled: true, quorum, veto_threshold }
+    }
+
+    #[test]
+    fn test_custom_id_round_trip() {
+        for vote in Vote::ALL {
+            assert_eq!(Vote::from_custom_id(&format!("vote_{}", vote.as_str())), Some(vote));
+        }
+        assert_eq!(Vote::from_custom_id("interesting_listing"), None);
+        assert_eq!(Vote::from_custom_id("vote_perhaps"), None);
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut tally = VoteTally::default();
+        tally.add(Vote::Yes, 1);
+        tally.add(Vote::Maybe, 2);
+        assert_eq!(tally.outcome(&config(2, 2)), VoteOutcome::None);
+
+        tally.add(Vote::Yes, 3);
+        assert_eq!(tally.outcome(&config(2, 2)), VoteOutcome::Promote);
+
+        tally.add(Vote::No, 4);
+        assert_eq!(tally.outcome(&config(2, 1)), VoteOutcome::Veto);
+        // 0 disables the veto
+        assert_eq!(tally.outcome(&config(2, 0)), VoteOutcome::Promote);
+    }
+
+    #[test]
+    fn test_format() {
+        let mut tally = VoteTally::default();
+        assert!(tally.is_empty());
+        tally.add(Vote::Yes, 1);
+        tally.add(Vote::Yes, 2);
+        tally.add(Vote::Maybe, 3);
+        assert_eq!(tally.format(), "👍 2 (<@1> <@2>)\n👎 0\n🤷 1 (<@3>)");
+    }
+}